@@ -0,0 +1,88 @@
+//! 用训练环境做表格 Q-learning 的小例子
+//!
+//! 用法：`cargo run --release --example q_learning -- [局数]`
+
+use bracket_lib::prelude::RandomNumberGenerator;
+use flappy::difficulty::Difficulty;
+use flappy::gym::{Env, EnvAction, Observation};
+
+/// 只用扇动、下冲和不动三个动作
+const ACTIONS: [EnvAction; 3] = [EnvAction::Idle, EnvAction::Flap, EnvAction::Dive];
+/// 每局最多推进的逻辑帧数（约五分钟）
+const MAX_TICKS: usize = 60 * 60 * 5;
+
+const LEARNING_RATE: f32 = 0.1;
+const DISCOUNT: f32 = 0.99;
+const EPSILON_START: f32 = 0.1;
+const EPSILON_END: f32 = 0.001;
+
+/// 空隙中点相对龙的距离 -10..=10、速度 4 档、离障碍物远近 3 档
+const GAP_BUCKETS: usize = 21;
+const VELOCITY_BUCKETS: usize = 4;
+const DISTANCE_BUCKETS: usize = 3;
+const STATES: usize = GAP_BUCKETS * VELOCITY_BUCKETS * DISTANCE_BUCKETS;
+
+/// 把连续的状态离散成表格的一行
+fn state_index(observation: &Observation) -> usize {
+    let gap_center = (observation.gap_top_dy + observation.gap_bottom_dy) / 2.0;
+    let gap = (gap_center.round() as i32).clamp(-10, 10) + 10;
+    let velocity = match observation.velocity {
+        v if v < -1.0 => 0,
+        v if v < 0.0 => 1,
+        v if v < 1.0 => 2,
+        _ => 3,
+    };
+    let distance = match observation.obstacle_dx {
+        dx if dx < 10.0 => 0,
+        dx if dx < 30.0 => 1,
+        _ => 2,
+    };
+    (gap as usize * VELOCITY_BUCKETS + velocity) * DISTANCE_BUCKETS + distance
+}
+
+fn best_action(q: &[[f32; 3]], state: usize) -> usize {
+    (0..ACTIONS.len())
+        .max_by(|&a, &b| q[state][a].total_cmp(&q[state][b]))
+        .unwrap_or(0)
+}
+
+fn main() {
+    let episodes: usize = std::env::args().nth(1).and_then(|arg| arg.parse().ok()).unwrap_or(500);
+    let mut env = Env::new(Difficulty::Normal);
+    let mut rng = RandomNumberGenerator::seeded(42);
+    let mut q = vec![[0.0f32; 3]; STATES];
+    let mut scores = Vec::new();
+
+    for episode in 0..episodes {
+        let epsilon = EPSILON_START + (EPSILON_END - EPSILON_START) * episode as f32 / episodes as f32;
+        let mut state = state_index(&env.reset(episode as u64));
+        for _ in 0..MAX_TICKS {
+            let action = if rng.rand::<f32>() < epsilon {
+                rng.range(0, ACTIONS.len())
+            } else {
+                best_action(&q, state)
+            };
+            let (observation, reward, done) = env.step(ACTIONS[action]);
+            let next = state_index(&observation);
+            let future = if done { 0.0 } else { q[next][best_action(&q, next)] };
+            q[state][action] += LEARNING_RATE * (reward + DISCOUNT * future - q[state][action]);
+            state = next;
+            if done {
+                break;
+            }
+        }
+        scores.push(env.game().dragons[0].score);
+
+        if (episode + 1) % 50 == 0 {
+            let recent = &scores[scores.len() - 50..];
+            let average = recent.iter().sum::<i32>() as f32 / recent.len() as f32;
+            println!(
+                "episodes {:>5}  average score {:>6.2}  best {:>4}  epsilon {:.3}",
+                episode + 1,
+                average,
+                recent.iter().max().unwrap_or(&0),
+                epsilon
+            );
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::difficulty::Difficulty;
+use crate::game::{Game, GameEvent};
+use crate::obstacle::ObstacleType;
+use crate::powerup::PowerUpType;
+use crate::storage::{unix_now, JsonStore};
+use crate::TICK_DURATION;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// 成就文件名
+const ACHIEVEMENTS_FILE: &str = "achievements.json";
+/// 低空飞行成就的高度上限（行号小于它即视为飞得太高）
+const LOW_FLYER_ROW: i32 = 10;
+/// 低空飞行需要坚持的时间（毫秒）
+const LOW_FLYER_MS: f32 = 60_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Achievement {
+    FirstFlight,
+    RotatingTen,
+    InsaneThirty,
+    Collector,
+    LowFlyer,
+    ComboTwenty,
+    ShieldSaved,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 7] = [
+        Achievement::FirstFlight,
+        Achievement::RotatingTen,
+        Achievement::InsaneThirty,
+        Achievement::Collector,
+        Achievement::LowFlyer,
+        Achievement::ComboTwenty,
+        Achievement::ShieldSaved,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstFlight => "First Flight",
+            Achievement::RotatingTen => "Dizzy Dragon",
+            Achievement::InsaneThirty => "Certified Insane",
+            Achievement::Collector => "Collector",
+            Achievement::LowFlyer => "Low Flyer",
+            Achievement::ComboTwenty => "Combo Master",
+            Achievement::ShieldSaved => "Saved by the Shield",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::FirstFlight => "Finish your first run",
+            Achievement::RotatingTen => "Pass 10 Rotating obstacles",
+            Achievement::InsaneThirty => "Finish Insane with 30+ points",
+            Achievement::Collector => "Collect all four power-up types in one run",
+            Achievement::LowFlyer => "Survive 60s without flying above row 10",
+            Achievement::ComboTwenty => "Reach a combo of 20",
+            Achievement::ShieldSaved => "Let a shield absorb a collision",
+        }
+    }
+}
+
+/// 持久化的成就进度
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AchievementProgress {
+    /// 已解锁的成就及解锁时间（Unix 秒）
+    pub unlocked: HashMap<Achievement, u64>,
+    /// 累计通过的旋转障碍物
+    pub rotating_passed: u64,
+}
+
+impl AchievementProgress {
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains_key(&achievement)
+    }
+
+    /// 合并对局中还没保存的成就和计数
+    pub fn merge(&mut self, run: &mut AchievementTracker) {
+        self.rotating_passed += std::mem::take(&mut run.unsaved_rotating);
+        for achievement in run.unsaved.drain(..) {
+            self.unlocked.entry(achievement).or_insert_with(unix_now);
+        }
+    }
+}
+
+pub type AchievementStore = JsonStore<AchievementProgress>;
+
+impl AchievementStore {
+    /// 打开 `dir` 目录下的文件
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(ACHIEVEMENTS_FILE))
+    }
+}
+
+/// 在一局游戏中根据事件判断成就
+///
+/// 保存对局时只序列化本局的进度，已解锁的成就和累计计数在继续时从存档重新读取。
+#[derive(Serialize, Deserialize)]
+pub struct AchievementTracker {
+    /// 已经解锁的成就，不会重复提示
+    #[serde(skip)]
+    unlocked: HashSet<Achievement>,
+    #[serde(skip)]
+    rotating_total: u64,
+    collected: HashSet<PowerUpType>,
+    low_flight_ms: f32,
+    // 还没写入存档的部分
+    #[serde(skip)]
+    unsaved: Vec<Achievement>,
+    #[serde(skip)]
+    unsaved_rotating: u64,
+}
+
+impl AchievementTracker {
+    pub fn new(progress: &AchievementProgress) -> Self {
+        AchievementTracker {
+            unlocked: progress.unlocked.keys().copied().collect(),
+            rotating_total: progress.rotating_passed,
+            collected: HashSet::new(),
+            low_flight_ms: 0.0,
+            unsaved: Vec::new(),
+            unsaved_rotating: 0,
+        }
+    }
+
+    /// 继续保存的对局时，同步最新的成就存档
+    pub fn sync(&mut self, progress: &AchievementProgress) {
+        self.unlocked = progress.unlocked.keys().copied().collect();
+        self.rotating_total = progress.rotating_passed;
+    }
+
+    /// 是否有需要写入存档的进度
+    pub fn has_unsaved(&self) -> bool {
+        !self.unsaved.is_empty() || self.unsaved_rotating > 0
+    }
+
+    /// 每个逻辑帧调用一次，返回本帧新解锁的成就
+    pub fn observe(&mut self, game: &Game, events: &[GameEvent]) -> Vec<Achievement> {
+        let before = self.unsaved.len();
+
+        for event in events {
+            match event {
+                GameEvent::ObstaclePassed(ObstacleType::Rotating) => {
+                    self.rotating_total += 1;
+                    self.unsaved_rotating += 1;
+                    if self.rotating_total >= 10 {
+                        self.unlock(Achievement::RotatingTen);
+                    }
+                }
+                GameEvent::PowerUpCollected(power_type) => {
+                    self.collected.insert(power_type.clone());
+                    if self.collected.len() == PowerUpType::ALL.len() {
+                        self.unlock(Achievement::Collector);
+                    }
+                }
+                GameEvent::ShieldConsumed => self.unlock(Achievement::ShieldSaved),
+                // 掉命后低空飞行重新计时
+                GameEvent::LifeLost(_) => self.low_flight_ms = 0.0,
+                _ => {}
+            }
+        }
+
+        // 成就只属于第一名玩家（当前选中的玩家档案）
+        let dragon = &game.dragons[0];
+        if dragon.combo_count >= 20 {
+            self.unlock(Achievement::ComboTwenty);
+        }
+
+        // 飞到第10行以上就重新计时
+        if dragon.player.y < LOW_FLYER_ROW {
+            self.low_flight_ms = 0.0;
+        } else {
+            self.low_flight_ms += TICK_DURATION;
+            if self.low_flight_ms >= LOW_FLYER_MS {
+                self.unlock(Achievement::LowFlyer);
+            }
+        }
+
+        if game.is_over() {
+            self.unlock(Achievement::FirstFlight);
+            if game.difficulty == Difficulty::Insane && dragon.score >= 30 {
+                self.unlock(Achievement::InsaneThirty);
+            }
+        }
+
+        self.unsaved[before..].to_vec()
+    }
+
+    fn unlock(&mut self, achievement: Achievement) {
+        if self.unlocked.insert(achievement) {
+            self.unsaved.push(achievement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::DeathCause;
+
+    /// 第一条龙停在低空飞行范围内的对局
+    fn game() -> Game {
+        let mut game = Game::new(Difficulty::Normal, 1);
+        game.dragons[0].player.y = 20;
+        game
+    }
+
+    #[test]
+    fn rotating_obstacles_count_across_runs() {
+        let game = game();
+        let progress = AchievementProgress { rotating_passed: 8, ..Default::default() };
+        let mut tracker = AchievementTracker::new(&progress);
+        let passed = [GameEvent::ObstaclePassed(ObstacleType::Rotating)];
+        assert!(tracker.observe(&game, &passed).is_empty());
+        assert!(tracker.observe(&game, &[GameEvent::ObstaclePassed(ObstacleType::Static)]).is_empty());
+        assert_eq!(tracker.observe(&game, &passed), vec![Achievement::RotatingTen]);
+        assert!(tracker.observe(&game, &passed).is_empty());
+
+        let mut progress = progress;
+        progress.merge(&mut tracker);
+        assert_eq!(progress.rotating_passed, 11);
+        assert!(progress.is_unlocked(Achievement::RotatingTen));
+        assert!(!tracker.has_unsaved());
+    }
+
+    #[test]
+    fn collector_needs_every_power_up_in_one_run() {
+        let game = game();
+        let mut tracker = AchievementTracker::new(&AchievementProgress::default());
+        let collect = |power_type: &PowerUpType| GameEvent::PowerUpCollected(power_type.clone());
+        let (last, rest) = PowerUpType::ALL.split_last().unwrap();
+        let events: Vec<GameEvent> = rest.iter().chain(rest).map(collect).collect();
+        assert!(tracker.observe(&game, &events).is_empty());
+        assert_eq!(tracker.observe(&game, &[collect(last)]), vec![Achievement::Collector]);
+        assert!(tracker.observe(&game, &[collect(last)]).is_empty());
+    }
+
+    #[test]
+    fn achievements_unlock_only_once() {
+        let mut game = game();
+        let mut tracker = AchievementTracker::new(&AchievementProgress::default());
+        game.dragons[0].combo_count = 20;
+        let events = [GameEvent::ShieldConsumed, GameEvent::ShieldConsumed];
+        assert_eq!(tracker.observe(&game, &events), vec![Achievement::ShieldSaved, Achievement::ComboTwenty]);
+        assert!(tracker.observe(&game, &events).is_empty());
+
+        // 存档里已经解锁的成就不再提示
+        let mut progress = AchievementProgress::default();
+        progress.merge(&mut tracker);
+        let mut tracker = AchievementTracker::new(&progress);
+        assert!(tracker.observe(&game, &events).is_empty());
+        assert!(!tracker.has_unsaved());
+    }
+
+    #[test]
+    fn low_flight_restarts_after_climbing_or_losing_a_life() {
+        let mut game = game();
+        let mut tracker = AchievementTracker::new(&AchievementProgress::default());
+        // 和计时一样按帧累加，避免浮点误差差一帧
+        let mut ticks = 0;
+        let mut ms = 0.0;
+        while ms < LOW_FLYER_MS {
+            ms += TICK_DURATION;
+            ticks += 1;
+        }
+        for _ in 1..ticks {
+            assert!(tracker.observe(&game, &[]).is_empty());
+        }
+        // 飞高一帧或者掉一条命都要重新计时
+        game.dragons[0].player.y = LOW_FLYER_ROW - 1;
+        tracker.observe(&game, &[]);
+        game.dragons[0].player.y = LOW_FLYER_ROW;
+        for _ in 1..ticks {
+            assert!(tracker.observe(&game, &[]).is_empty());
+        }
+        // 掉命的这一帧已经算作重新计时的第一帧
+        tracker.observe(&game, &[GameEvent::LifeLost(DeathCause::Floor)]);
+        for _ in 2..ticks {
+            assert!(tracker.observe(&game, &[]).is_empty());
+        }
+        assert_eq!(tracker.observe(&game, &[]), vec![Achievement::LowFlyer]);
+    }
+
+    #[test]
+    fn finishing_a_run_unlocks_first_flight_and_insane_thirty() {
+        let mut game = game();
+        game.dragons[0].lives = 0;
+        game.dragons[0].score = 30;
+        let mut tracker = AchievementTracker::new(&AchievementProgress::default());
+        assert_eq!(tracker.observe(&game, &[]), vec![Achievement::FirstFlight]);
+
+        let mut insane = Game::new(Difficulty::Insane, 1);
+        insane.dragons[0].player.y = 20;
+        insane.dragons[0].lives = 0;
+        insane.dragons[0].score = 29;
+        let mut tracker = AchievementTracker::new(&AchievementProgress::default());
+        assert_eq!(tracker.observe(&insane, &[]), vec![Achievement::FirstFlight]);
+        insane.dragons[0].score = 30;
+        assert_eq!(tracker.observe(&insane, &[]), vec![Achievement::InsaneThirty]);
+    }
+}
@@ -28,7 +28,7 @@ impl AudioSystem {
             while !stop_flag.load(Ordering::Relaxed) {
                 let status = if cfg!(target_os = "windows") {
                     Command::new("powershell")
-                        .args(&[
+                        .args([
                             "-c",
                             &format!("(New-Object Media.SoundPlayer '{}').PlaySync()", path)
                         ])
@@ -43,7 +43,7 @@ impl AudioSystem {
                         .status()
                 } else {
                     Command::new("mpg123")
-                        .args(&["-q", "--loop", "-1", &path])
+                        .args(["-q", "--loop", "-1", &path])
                         .status()
                 };
                 
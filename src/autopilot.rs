@@ -0,0 +1,153 @@
+use bracket_lib::prelude::*;
+use flappy::bot::Bot;
+use crate::State;
+
+/// 主菜单闲置多久后开始演示（毫秒）
+const ATTRACT_IDLE_MS: f32 = 20000.0;
+
+/// 由机器人操作一号玩家的对局
+pub struct Autopilot {
+    pub bot: Bot,
+    /// 主菜单闲置后自动开始的演示，任意输入回到主菜单；否则是 `--bot` 启动的长时间测试
+    pub attract: bool,
+}
+
+/// 主菜单的闲置计时
+#[derive(Default)]
+pub struct Idle {
+    ms: f32,
+    /// 上一帧的鼠标位置，刚切换界面时还不知道
+    mouse: Option<(i32, i32)>,
+}
+
+impl Idle {
+    /// 这一帧有没有按键、点击或移动鼠标
+    fn input(&mut self, ctx: &BTerm) -> bool {
+        let mouse = ctx.mouse_pos();
+        let moved = self.mouse.is_some_and(|last| last != mouse);
+        self.mouse = Some(mouse);
+        moved || ctx.key.is_some() || INPUT.lock().is_mouse_button_pressed(0)
+    }
+}
+
+impl State {
+    pub(crate) fn start_autopilot(&mut self, attract: bool) {
+        self.start_game(1);
+        self.autopilot = Some(Autopilot {
+            bot: Bot::new(0),
+            attract,
+        });
+    }
+
+    /// 主菜单每帧调用，闲置足够久后开始演示
+    pub(crate) fn track_idle(&mut self, ctx: &BTerm) {
+        if self.idle.input(ctx) {
+            self.idle.ms = 0.0;
+            return;
+        }
+        self.idle.ms += ctx.frame_time_ms;
+        if self.idle.ms >= ATTRACT_IDLE_MS {
+            self.start_autopilot(true);
+        }
+    }
+
+    /// 演示中有任何输入时回到主菜单，返回是否已经离开
+    pub(crate) fn attract_interrupted(&mut self, ctx: &BTerm) -> bool {
+        let attract = self.autopilot.as_ref().is_some_and(|autopilot| autopilot.attract);
+        if attract && self.idle.input(ctx) {
+            self.quit_to_menu();
+            return true;
+        }
+        false
+    }
+
+    /// 机器人的一局结束后接着开始下一局
+    pub(crate) fn autopilot_finished(&mut self) {
+        let Some(attract) = self.autopilot.as_ref().map(|autopilot| autopilot.attract) else {
+            return;
+        };
+        if !attract {
+            let dragon = &self.game.dragons[0];
+            println!(
+                "Bot run finished: {} points, best combo {}, {:.0}s ({:?}, seed {})",
+                dragon.score,
+                dragon.best_combo,
+                self.game.elapsed_ms() / 1000.0,
+                self.game.difficulty,
+                self.game.seed()
+            );
+        }
+        self.start_autopilot(attract);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioSystem;
+    use crate::GameMode;
+    use flappy::achievements::{AchievementProgress, AchievementTracker};
+    use flappy::difficulty::Difficulty;
+    use flappy::game::Game;
+    use flappy::profile::ProfileList;
+    use flappy::replay::Replay;
+    use flappy::savegame::SavedRun;
+    use flappy::storage::temp_dir;
+
+    /// 数据目录指向临时目录，不碰玩家真实的存档
+    fn headless() -> State {
+        State::with_profiles(ProfileList::with_default(&temp_dir("autopilot")), AudioSystem::new())
+    }
+
+    fn has_save_entry(state: &State) -> bool {
+        state.pause_entries().iter().any(|&(key, _)| key == VirtualKeyCode::S)
+    }
+
+    #[test]
+    fn bot_runs_cannot_be_saved() {
+        let mut state = headless();
+        state.start_game(1);
+        assert!(has_save_entry(&state));
+        for attract in [false, true] {
+            state.start_autopilot(attract);
+            assert!(!has_save_entry(&state));
+        }
+    }
+
+    #[test]
+    fn interrupted_demo_clears_autopilot() {
+        let mut state = headless();
+        state.start_autopilot(true);
+        state.quit_to_menu();
+        assert!(state.autopilot.is_none());
+        assert!(state.mode == GameMode::Menu);
+    }
+
+    #[test]
+    fn leaving_from_pause_clears_autopilot() {
+        let mut state = headless();
+        state.start_autopilot(false);
+        state.mode = GameMode::Paused;
+        state.pause_to_menu();
+        assert!(state.autopilot.is_none());
+        assert!(state.mode == GameMode::Menu);
+    }
+
+    #[test]
+    fn continued_run_is_played_by_the_player() {
+        let mut state = headless();
+        let run = SavedRun {
+            game: Game::new(Difficulty::Easy, 7),
+            replay: Replay::new(7, Difficulty::Easy),
+            run_stats: Default::default(),
+            achievements: AchievementTracker::new(&AchievementProgress::default()),
+        };
+        state.profile().saved_run().save(run).unwrap();
+
+        state.start_autopilot(true);
+        state.continue_run();
+        assert!(state.autopilot.is_none());
+        assert!(state.mode == GameMode::Paused);
+        assert_eq!(state.game.seed(), 7);
+    }
+}
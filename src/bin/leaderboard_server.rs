@@ -0,0 +1,236 @@
+//! 本地排行榜服务，用于离线开发和测试在线排行榜
+//!
+//! 用法：`cargo run --bin leaderboard_server -- [--addr 127.0.0.1:7878] [--data leaderboard.json]`
+
+use flappy::difficulty::Difficulty;
+use flappy::http::{write_response, Request};
+use flappy::leaderboard::{LeaderboardEntry, RunSubmission, DEFAULT_ADDR};
+use flappy::scores::is_valid_run_id;
+use flappy::storage::{data_dir, JsonStore};
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// 单次查询最多返回的条数
+const MAX_LIMIT: usize = 100;
+
+/// 服务端保存的全部成绩，每次变更后写回磁盘
+///
+/// 和玩家数据一样原子写入；文件损坏时备份后从空排行榜开始。
+struct Board {
+    runs: Vec<RunSubmission>,
+    store: JsonStore<Vec<RunSubmission>>,
+}
+
+impl Board {
+    fn load(path: PathBuf) -> io::Result<Board> {
+        let store = JsonStore::new(path);
+        Ok(Board { runs: store.load()?, store })
+    }
+
+    fn save(&self) -> io::Result<()> {
+        self.store.update(|runs| runs.clone_from(&self.runs))
+    }
+
+    fn top(&self, difficulty: Option<&Difficulty>, limit: usize) -> Vec<LeaderboardEntry> {
+        let mut runs: Vec<LeaderboardEntry> = self
+            .runs
+            .iter()
+            .filter(|run| difficulty.is_none_or(|d| &run.difficulty == d))
+            .map(RunSubmission::entry)
+            .collect();
+        runs.sort_by_key(|run| std::cmp::Reverse(run.score));
+        runs.truncate(limit);
+        runs
+    }
+}
+
+fn handle(request: &Request, board: &Mutex<Board>) -> (u16, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/runs") => submit(request, board),
+        ("GET", "/top") => {
+            let limit = request
+                .query_param("limit")
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(10)
+                .min(MAX_LIMIT);
+            let difficulty = match request.query_param("difficulty").map(str::parse::<Difficulty>) {
+                Some(Ok(difficulty)) => Some(difficulty),
+                Some(Err(())) => return (400, error_body("unknown difficulty")),
+                None => None,
+            };
+            let runs = board.lock().unwrap().top(difficulty.as_ref(), limit);
+            (200, serde_json::to_string(&runs).unwrap_or_else(|_| "[]".to_string()))
+        }
+        _ => (404, error_body("not found")),
+    }
+}
+
+fn submit(request: &Request, board: &Mutex<Board>) -> (u16, String) {
+    let run: RunSubmission = match serde_json::from_str(&request.body) {
+        Ok(run) => run,
+        Err(err) => return (400, error_body(&err.to_string())),
+    };
+    if let Err(reason) = verify(&run) {
+        println!("Rejected run {}: {}", run.run_id, reason);
+        return (422, error_body(&reason));
+    }
+
+    let mut board = board.lock().unwrap();
+    // 同一局重复提交时直接返回成功
+    if board.runs.iter().any(|existing| existing.run_id == run.run_id) {
+        return (200, "{}".to_string());
+    }
+    println!("Accepted run {} ({} points, {:?})", run.run_id, run.score, run.difficulty);
+    board.runs.push(run);
+    if let Err(err) = board.save() {
+        eprintln!("Failed to save leaderboard: {}", err);
+        return (500, error_body("failed to save"));
+    }
+    (201, "{}".to_string())
+}
+
+/// 用无界面的游戏核心重新模拟录像，确认声明的难度、种子和分数都是真实的
+fn verify(run: &RunSubmission) -> Result<(), String> {
+    if !is_valid_run_id(&run.run_id) {
+        return Err("invalid run id".to_string());
+    }
+    if run.difficulty != run.replay.difficulty || run.seed != run.replay.seed {
+        return Err("difficulty or seed does not match the replay".to_string());
+    }
+    if run.replay_hash != run.replay.hash() {
+        return Err("replay hash mismatch".to_string());
+    }
+    run.replay.verify(run.score).map_err(|err| err.to_string())
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn serve(stream: TcpStream, board: &Mutex<Board>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let (status, body) = match Request::read_from(&stream) {
+        Ok(request) => handle(&request, board),
+        Err(err) => (400, error_body(&err.to_string())),
+    };
+    write_response(&stream, status, &body)
+}
+
+fn main() -> io::Result<()> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut data = data_dir().join("leaderboard.json");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            ("--data", Some(value)) => data = PathBuf::from(value),
+            _ => {
+                eprintln!("Usage: leaderboard_server [--addr <host:port>] [--data <file.json>]");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let board = Arc::new(Mutex::new(Board::load(data)?));
+    let listener = TcpListener::bind(&addr)?;
+    println!("Leaderboard server listening on http://{}", addr);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let board = board.clone();
+                thread::spawn(move || {
+                    if let Err(err) = serve(stream, &board) {
+                        eprintln!("Connection error: {}", err);
+                    }
+                });
+            }
+            Err(err) => eprintln!("Failed to accept connection: {}", err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flappy::bot::Bot;
+    use flappy::game::{Game, TickInput};
+    use flappy::replay::{Replay, MAX_REPLAY_TICKS};
+    use flappy::storage::temp_dir;
+    use std::fs;
+
+    /// 机器人玩一段时间后松手直到游戏结束，按客户端的方式打包成提交
+    fn recorded_submission() -> RunSubmission {
+        let mut replay = Replay::new(3, Difficulty::Hard);
+        let mut game = Game::new(Difficulty::Hard, 3);
+        let bot = Bot::new(0);
+        while !game.is_over() {
+            let input = if replay.ticks() < 1000 { bot.input(&game) } else { TickInput::default() };
+            replay.record(input);
+            game.tick(input);
+        }
+        RunSubmission::new("run-1".to_string(), game.dragons[0].score, replay)
+    }
+
+    #[test]
+    fn board_recovers_from_a_corrupted_file() {
+        let dir = temp_dir("leaderboard-board");
+        let path = dir.join("leaderboard.json");
+        fs::write(&path, "[{").unwrap();
+
+        let mut board = Board::load(path.clone()).unwrap();
+        assert!(board.runs.is_empty());
+        assert_eq!(fs::read_to_string(dir.join("leaderboard.json.bak")).unwrap(), "[{");
+
+        board.runs.push(recorded_submission());
+        board.save().unwrap();
+        assert!(!dir.join("leaderboard.json.tmp").exists());
+        assert_eq!(Board::load(path).unwrap().runs, board.runs);
+    }
+
+    #[test]
+    fn accepts_recorded_run() {
+        let run = recorded_submission();
+        assert!(run.score > 0);
+        assert_eq!(verify(&run), Ok(()));
+    }
+
+    #[test]
+    fn rejects_tampered_runs() {
+        let run = recorded_submission();
+        let tamper = |f: fn(&mut RunSubmission)| {
+            let mut run = run.clone();
+            f(&mut run);
+            verify(&run).unwrap_err()
+        };
+
+        assert_eq!(tamper(|run| run.run_id.clear()), "invalid run id");
+        // 和导入分数时的规则一致
+        assert_eq!(tamper(|run| run.run_id.push(':')), "invalid run id");
+        assert_eq!(tamper(|run| run.run_id = "a,b".to_string()), "invalid run id");
+        assert_eq!(tamper(|run| run.run_id = "x".repeat(65)), "invalid run id");
+        assert_eq!(
+            tamper(|run| run.difficulty = Difficulty::Easy),
+            "difficulty or seed does not match the replay"
+        );
+        assert_eq!(tamper(|run| run.seed += 1), "difficulty or seed does not match the replay");
+        assert_eq!(tamper(|run| run.replay.inputs[0] ^= 1), "replay hash mismatch");
+        assert!(tamper(|run| run.score += 1).starts_with("claimed score"));
+
+        // 重新计算指纹也骗不过重新模拟
+        let rehashed = |f: fn(&mut Replay)| {
+            let mut run = run.clone();
+            f(&mut run.replay);
+            run.replay_hash = run.replay.hash();
+            verify(&run).unwrap_err()
+        };
+        assert!(rehashed(|replay| replay.inputs.push(0)).starts_with("game ended at tick"));
+        assert!(rehashed(|replay| replay.inputs.truncate(10)).contains("still running"));
+        assert!(rehashed(|replay| replay.inputs.resize(MAX_REPLAY_TICKS + 1, 0)).starts_with("replay too long"));
+    }
+}
@@ -0,0 +1,126 @@
+//! 联机对战的中继服务，按连接顺序把玩家两两配对并转发双方的消息
+//!
+//! 用法：`cargo run --bin relay_server -- [--addr 127.0.0.1:7879]`
+
+use bracket_lib::prelude::RandomNumberGenerator;
+use flappy::difficulty::Difficulty;
+use flappy::netplay::{Message, DEFAULT_RELAY_ADDR};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// 已经发送了 `Join` 的连接
+struct Peer {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    difficulty: Difficulty,
+}
+
+/// 读取新连接的 `Join` 消息
+fn handshake(stream: TcpStream) -> io::Result<Peer> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let difficulty = match Message::read_from(&mut reader)? {
+        Some(Message::Join { difficulty }) => difficulty,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "expected a join message")),
+    };
+    stream.set_read_timeout(None)?;
+    stream.set_nodelay(true)?;
+    Ok(Peer {
+        stream,
+        reader,
+        difficulty,
+    })
+}
+
+/// 把 `from` 发来的每一行原样转发给 `to`，任意一方断开时通知另一方
+fn forward(mut from: BufReader<TcpStream>, mut to: TcpStream) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match from.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if to.write_all(line.as_bytes()).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = Message::Left.write_to(&to);
+    let _ = to.shutdown(Shutdown::Both);
+    let _ = from.get_ref().shutdown(Shutdown::Both);
+}
+
+/// 开始一场对局：先到的玩家决定难度，双方使用同一个种子
+fn start_match(host: Peer, guest: Peer) -> Result<(), Peer> {
+    let seed = RandomNumberGenerator::new().next_u64();
+    let start = |player| Message::Start {
+        seed,
+        difficulty: host.difficulty.clone(),
+        player,
+    };
+    // 等待中的玩家可能已经断开，这时让新玩家继续等待
+    if start(0).write_to(&host.stream).is_err() {
+        return Err(guest);
+    }
+    if start(1).write_to(&guest.stream).is_err() {
+        let _ = Message::Left.write_to(&host.stream);
+        return Ok(());
+    }
+    println!("Match started ({:?}, seed {})", host.difficulty, seed);
+
+    let (Ok(host_out), Ok(guest_out)) = (host.stream.try_clone(), guest.stream.try_clone()) else {
+        return Ok(());
+    };
+    thread::spawn(move || forward(host.reader, guest_out));
+    thread::spawn(move || forward(guest.reader, host_out));
+    Ok(())
+}
+
+fn matchmaker(peers: Receiver<Peer>) {
+    let mut waiting: Option<Peer> = None;
+    for peer in peers {
+        waiting = match waiting.take() {
+            None => Some(peer),
+            Some(host) => start_match(host, peer).err(),
+        };
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut addr = DEFAULT_RELAY_ADDR.to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            _ => {
+                eprintln!("Usage: relay_server [--addr <host:port>]");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let listener = TcpListener::bind(&addr)?;
+    println!("Relay server listening on {}", addr);
+
+    let (sender, peers) = mpsc::channel();
+    thread::spawn(move || matchmaker(peers));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let sender = sender.clone();
+                thread::spawn(move || match handshake(stream) {
+                    Ok(peer) => {
+                        let _ = sender.send(peer);
+                    }
+                    Err(err) => eprintln!("Handshake failed: {}", err),
+                });
+            }
+            Err(err) => eprintln!("Failed to accept connection: {}", err),
+        }
+    }
+    Ok(())
+}
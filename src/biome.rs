@@ -0,0 +1,174 @@
+use bracket_lib::prelude::*;
+use crate::hazard::HazardKind;
+use crate::obstacle::ObstacleType;
+use crate::render::Canvas;
+use crate::SCREEN_HEIGHT;
+use serde::{Deserialize, Serialize};
+
+/// 无尽模式每得到这么多分进入下一个生物群系，最后一个之后回到第一个
+pub const BIOME_POINTS: i32 = 25;
+/// 进入新生物群系时过渡效果持续的逻辑帧数
+pub const TRANSITION_TICKS: i32 = 90;
+
+/// 无尽模式依次经过的生物群系，各有自己的背景、障碍物和危险
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Biome {
+    #[default]
+    Cave,
+    Sky,
+    Storm,
+    Space,
+}
+
+/// 背景的配色和装饰
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    /// 背景从上到下的渐变
+    pub top: (u8, u8, u8),
+    pub bottom: (u8, u8, u8),
+    /// 背景上散落的装饰符号
+    pub decoration: char,
+    pub decoration_color: (u8, u8, u8),
+    /// 大约每多少格出现一个装饰
+    pub density: u32,
+    /// 装饰每隔几个逻辑帧向左移动一格，数值越大越远
+    pub drift: u64,
+    /// 装饰每隔几个逻辑帧向下移动一格，0 表示不下落
+    pub fall: u64,
+    /// 过渡时的标题颜色
+    pub accent: (u8, u8, u8),
+}
+
+impl Biome {
+    pub const ALL: [Biome; 4] = [Biome::Cave, Biome::Sky, Biome::Storm, Biome::Space];
+
+    /// 最高分为 `score` 时所在的生物群系
+    pub fn at_score(score: i32) -> Biome {
+        Biome::ALL[(score.max(0) / BIOME_POINTS) as usize % Biome::ALL.len()]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Biome::Cave => "Cave",
+            Biome::Sky => "Sky",
+            Biome::Storm => "Storm",
+            Biome::Space => "Space",
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        match self {
+            Biome::Cave => Palette {
+                top: (20, 12, 8),
+                bottom: (55, 38, 26),
+                decoration: '·',
+                decoration_color: (110, 85, 60),
+                density: 40,
+                drift: 4,
+                fall: 0,
+                accent: (230, 170, 90),
+            },
+            Biome::Sky => Palette {
+                top: (60, 120, 190),
+                bottom: (160, 205, 240),
+                decoration: '~',
+                decoration_color: (235, 240, 250),
+                density: 60,
+                drift: 2,
+                fall: 0,
+                accent: (140, 200, 255),
+            },
+            Biome::Storm => Palette {
+                top: (22, 22, 36),
+                bottom: (60, 60, 80),
+                decoration: '/',
+                decoration_color: (110, 130, 180),
+                density: 14,
+                drift: 1,
+                fall: 1,
+                accent: (250, 230, 100),
+            },
+            Biome::Space => Palette {
+                top: (0, 0, 8),
+                bottom: (12, 6, 32),
+                decoration: '*',
+                decoration_color: (200, 200, 255),
+                density: 50,
+                drift: 8,
+                fall: 0,
+                accent: (200, 150, 255),
+            },
+        }
+    }
+
+    /// 各种障碍物的权重，顺序和 `ObstacleType::ALL` 一致
+    pub fn obstacle_weights(&self) -> [i32; 7] {
+        match self {
+            Biome::Cave => [45, 10, 10, 10, 0, 15, 10],
+            Biome::Sky => [40, 25, 0, 10, 0, 0, 25],
+            Biome::Storm => [25, 20, 15, 10, 20, 0, 10],
+            Biome::Space => [15, 15, 30, 10, 20, 10, 0],
+        }
+    }
+
+    /// 按权重随机选择障碍物类型
+    pub fn pick_obstacle_type(&self, random: &mut RandomNumberGenerator) -> ObstacleType {
+        let weights = self.obstacle_weights();
+        let mut roll = random.range(0, weights.iter().sum::<i32>());
+        for (obstacle_type, weight) in ObstacleType::ALL.into_iter().zip(weights) {
+            if roll < weight {
+                return obstacle_type;
+            }
+            roll -= weight;
+        }
+        ObstacleType::Static
+    }
+
+    pub fn hazard(&self) -> HazardKind {
+        match self {
+            Biome::Cave => HazardKind::Stalactite,
+            Biome::Sky => HazardKind::Gust,
+            Biome::Storm => HazardKind::Lightning,
+            Biome::Space => HazardKind::Meteor,
+        }
+    }
+
+    /// 两次危险之间最少的逻辑帧数，实际间隔在它和它的 1.5 倍之间
+    pub fn hazard_interval(&self) -> i32 {
+        match self {
+            Biome::Cave => 150,
+            Biome::Sky => 180,
+            Biome::Storm => 200,
+            Biome::Space => 160,
+        }
+    }
+}
+
+impl Palette {
+    fn background(&self, y: i32) -> (u8, u8, u8) {
+        let t = y as f32 / (SCREEN_HEIGHT - 1) as f32;
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+        (mix(self.top.0, self.bottom.0), mix(self.top.1, self.bottom.1), mix(self.top.2, self.bottom.2))
+    }
+
+    /// 画背景的第 `x` 列，装饰随 `ticks` 慢慢移动
+    pub fn render_column(&self, ctx: &mut impl Canvas, x: i32, ticks: u64) {
+        let world_x = x as i64 + (ticks / self.drift) as i64;
+        let fall = ticks.checked_div(self.fall).unwrap_or(0) as i64;
+        for y in 0..SCREEN_HEIGHT {
+            let bg = self.background(y);
+            if noise(world_x, y as i64 - fall).is_multiple_of(self.density) {
+                ctx.set(x, y, self.decoration_color, bg, to_cp437(self.decoration));
+            } else {
+                ctx.set_bg(x, y, bg);
+            }
+        }
+    }
+}
+
+/// 由位置决定的伪随机数，让装饰的分布固定不变
+fn noise(x: i64, y: i64) -> u32 {
+    let mut h = (x.wrapping_mul(374_761_393) ^ y.wrapping_mul(668_265_263)) as u64;
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    (h ^ (h >> 16)) as u32
+}
@@ -0,0 +1,277 @@
+use bracket_lib::prelude::*;
+use crate::player::Player;
+use crate::render::Canvas;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH, TICK_DURATION};
+use serde::{Deserialize, Serialize};
+
+/// 无尽模式每得到这么多分出现一次首领
+pub const BOSS_POINTS: i32 = 40;
+/// 坚持到首领离开后每条还在场的龙得到的奖励分
+pub const BOSS_BONUS: i32 = 10;
+/// 首领战持续的逻辑帧数（从首领就位开始算）
+const FIGHT_TICKS: i32 = 60 * 20;
+/// 每种攻击方式持续的逻辑帧数，之后换下一种
+const PATTERN_TICKS: i32 = 240;
+/// 首领就位时左边缘的横坐标
+const BOSS_X: i32 = SCREEN_WIDTH - 12;
+/// 首领上下移动的范围
+const BOSS_TOP: i32 = 8;
+const BOSS_BOTTOM: i32 = SCREEN_HEIGHT - 12;
+/// 弹幕每隔几个逻辑帧上下偏移一格
+const DRIFT_PERIOD: i32 = 4;
+/// 首领的生命，被火球打中一次减一，打光后提前离开
+const BOSS_HEALTH: i32 = 10;
+/// 被打中后闪烁的逻辑帧数
+const HIT_FLASH_TICKS: i32 = 6;
+/// 弹墙的空隙半高，以及空隙中心离龙最远的格数
+const WALL_GAP_HALF: i32 = 4;
+const WALL_GAP_RANGE: i32 = 8;
+
+const ART: [&str; 4] = [
+    "  __/\\__ ",
+    " <(@  @)=",
+    " /|\\__/|\\",
+    "   V  V  ",
+];
+const WIDTH: i32 = 9;
+const HEIGHT: i32 = ART.len() as i32;
+
+/// 首领发射的弹幕，向左飞行，可能斜着偏移
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Projectile {
+    pub x: i32,
+    pub y: i32,
+    prev_x: i32,
+    /// 每隔 `DRIFT_PERIOD` 个逻辑帧上下移动的格数
+    pub dy: i32,
+    timer: i32,
+}
+
+impl Projectile {
+    fn new(x: i32, y: i32, dy: i32) -> Self {
+        Projectile { x, y, prev_x: x, dy, timer: 0 }
+    }
+
+    fn update(&mut self) {
+        self.prev_x = self.x;
+        self.x -= 1;
+        self.timer += 1;
+        if self.timer % DRIFT_PERIOD == 0 {
+            self.y += self.dy;
+        }
+    }
+
+    /// `ticks` 个逻辑帧后大约所在的高度
+    pub fn y_after(&self, ticks: i32) -> i32 {
+        self.y + self.dy * ((self.timer % DRIFT_PERIOD + ticks) / DRIFT_PERIOD)
+    }
+
+    pub fn hits(&self, player: &Player) -> bool {
+        player.y == self.y && (self.x..=self.prev_x).contains(&player.x)
+    }
+
+    /// 这个逻辑帧扫过的列（含两端）
+    pub fn span(&self) -> (i32, i32) {
+        (self.x, self.prev_x)
+    }
+}
+
+/// 龙撞上了首领的哪一部分；撞上弹幕时是它的序号，被护盾挡下后移除
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossHit {
+    Body,
+    Projectile(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Phase {
+    /// 从屏幕右边飞进来
+    Entering,
+    Fighting,
+    /// 时间到，飞回右边，剩下的弹幕继续飞
+    Leaving,
+}
+
+/// 首领战的敌人：占据多个格子，停在屏幕右边按几种方式轮流发射弹幕
+///
+/// 首领战期间障碍物暂停，坚持到首领离开或者用火球把它打跑都能得到奖励分。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Boss {
+    /// 左上角的位置
+    pub x: i32,
+    pub y: i32,
+    phase: Phase,
+    /// 首领就位后经过的逻辑帧数
+    timer: i32,
+    direction: i32,
+    pub projectiles: Vec<Projectile>,
+    color: (u8, u8, u8),
+    pub health: i32,
+    hit_flash: i32,
+}
+
+impl Boss {
+    pub fn new(color: (u8, u8, u8)) -> Self {
+        Boss {
+            x: SCREEN_WIDTH,
+            y: (BOSS_TOP + BOSS_BOTTOM) / 2,
+            phase: Phase::Entering,
+            timer: 0,
+            direction: 1,
+            projectiles: Vec::new(),
+            color,
+            health: BOSS_HEALTH,
+            hit_flash: 0,
+        }
+    }
+
+    /// 每个逻辑帧调用一次，`target_y` 是瞄准的高度；首领战刚结束的那一帧返回 `true`
+    pub fn update(&mut self, target_y: i32, random: &mut RandomNumberGenerator) -> bool {
+        self.hit_flash = (self.hit_flash - 1).max(0);
+        for projectile in &mut self.projectiles {
+            projectile.update();
+        }
+        self.projectiles.retain(|p| p.x > -2 && (0..SCREEN_HEIGHT).contains(&p.y));
+
+        match self.phase {
+            Phase::Entering => {
+                self.x -= 1;
+                if self.x <= BOSS_X {
+                    self.phase = Phase::Fighting;
+                }
+                false
+            }
+            Phase::Fighting => {
+                self.timer += 1;
+                if self.timer % 6 == 0 {
+                    if !(BOSS_TOP..=BOSS_BOTTOM).contains(&(self.y + self.direction)) {
+                        self.direction = -self.direction;
+                    }
+                    self.y += self.direction;
+                }
+                self.fire(target_y, random);
+                if self.timer >= FIGHT_TICKS {
+                    self.phase = Phase::Leaving;
+                    return true;
+                }
+                false
+            }
+            Phase::Leaving => {
+                self.x += 1;
+                false
+            }
+        }
+    }
+
+    /// 按当前的攻击方式发射：瞄准、散射、留有空隙的弹墙轮流出现
+    fn fire(&mut self, target_y: i32, random: &mut RandomNumberGenerator) {
+        let (mouth_x, mouth_y) = (self.x - 1, self.y + 1);
+        let tick = self.timer % PATTERN_TICKS;
+        match self.timer / PATTERN_TICKS % 3 {
+            0 if tick % 25 == 0 => {
+                let dy = if (target_y - mouth_y).abs() > 3 { (target_y - mouth_y).signum() } else { 0 };
+                self.projectiles.push(Projectile::new(mouth_x, mouth_y, dy));
+            }
+            1 if tick % 45 == 0 => {
+                for dy in -1..=1 {
+                    self.projectiles.push(Projectile::new(mouth_x, mouth_y, dy));
+                }
+            }
+            2 if tick % 70 == 0 => {
+                // 空隙离龙不远，来得及飞过去
+                let gap_y = (target_y + random.range(-WALL_GAP_RANGE, WALL_GAP_RANGE + 1))
+                    .clamp(WALL_GAP_HALF + 2, SCREEN_HEIGHT - WALL_GAP_HALF - 3);
+                for y in (0..SCREEN_HEIGHT).filter(|y| (y - gap_y).abs() > WALL_GAP_HALF) {
+                    self.projectiles.push(Projectile::new(mouth_x, y, 0));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 飞走并且弹幕都离开屏幕后结束
+    pub fn is_finished(&self) -> bool {
+        self.phase == Phase::Leaving && self.x >= SCREEN_WIDTH && self.projectiles.is_empty()
+    }
+
+    /// 碰到首领身体的龙
+    fn touches(&self, player: &Player) -> bool {
+        self.covers(player.x, player.y)
+    }
+
+    /// 格子 `(x, y)` 在首领身体的范围内
+    pub fn covers(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + WIDTH).contains(&x) && (self.y..self.y + HEIGHT).contains(&y)
+    }
+
+    /// `ticks` 个逻辑帧后身体所在的行，首领战中会上下来回移动
+    pub fn rows_after(&self, ticks: i32) -> std::ops::Range<i32> {
+        let (mut y, mut direction) = (self.y, self.direction);
+        if self.phase == Phase::Fighting {
+            for tick in self.timer + 1..=self.timer + ticks {
+                if tick % 6 == 0 {
+                    if !(BOSS_TOP..=BOSS_BOTTOM).contains(&(y + direction)) {
+                        direction = -direction;
+                    }
+                    y += direction;
+                }
+            }
+        }
+        y..y + HEIGHT
+    }
+
+    /// 首领战中才能被火球打中
+    pub fn is_vulnerable(&self) -> bool {
+        self.phase == Phase::Fighting
+    }
+
+    /// 被火球打中一次，生命打光时提前离开并返回 `true`
+    pub fn damage(&mut self) -> bool {
+        self.health -= 1;
+        self.hit_flash = HIT_FLASH_TICKS;
+        if self.health <= 0 {
+            self.phase = Phase::Leaving;
+            return true;
+        }
+        false
+    }
+
+    pub fn hit(&self, player: &Player) -> Option<BossHit> {
+        if self.touches(player) {
+            return Some(BossHit::Body);
+        }
+        self.projectiles.iter().position(|p| p.hits(player)).map(BossHit::Projectile)
+    }
+
+    /// 龙失去生命重新出现时清除弹幕，留出喘息的时间
+    pub fn clear_projectiles(&mut self) {
+        self.projectiles.clear();
+    }
+
+    pub fn render(&self, ctx: &mut impl Canvas) {
+        for (row, line) in ART.iter().enumerate() {
+            for (col, c) in line.chars().enumerate().filter(|(_, c)| *c != ' ') {
+                let x = self.x + col as i32;
+                if (0..SCREEN_WIDTH).contains(&x) {
+                    let color = if self.hit_flash > 0 { WHITE } else { self.color };
+                    ctx.set(x, self.y + row as i32, color, BLACK, to_cp437(c));
+                }
+            }
+        }
+        for projectile in &self.projectiles {
+            let glyph = if projectile.timer % 8 < 4 { 'o' } else { 'O' };
+            ctx.set(projectile.x, projectile.y, ORANGE_RED, BLACK, to_cp437(glyph));
+        }
+
+        let status = match self.phase {
+            Phase::Entering => "BOSS INCOMING!".to_string(),
+            Phase::Fighting => {
+                let remaining = (FIGHT_TICKS - self.timer) as f32 * TICK_DURATION / 1000.0;
+                format!("BOSS - survive {:.1}s  |  HP {}", remaining, self.health)
+            }
+            Phase::Leaving if self.health <= 0 => format!("Boss defeated! +{}", BOSS_BONUS),
+            Phase::Leaving => format!("Boss survived! +{}", BOSS_BONUS),
+        };
+        ctx.print_color((SCREEN_WIDTH - status.len() as i32) / 2, 6, self.color, BLACK, status);
+    }
+}
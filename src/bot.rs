@@ -0,0 +1,160 @@
+use crate::boss::Boss;
+use crate::fireball::FIREBALL_SPEED;
+use crate::game::{Dragon, Game, TickInput};
+use crate::obstacle::ObstacleType;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// 扇动一次大约上升的格数
+const RISE: i32 = 3;
+/// 首领战时提前躲避多少个逻辑帧内飞到的弹幕
+const DODGE_TICKS: i32 = 30;
+/// 前方多远以内的目标值得喷火
+const FIRE_RANGE: i32 = 40;
+/// 龙出生的横坐标，躲开激光和压碎机后慢慢回到这里
+const HOME_X: i32 = 5;
+
+/// 自动驾驶：观察对局状态，像人类玩家一样给出每个逻辑帧的输入
+///
+/// 瞄准当前障碍物的空隙，能在障碍物到达之前顺路吃到的道具也会去拿；首领战时躲避弹幕。
+/// 前方同一行有能烧毁的目标时喷火；激光或压碎机到达时会封住空隙的话，左右移动提前或推迟到达。
+/// 扇动一次大约上升三格，所以让龙在目标和目标上方三格之间来回。
+#[derive(Debug, Clone)]
+pub struct Bot {
+    player: usize,
+}
+
+impl Bot {
+    /// 操作第 `player` 名玩家的机器人
+    pub fn new(player: usize) -> Self {
+        Bot { player }
+    }
+
+    pub fn input(&self, game: &Game) -> TickInput {
+        let Some(dragon) = game.dragons.get(self.player).filter(|dragon| !dragon.is_out()) else {
+            return TickInput::default();
+        };
+        let player = &dragon.player;
+        let velocity = player.get_status().velocity;
+        let target = Self::target(game, dragon);
+        let (left, right) = Self::timing(game, dragon);
+
+        // 下一次重力更新后的位置
+        let next_velocity = if velocity < 1.5 { velocity + 0.3 } else { velocity };
+        let next_y = player.y + next_velocity as i32;
+        TickInput {
+            // 上升明显减速后才再次扇动
+            flap: next_y > target && velocity > -1.0,
+            // 离目标太高时下冲，比等重力快得多
+            dive: player.y < target - RISE - 1 && velocity >= 0.0,
+            fire: dragon.can_fire() && Self::target_ahead(game, dragon),
+            left,
+            right,
+        }
+    }
+
+    /// 左右移动，让龙在空隙张开的时候到达障碍物
+    fn timing(game: &Game, dragon: &Dragon) -> (bool, bool) {
+        let player = &dragon.player;
+        let obstacle = &game.obstacle;
+        if game.boss.is_some() || obstacle.x < player.x {
+            return (player.x > HOME_X, false);
+        }
+        if obstacle.x == player.x {
+            // 正在穿过障碍物，往左移动会和它一起留在同一列
+            return (false, false);
+        }
+        let step = game.scroll_step();
+        // 到达前后各一帧空隙都至少有扇动一次的高度
+        let safe = |distance: i32| {
+            let ticks = distance / step;
+            (ticks - 1..=ticks + 1).all(|ticks| obstacle.gap_after(ticks).is_some_and(|(top, bottom)| bottom - top >= RISE))
+        };
+        let distance = obstacle.x - player.x;
+        if !safe(distance) {
+            // 往前赶或者往后退，选需要移动的格数少的一边
+            let earlier = (1..(SCREEN_WIDTH / 2 - player.x).min(distance)).find(|&n| safe(distance - n));
+            let later = (1..=player.x).find(|&n| safe(distance + n));
+            return match (earlier, later) {
+                (Some(earlier), Some(later)) if later < earlier => (true, false),
+                (None, Some(_)) => (true, false),
+                _ => (false, true),
+            };
+        }
+        (player.x > HOME_X && safe(distance + 1), false)
+    }
+
+    /// 龙来回范围的下沿
+    fn target(game: &Game, dragon: &Dragon) -> i32 {
+        if let Some(boss) = &game.boss {
+            return Self::dodge(boss, dragon);
+        }
+        let player = &dragon.player;
+        let obstacle = &game.obstacle;
+        let gap_target = if obstacle.x >= player.x {
+            let half = obstacle.size / 2;
+            // 移动障碍物的空隙会上下移动，多留一格余量
+            let margin = if obstacle.obstacle_type == ObstacleType::Moving { 1 } else { 0 };
+            let (top, bottom) = (obstacle.gap_y - half + margin, obstacle.gap_y + half - margin);
+            ((top + bottom) / 2 + RISE / 2).clamp(top, bottom.max(top))
+        } else {
+            SCREEN_HEIGHT / 2
+        };
+
+        // 重力每隔几个逻辑帧更新一次，龙每次最多上下移动一格
+        let ticks_per_move = dragon.ticks_per_move();
+        let speed = game.scroll_step();
+        let ticks_to_obstacle = (obstacle.x - player.x) / speed;
+
+        // 在障碍物之前到达、来得及吃到又来得及回到空隙的最近的道具
+        let powerup = game
+            .powerups
+            .iter()
+            .filter(|powerup| {
+                let ticks = powerup.x - player.x;
+                ticks >= 0
+                    && (obstacle.x < player.x || ticks < ticks_to_obstacle)
+                    && (powerup.y - player.y).abs() <= ticks / ticks_per_move
+                    && (obstacle.x < player.x || (powerup.y - gap_target).abs() <= (ticks_to_obstacle - ticks) / ticks_per_move)
+            })
+            .min_by_key(|powerup| powerup.x);
+        match powerup {
+            Some(powerup) => powerup.y + 1,
+            None => gap_target,
+        }
+    }
+
+    /// 前方同一行是否有火球能命中的目标
+    fn target_ahead(game: &Game, dragon: &Dragon) -> bool {
+        let player = &dragon.player;
+        let ahead = |x: i32| (player.x + 1..=player.x + FIRE_RANGE).contains(&x);
+        let obstacle = &game.obstacle;
+        if obstacle.can_burn() && obstacle.blocks(player.y) && ahead(obstacle.x) {
+            return true;
+        }
+        if let Some(boss) = &game.boss {
+            // 火球飞到时首领已经上下移动了几格
+            let ticks = (boss.x - player.x) / FIREBALL_SPEED;
+            if boss.is_vulnerable() && boss.rows_after(ticks).contains(&player.y) {
+                return true;
+            }
+        }
+        game.hazards
+            .iter()
+            .any(|hazard| hazard.kind.is_destructible() && hazard.y == player.y && ahead(hazard.x))
+    }
+
+    /// 离龙最近的、来回范围内不会被即将飞到的弹幕打中的高度
+    fn dodge(boss: &Boss, dragon: &Dragon) -> i32 {
+        let player = &dragon.player;
+        let danger = |y: i32| {
+            boss.projectiles.iter().any(|projectile| {
+                let ticks = projectile.x - player.x;
+                (0..=DODGE_TICKS).contains(&ticks) && (projectile.y_after(ticks) - y).abs() <= 1
+            })
+        };
+        (RISE + 2..SCREEN_HEIGHT - 2)
+            .filter(|&y| (y - RISE..=y).all(|y| !danger(y)))
+            .min_by_key(|&y| (y - player.y).abs())
+            .unwrap_or(player.y)
+    }
+}
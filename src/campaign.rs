@@ -0,0 +1,211 @@
+use bracket_lib::prelude::*;
+use flappy::game::Game;
+use flappy::input::Action;
+use flappy::level::{Campaign, CampaignProgress, Level, CAMPAIGN_DIR};
+use crate::editor::Editor;
+use crate::menu::MenuItem;
+use crate::{GameMode, State};
+use std::path::Path;
+
+/// 战役界面的关卡列表和当前玩家的进度
+#[derive(Default)]
+pub struct CampaignMenu {
+    pub campaign: Campaign,
+    pub progress: CampaignProgress,
+    pub cursor: usize,
+    pub message: Option<String>,
+    /// 正在进行的关卡序号，结束后记录进度和进入下一关
+    pub playing: Option<usize>,
+}
+
+impl State {
+    /// 读取关卡和当前玩家的进度，进入战役界面
+    pub(crate) fn show_campaign(&mut self) {
+        let progress = self.profile().campaign().load().unwrap_or_else(|err| {
+            eprintln!("Failed to load campaign progress: {}", err);
+            CampaignProgress::default()
+        });
+        let menu = &mut self.campaign;
+        menu.progress = progress;
+        menu.message = None;
+        match Campaign::load_dir(Path::new(CAMPAIGN_DIR)) {
+            Ok(campaign) => menu.campaign = campaign,
+            Err(err) => {
+                menu.campaign = Campaign::default();
+                menu.message = Some(format!("Failed to load levels: {}", err));
+            }
+        }
+        // 光标停在第一个还没通过的关卡
+        menu.cursor = menu
+            .campaign
+            .levels
+            .iter()
+            .position(|level| !menu.progress.is_completed(&level.id))
+            .unwrap_or(0);
+        self.mode = GameMode::Campaign;
+    }
+
+    pub(crate) fn display_campaign(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/menu_bg.png");
+        ctx.print_centered(5, "Campaign");
+
+        let menu = &self.campaign;
+        for (i, entry) in menu.campaign.levels.iter().enumerate() {
+            let y = 9 + i as i32 * 2;
+            let cursor = if i == menu.cursor { ">" } else { " " };
+            let status = match menu.progress.completed.get(&entry.id) {
+                Some(record) => format!("Best {}", record.best_score),
+                None if menu.campaign.is_unlocked(i, &menu.progress) => String::new(),
+                None => "Locked".to_string(),
+            };
+            let color = if menu.campaign.is_unlocked(i, &menu.progress) { WHITE } else { GRAY };
+            ctx.print_color(24, y, color, BLACK, format!("{} {}. {}", cursor, i + 1, entry.level.name));
+            ctx.print_color(56, y, color, BLACK, status);
+        }
+
+        let bottom = 11 + menu.campaign.levels.len() as i32 * 2;
+        ctx.print(24, bottom, format!("(Up/Down) Move  ({}) Play  (M) Back to Menu", self.key_label(Action::Confirm)));
+        ctx.print(24, bottom + 1, "(E) Edit Level  (N) New Level");
+        if let Some(message) = &menu.message {
+            ctx.print_color(24, bottom + 3, RED, BLACK, message);
+        }
+
+        if let Some(key) = ctx.key {
+            self.campaign_input(key);
+        }
+    }
+
+    fn campaign_input(&mut self, key: VirtualKeyCode) {
+        let count = self.campaign.campaign.levels.len();
+        match key {
+            key if self.key_bindings.matches(Action::Confirm, key) => {
+                let cursor = self.campaign.cursor;
+                if cursor >= count {
+                    return;
+                }
+                if self.campaign.campaign.is_unlocked(cursor, &self.campaign.progress) {
+                    self.start_level(cursor);
+                } else {
+                    self.campaign.message = Some("Clear the previous level to unlock this one".to_string());
+                }
+            }
+            VirtualKeyCode::E => {
+                if let Some(entry) = self.campaign.campaign.levels.get(self.campaign.cursor) {
+                    let path = Path::new(CAMPAIGN_DIR).join(format!("{}.json", entry.id));
+                    self.edit_level(&path);
+                }
+            }
+            VirtualKeyCode::N => {
+                // 新关卡排在战役最后，文件名以序号开头
+                let mut number = count + 1;
+                while Path::new(CAMPAIGN_DIR).join(format!("{:02}-new-level.json", number)).exists() {
+                    number += 1;
+                }
+                self.edit_level(&Path::new(CAMPAIGN_DIR).join(format!("{:02}-new-level.json", number)));
+            }
+            key if key == VirtualKeyCode::M || self.key_bindings.matches(Action::Back, key) => self.mode = GameMode::Menu,
+            VirtualKeyCode::Up => self.campaign.cursor = self.campaign.cursor.saturating_sub(1),
+            VirtualKeyCode::Down => self.campaign.cursor = (self.campaign.cursor + 1).min(count.saturating_sub(1)),
+            _ => {}
+        }
+    }
+
+    /// 在编辑器里打开关卡文件，离开编辑器后回到战役界面
+    fn edit_level(&mut self, path: &Path) {
+        match Editor::open(path) {
+            Ok(editor) => self.open_editor(editor, true),
+            Err(err) => self.campaign.message = Some(format!("Failed to open level: {}", err)),
+        }
+    }
+
+    /// 开始战役的第 `index` 关
+    pub(crate) fn start_level(&mut self, index: usize) {
+        if let Some(entry) = self.campaign.campaign.levels.get(index) {
+            self.play_level(entry.level.clone(), Some(index));
+        }
+    }
+
+    /// 以当前玩家开始一个关卡，`playing` 是它在战役中的序号
+    fn play_level(&mut self, level: Level, playing: Option<usize>) {
+        self.start_game(1);
+        self.game = Game::with_level(level, self.game.seed());
+        self.campaign.playing = playing;
+    }
+
+    /// 关卡结束：通关时记录进度并可以进入下一关
+    pub(crate) fn level_end(&mut self, ctx: &mut BTerm) {
+        self.audio.stop_bgm();
+        ctx.cls();
+        self.set_background(ctx, "assets/end_bg.png");
+        let cleared = self.game.level_cleared();
+        let name = self.game.level.as_ref().map(|run| run.level.name.clone()).unwrap_or_default();
+        ctx.print_centered(8, if cleared { "Level Complete!" } else { "Level Failed" });
+        ctx.print_centered(9, name);
+        ctx.print_centered(10, format!("You earned {} points", self.game.dragons[0].score));
+
+        if !self.score_saved {
+            if cleared {
+                self.record_level_cleared();
+            }
+            if let Err(err) = self.save_statistics() {
+                eprintln!("Failed to save statistics: {}", err);
+            }
+            self.save_achievements();
+            self.score_saved = true;
+        }
+
+        let next = self.campaign.playing.map(|index| index + 1).filter(|&next| next < self.campaign.campaign.levels.len());
+        let mut entries = Vec::new();
+        if let Some(next) = next.filter(|_| cleared) {
+            let label = format!("(N) Next Level: {}", self.campaign.campaign.levels[next].level.name);
+            entries.push((VirtualKeyCode::N, label));
+        }
+        entries.extend([
+            (VirtualKeyCode::R, "(R) Retry".to_string()),
+            (VirtualKeyCode::L, "(L) Campaign".to_string()),
+            (VirtualKeyCode::M, "(M) Main Menu".to_string()),
+        ]);
+        let items: Vec<MenuItem> = entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, label))| MenuItem::new(35, 14 + i as i32 * 2, key, label))
+            .collect();
+
+        if ctx.key.is_some_and(|key| self.key_bindings.matches(Action::Back, key)) {
+            self.mode = GameMode::Menu;
+        } else if let Some(key) = self.menu.show(ctx, &items, &self.key_bindings) {
+            match key {
+                VirtualKeyCode::N => {
+                    if let Some(next) = next {
+                        self.start_level(next);
+                    }
+                }
+                VirtualKeyCode::R => {
+                    if let Some(run) = &self.game.level {
+                        self.play_level(run.level.clone(), self.campaign.playing);
+                    }
+                }
+                VirtualKeyCode::L => self.show_campaign(),
+                VirtualKeyCode::M => self.mode = GameMode::Menu,
+                _ => {}
+            }
+        }
+    }
+
+    /// 保存通关记录，解锁下一关
+    fn record_level_cleared(&mut self) {
+        let Some(index) = self.campaign.playing else {
+            return;
+        };
+        let id = self.campaign.campaign.levels[index].id.clone();
+        let score = self.game.dragons[0].score;
+        match self.profile().campaign().update(|progress| {
+            progress.record(&id, score);
+            progress.clone()
+        }) {
+            Ok(progress) => self.campaign.progress = progress,
+            Err(err) => eprintln!("Failed to save campaign progress: {}", err),
+        }
+    }
+}
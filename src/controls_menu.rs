@@ -0,0 +1,111 @@
+use bracket_lib::prelude::*;
+use flappy::input::{is_bindable, key_name, Action, BindError};
+use crate::{GameMode, State};
+
+/// 按键设置界面的当前操作
+pub enum ControlsMenu {
+    Browse,
+    /// 等待为动作按下新的按键
+    Capture(Action),
+}
+
+impl State {
+    pub(crate) fn show_controls(&mut self) {
+        self.controls_cursor = 0;
+        self.controls_menu = ControlsMenu::Browse;
+        self.controls_message = None;
+        self.mode = GameMode::Controls;
+    }
+
+    pub(crate) fn display_controls(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/menu_bg.png");
+        ctx.print_centered(5, "Controls");
+
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let y = 9 + i as i32 * 2;
+            let cursor = if i == self.controls_cursor { ">" } else { " " };
+            ctx.print(25, y, format!("{} {:<10}{}", cursor, action.name(), self.key_label(*action)));
+        }
+
+        let bottom = 11 + Action::ALL.len() as i32 * 2;
+        match self.controls_menu {
+            ControlsMenu::Browse => {
+                ctx.print(
+                    20,
+                    bottom,
+                    format!(
+                        "(Up/Down) Move  ({}) Rebind  (D) Defaults  ({}) Back",
+                        self.key_label(Action::Confirm),
+                        self.key_label(Action::Back)
+                    ),
+                );
+            }
+            ControlsMenu::Capture(action) => {
+                ctx.print(20, bottom, format!("Press a key for {}...  (Escape) Cancel", action.name()));
+            }
+        }
+        if let Some(message) = &self.controls_message {
+            ctx.print_color(20, bottom + 2, RED, BLACK, message);
+        }
+
+        if let Some(key) = ctx.key {
+            self.controls_input(key);
+        }
+    }
+
+    fn controls_input(&mut self, key: VirtualKeyCode) {
+        match self.controls_menu {
+            ControlsMenu::Browse => match key {
+                key if self.key_bindings.matches(Action::Confirm, key) => {
+                    self.controls_message = None;
+                    self.controls_menu = ControlsMenu::Capture(Action::ALL[self.controls_cursor]);
+                }
+                key if self.key_bindings.matches(Action::Back, key) => self.mode = GameMode::Menu,
+                VirtualKeyCode::Up => self.controls_cursor = self.controls_cursor.saturating_sub(1),
+                VirtualKeyCode::Down => self.controls_cursor = (self.controls_cursor + 1).min(Action::ALL.len() - 1),
+                VirtualKeyCode::D => {
+                    self.key_bindings = Default::default();
+                    self.controls_message = None;
+                    self.save_key_bindings();
+                }
+                _ => {}
+            },
+            ControlsMenu::Capture(action) => {
+                self.controls_menu = ControlsMenu::Browse;
+                if key == VirtualKeyCode::Escape {
+                    return;
+                }
+                if !is_bindable(key) {
+                    self.controls_message = Some(format!("{} can't be bound", key_name(key)));
+                    return;
+                }
+                match self.key_bindings.bind(action, key) {
+                    Ok(()) => {
+                        self.controls_message = None;
+                        self.save_key_bindings();
+                    }
+                    Err(BindError::Conflict(other)) => {
+                        self.controls_message = Some(format!("{} is already bound to {}", key_name(key), other.name()));
+                    }
+                    Err(BindError::MenuHotkey) => {
+                        self.controls_message = Some(format!("{} is a menu shortcut", key_name(key)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// 动作当前绑定的按键，用于界面提示
+    pub(crate) fn key_label(&self, action: Action) -> String {
+        let names: Vec<String> = self.key_bindings.keys(action).iter().map(|&key| key_name(key)).collect();
+        names.join("/")
+    }
+
+    fn save_key_bindings(&mut self) {
+        let bindings = self.key_bindings.clone();
+        if let Err(err) = self.profile().settings().update(|settings| settings.key_bindings = bindings) {
+            self.controls_message = Some(format!("Failed to save settings: {}", err));
+        }
+    }
+}
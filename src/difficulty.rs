@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Difficulty {
     Easy,
     Normal, 
@@ -8,6 +9,15 @@ pub enum Difficulty {
     Insane,
 }
 
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Insane,
+    ];
+}
+
 impl FromStr for Difficulty {
     type Err = ();
 
@@ -22,6 +32,7 @@ impl FromStr for Difficulty {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DifficultySettings {
     pub obstacle_speed_multiplier: f32,
     pub obstacle_gap_size_modifier: i32,
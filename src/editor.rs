@@ -0,0 +1,519 @@
+use bracket_lib::prelude::*;
+use flappy::difficulty::{Difficulty, DifficultySettings};
+use flappy::game::Game;
+use flappy::input::Action;
+use flappy::level::{Level, LevelObstacle, LevelPowerUp, SpeedChange, MAX_SPEED, MIN_GAP_SIZE};
+use flappy::obstacle::{Obstacle, ObstacleType};
+use flappy::powerup::{PowerUp, PowerUpType};
+use flappy::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::profile_menu::key_to_char;
+use crate::{GameMode, State};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 新放置的障碍物的空隙大小
+const DEFAULT_GAP_SIZE: i32 = 12;
+/// 编辑器里允许的最大空隙
+const MAX_GAP_SIZE: i32 = 40;
+/// 按住 Shift 时每次滚动的格数
+const FAST_SCROLL: i32 = 10;
+/// 关卡名字的最大长度
+const MAX_LEVEL_NAME_LEN: usize = 24;
+/// 龙在屏幕上的横坐标，试玩时从这一列开始
+const START_X: i32 = 5;
+
+/// 鼠标左键放置的东西，数字键 1-9 选择，更多的用 Tab 切换
+#[derive(Debug, Clone, PartialEq)]
+enum Tool {
+    Obstacle(ObstacleType),
+    PowerUp(PowerUpType),
+}
+
+impl Tool {
+    fn all() -> Vec<Tool> {
+        let obstacles = ObstacleType::ALL.into_iter().map(Tool::Obstacle);
+        obstacles.chain(PowerUpType::ALL.into_iter().map(Tool::PowerUp)).collect()
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Tool::Obstacle(obstacle_type) => format!("{:?} obstacle", obstacle_type),
+            Tool::PowerUp(power_type) => format!("{:?} power-up", power_type),
+        }
+    }
+}
+
+/// 选中的障碍物或道具在关卡列表中的序号
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Selection {
+    Obstacle(usize),
+    PowerUp(usize),
+}
+
+/// 关卡编辑器：沿着关卡滚动，用鼠标放置、拖动和删除障碍物与道具
+///
+/// 编辑期间列表不排序，序号在删除之前一直有效；保存和试玩时才排序。
+pub struct Editor {
+    level: Level,
+    path: PathBuf,
+    /// 屏幕左边缘在关卡中的位置
+    scroll: i32,
+    tool: usize,
+    selected: Option<Selection>,
+    dragging: bool,
+    mouse_down: bool,
+    right_down: bool,
+    /// 正在输入的关卡名字
+    naming: Option<String>,
+    unsaved: bool,
+    /// 有未保存的修改时，第一次按返回只提示
+    confirm_leave: bool,
+    /// 正在试玩，结束后回到编辑器
+    pub testing: bool,
+    /// 从战役界面打开的，离开时回到战役界面
+    from_campaign: bool,
+    message: Option<String>,
+}
+
+impl Editor {
+    /// 打开关卡文件，文件不存在时新建一个空关卡
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let level = match Level::load(path) {
+            Ok(level) => level,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                // 文件名去掉开头的序号作为关卡名字
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy().replace(['-', '_'], " ");
+                Level::empty(stem.trim_start_matches(|c: char| c.is_ascii_digit()).trim())
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(Editor {
+            level,
+            path: path.to_path_buf(),
+            scroll: 0,
+            tool: 0,
+            selected: None,
+            dragging: false,
+            mouse_down: false,
+            right_down: false,
+            naming: None,
+            unsaved: false,
+            confirm_leave: false,
+            testing: false,
+            from_campaign: false,
+            message: None,
+        })
+    }
+
+    fn settings(&self) -> DifficultySettings {
+        DifficultySettings::new(self.level.difficulty.clone())
+    }
+
+    fn scroll_to(&mut self, scroll: i32) {
+        self.scroll = scroll.clamp(0, self.level.finish.max(0));
+    }
+
+    /// 鼠标所在格子对应的关卡位置
+    fn level_pos(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        (self.scroll + x, y.clamp(0, SCREEN_HEIGHT - 1))
+    }
+
+    /// 鼠标下的道具（周围一格内）或障碍物（同一列）
+    fn item_at(&self, (x, y): (i32, i32)) -> Option<Selection> {
+        let powerup = self.level.powerups.iter().position(|powerup| (powerup.x - x).abs() <= 1 && (powerup.y - y).abs() <= 1);
+        powerup
+            .map(Selection::PowerUp)
+            .or_else(|| self.level.obstacles.iter().position(|obstacle| obstacle.x == x).map(Selection::Obstacle))
+    }
+
+    fn changed(&mut self) {
+        self.unsaved = true;
+        self.confirm_leave = false;
+    }
+
+    /// 在关卡位置 `(x, y)` 放置当前工具，同一列已有障碍物时不放
+    fn place(&mut self, (x, y): (i32, i32)) {
+        if !(0..self.level.finish).contains(&x) {
+            self.message = Some("Items must be placed before the finish line".to_string());
+            return;
+        }
+        match Tool::all().swap_remove(self.tool) {
+            Tool::Obstacle(obstacle_type) => {
+                if self.level.obstacles.iter().any(|obstacle| obstacle.x == x) {
+                    return;
+                }
+                self.level.obstacles.push(LevelObstacle { x, gap_y: y, gap_size: DEFAULT_GAP_SIZE, obstacle_type });
+                self.selected = Some(Selection::Obstacle(self.level.obstacles.len() - 1));
+            }
+            Tool::PowerUp(power_type) => {
+                self.level.powerups.push(LevelPowerUp { x, y, power_type });
+                self.selected = Some(Selection::PowerUp(self.level.powerups.len() - 1));
+            }
+        }
+        self.dragging = true;
+        self.changed();
+    }
+
+    /// 把选中的东西拖到关卡位置 `(x, y)`，障碍物不会拖到另一个障碍物所在的列
+    fn drag_to(&mut self, (x, y): (i32, i32)) {
+        let x = x.clamp(0, self.level.finish - 1);
+        match self.selected {
+            Some(Selection::Obstacle(i)) => {
+                let occupied = self.level.obstacles.iter().enumerate().any(|(j, obstacle)| j != i && obstacle.x == x);
+                let obstacle = &mut self.level.obstacles[i];
+                if (occupied || obstacle.x == x) && obstacle.gap_y == y {
+                    return;
+                }
+                if !occupied {
+                    obstacle.x = x;
+                }
+                obstacle.gap_y = y;
+            }
+            Some(Selection::PowerUp(i)) => {
+                let powerup = &mut self.level.powerups[i];
+                if (powerup.x, powerup.y) == (x, y) {
+                    return;
+                }
+                powerup.x = x;
+                powerup.y = y;
+            }
+            None => return,
+        }
+        self.changed();
+    }
+
+    fn remove(&mut self, selection: Selection) {
+        match selection {
+            Selection::Obstacle(i) => {
+                self.level.obstacles.remove(i);
+            }
+            Selection::PowerUp(i) => {
+                self.level.powerups.remove(i);
+            }
+        }
+        self.selected = None;
+        self.dragging = false;
+        self.changed();
+    }
+
+    /// 调整选中障碍物的空隙大小
+    fn resize_gap(&mut self, delta: i32) {
+        match self.selected {
+            Some(Selection::Obstacle(i)) => {
+                let obstacle = &mut self.level.obstacles[i];
+                obstacle.gap_size = (obstacle.gap_size + delta).clamp(MIN_GAP_SIZE, MAX_GAP_SIZE);
+                self.changed();
+            }
+            _ => self.message = Some("Select an obstacle to change its gap".to_string()),
+        }
+    }
+
+    /// 把终点线移到关卡位置 `x`，之后的东西会被删除
+    fn set_finish(&mut self, x: i32) {
+        let finish = x.max(1);
+        let before = self.level.obstacles.len() + self.level.powerups.len() + self.level.speed_changes.len();
+        self.level.finish = finish;
+        self.level.obstacles.retain(|obstacle| obstacle.x < finish);
+        self.level.powerups.retain(|powerup| powerup.x < finish);
+        self.level.speed_changes.retain(|change| change.at < finish);
+        let removed = before - self.level.obstacles.len() - self.level.powerups.len() - self.level.speed_changes.len();
+        self.message = Some(match removed {
+            0 => format!("Finish line moved to {}", finish),
+            _ => format!("Finish line moved to {}, removed {} items past it", finish, removed),
+        });
+        self.selected = None;
+        self.changed();
+    }
+
+    /// 在关卡位置 `x` 的变速点上循环切换速度，转一圈后删除
+    fn cycle_speed_change(&mut self, x: i32) {
+        if !(1..self.level.finish).contains(&x) {
+            return;
+        }
+        let changes = &mut self.level.speed_changes;
+        match changes.iter().position(|change| change.at == x) {
+            Some(i) if changes[i].speed >= MAX_SPEED => {
+                changes.remove(i);
+            }
+            Some(i) => changes[i].speed += 1,
+            None => changes.push(SpeedChange { at: x, speed: 1 }),
+        }
+        self.changed();
+    }
+
+    /// 按位置排序后检查并写入文件
+    fn save(&mut self) -> Result<(), String> {
+        self.level.sort();
+        self.selected = None;
+        self.level.validate()?;
+        self.level.save(&self.path).map_err(|err| format!("Failed to save {}: {}", self.path.display(), err))?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    fn render(&self, ctx: &mut BTerm) {
+        let settings = self.settings();
+        let on_screen = |x: i32| (0..SCREEN_WIDTH).contains(&(x - self.scroll));
+
+        // 选中的东西用背景色标出
+        match self.selected {
+            Some(Selection::Obstacle(i)) => {
+                let x = self.level.obstacles[i].x - self.scroll;
+                for y in 0..SCREEN_HEIGHT {
+                    ctx.set_bg(x, y, (60, 60, 0));
+                }
+            }
+            Some(Selection::PowerUp(i)) => {
+                let powerup = &self.level.powerups[i];
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        ctx.set_bg(powerup.x - self.scroll + dx, powerup.y + dy, (60, 60, 0));
+                    }
+                }
+            }
+            None => {}
+        }
+
+        for obstacle in self.level.obstacles.iter().filter(|obstacle| on_screen(obstacle.x)) {
+            Obstacle::placed(obstacle.x - self.scroll, obstacle.gap_y, obstacle.gap_size, obstacle.obstacle_type.clone())
+                .render(ctx, 0, &settings);
+        }
+        for powerup in self.level.powerups.iter().filter(|powerup| on_screen(powerup.x)) {
+            PowerUp::new(powerup.x - self.scroll, powerup.y, powerup.power_type.clone()).render(ctx);
+        }
+        // 试玩时龙出现的位置
+        ctx.set(START_X, SCREEN_HEIGHT / 2, YELLOW, BLACK, to_cp437('>'));
+        if on_screen(self.level.finish) {
+            for y in 0..SCREEN_HEIGHT {
+                let color = if y % 2 == 0 { WHITE } else { GRAY };
+                ctx.set(self.level.finish - self.scroll, y, color, BLACK, to_cp437('#'));
+            }
+        }
+
+        // 底部的标尺和变速点
+        for x in 0..SCREEN_WIDTH {
+            let pos = self.scroll + x;
+            if pos % 10 == 0 {
+                ctx.set(x, SCREEN_HEIGHT - 1, GRAY, BLACK, to_cp437('|'));
+            }
+            if pos % 30 == 0 {
+                ctx.print_color(x + 1, SCREEN_HEIGHT - 1, GRAY, BLACK, pos.to_string());
+            }
+        }
+        for change in self.level.speed_changes.iter().filter(|change| on_screen(change.at)) {
+            ctx.print_color(change.at - self.scroll, SCREEN_HEIGHT - 2, CYAN, BLACK, format!("»{}", change.speed));
+        }
+    }
+}
+
+impl State {
+    /// 打开编辑器，`from_campaign` 为真时离开后回到战役界面
+    pub(crate) fn open_editor(&mut self, mut editor: Editor, from_campaign: bool) {
+        editor.from_campaign = from_campaign;
+        self.editor = Some(editor);
+        self.mode = GameMode::Editor;
+    }
+
+    /// 是否正在从编辑器试玩关卡
+    pub(crate) fn testing_level(&self) -> bool {
+        self.editor.as_ref().is_some_and(|editor| editor.testing)
+    }
+
+    /// 试玩结束，回到编辑器
+    pub(crate) fn return_to_editor(&mut self) {
+        let cleared = self.game.level_cleared();
+        let distance = self.game.level.as_ref().map_or(0, |run| run.distance);
+        if let Some(editor) = &mut self.editor {
+            editor.testing = false;
+            editor.message = Some(if cleared {
+                "Test run: level cleared".to_string()
+            } else if self.game.is_over() {
+                format!("Test run: failed at {}", distance)
+            } else {
+                format!("Test run stopped at {}", distance)
+            });
+        }
+        self.audio.stop_bgm();
+        self.mode = GameMode::Editor;
+    }
+
+    fn close_editor(&mut self) {
+        let from_campaign = self.editor.take().is_some_and(|editor| editor.from_campaign);
+        if from_campaign {
+            self.show_campaign();
+        } else {
+            self.mode = GameMode::Menu;
+        }
+    }
+
+    pub(crate) fn editor(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/game_bg.png");
+        let back = self.key_label(Action::Back);
+        let Some(editor) = self.editor.as_mut() else {
+            self.mode = GameMode::Menu;
+            return;
+        };
+
+        editor.render(ctx);
+        let file = editor.path.file_name().unwrap_or_default().to_string_lossy();
+        let unsaved = if editor.unsaved { " *" } else { "" };
+        ctx.print(0, 0, format!(
+            "{} ({}{}) | {:?} | Speed {} | {}/{}",
+            editor.level.name, file, unsaved, editor.level.difficulty, editor.level.speed, editor.scroll, editor.level.finish
+        ));
+        ctx.print(0, 1, format!(
+            "Tool [1-9/Tab]: {} | Click place/drag  Right-click remove  +/- gap  F finish  V speed",
+            Tool::all()[editor.tool].name()
+        ));
+        ctx.print(0, 2, format!("Arrows scroll  P test from here  S save  N rename  D difficulty  ({}) leave", back));
+        match &editor.naming {
+            Some(buffer) => ctx.print_color(0, 3, YELLOW, BLACK, format!("Level name: {}_", buffer)),
+            None => {
+                if let Some(message) = &editor.message {
+                    ctx.print_color(0, 3, YELLOW, BLACK, message);
+                }
+            }
+        }
+
+        self.editor_mouse(ctx);
+        if let Some(key) = ctx.key {
+            self.editor_input(key, ctx.shift, ctx.mouse_pos().0);
+        }
+    }
+
+    fn editor_mouse(&mut self, ctx: &mut BTerm) {
+        let Some(editor) = self.editor.as_mut() else {
+            return;
+        };
+        let (left, right) = {
+            let input = INPUT.lock();
+            (input.is_mouse_button_pressed(0), input.is_mouse_button_pressed(1))
+        };
+        let pos = editor.level_pos(ctx.mouse_pos());
+        if left && !editor.mouse_down {
+            editor.message = None;
+            match editor.item_at(pos) {
+                Some(selection) => {
+                    editor.selected = Some(selection);
+                    editor.dragging = true;
+                }
+                None => editor.place(pos),
+            }
+        } else if left && editor.dragging {
+            editor.drag_to(pos);
+        } else if !left {
+            editor.dragging = false;
+        }
+        if right && !editor.right_down {
+            if let Some(selection) = editor.item_at(pos) {
+                editor.remove(selection);
+            }
+        }
+        editor.mouse_down = left;
+        editor.right_down = right;
+    }
+
+    /// `mouse_x` 是鼠标所在的屏幕列，终点线和变速点放在这一列
+    fn editor_input(&mut self, key: VirtualKeyCode, shift: bool, mouse_x: i32) {
+        let back_label = self.key_label(Action::Back);
+        let back = self.key_bindings.matches(Action::Back, key);
+        let confirm = self.key_bindings.matches(Action::Confirm, key);
+        let Some(editor) = self.editor.as_mut() else {
+            return;
+        };
+
+        // 输入关卡名字时按键都用于输入
+        if let Some(buffer) = &mut editor.naming {
+            if confirm {
+                if !buffer.trim().is_empty() {
+                    editor.level.name = buffer.trim().to_string();
+                    editor.changed();
+                }
+                editor.naming = None;
+            } else if back {
+                editor.naming = None;
+            } else if key == VirtualKeyCode::Back {
+                buffer.pop();
+            } else if let Some(c) = key_to_char(key, shift) {
+                if buffer.chars().count() < MAX_LEVEL_NAME_LEN {
+                    buffer.push(c);
+                }
+            }
+            return;
+        }
+
+        let step = if shift { FAST_SCROLL } else { 1 };
+        let tools = Tool::all().len();
+        let digits = [
+            VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3, VirtualKeyCode::Key4, VirtualKeyCode::Key5,
+            VirtualKeyCode::Key6, VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+        ];
+        let mouse_x = editor.scroll + mouse_x;
+        if !back {
+            editor.confirm_leave = false;
+        }
+        match key {
+            _ if back => {
+                if editor.unsaved && !editor.confirm_leave {
+                    editor.confirm_leave = true;
+                    editor.message = Some(format!("Unsaved changes: press {} again to discard them", back_label));
+                } else {
+                    self.close_editor();
+                }
+            }
+            VirtualKeyCode::Left => editor.scroll_to(editor.scroll - step),
+            VirtualKeyCode::Right => editor.scroll_to(editor.scroll + step),
+            VirtualKeyCode::PageUp => editor.scroll_to(editor.scroll - SCREEN_WIDTH / 2),
+            VirtualKeyCode::PageDown => editor.scroll_to(editor.scroll + SCREEN_WIDTH / 2),
+            VirtualKeyCode::Home => editor.scroll_to(0),
+            VirtualKeyCode::End => editor.scroll_to(editor.level.finish - SCREEN_WIDTH / 2),
+            VirtualKeyCode::Tab => editor.tool = (editor.tool + 1) % tools,
+            key if digits.contains(&key) => {
+                editor.tool = digits.iter().position(|&digit| digit == key).unwrap_or(0).min(tools - 1);
+            }
+            VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => editor.resize_gap(1),
+            VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => editor.resize_gap(-1),
+            VirtualKeyCode::X | VirtualKeyCode::Delete => {
+                if let Some(selection) = editor.selected {
+                    editor.remove(selection);
+                }
+            }
+            VirtualKeyCode::F => editor.set_finish(mouse_x),
+            VirtualKeyCode::V => editor.cycle_speed_change(mouse_x),
+            VirtualKeyCode::D => {
+                let index = Difficulty::ALL.iter().position(|d| *d == editor.level.difficulty).unwrap_or(0);
+                editor.level.difficulty = Difficulty::ALL[(index + 1) % Difficulty::ALL.len()].clone();
+                editor.changed();
+            }
+            VirtualKeyCode::N => editor.naming = Some(editor.level.name.clone()),
+            VirtualKeyCode::S => {
+                editor.message = Some(match editor.save() {
+                    Ok(()) => format!("Saved {}", editor.path.display()),
+                    Err(err) => err,
+                });
+            }
+            VirtualKeyCode::P => self.test_level(),
+            _ => {}
+        }
+    }
+
+    /// 从编辑器当前滚动到的位置开始试玩，不计入统计和成就
+    fn test_level(&mut self) {
+        let Some(editor) = self.editor.as_mut() else {
+            return;
+        };
+        editor.level.sort();
+        editor.selected = None;
+        if let Err(err) = editor.level.validate() {
+            editor.message = Some(err);
+            return;
+        }
+        let (level, distance) = (editor.level.clone(), editor.scroll);
+        self.start_game(1);
+        self.game = Game::with_level_from(level, self.game.seed(), distance);
+        if let Some(editor) = &mut self.editor {
+            editor.testing = true;
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use bracket_lib::prelude::*;
+use crate::hazard::HazardKind;
+use crate::render::Canvas;
+use crate::SCREEN_WIDTH;
+use serde::{Deserialize, Serialize};
+
+/// 能量上限，每次喷火消耗 `FIRE_COST`
+pub const MAX_ENERGY: f32 = 100.0;
+pub const FIRE_COST: f32 = 25.0;
+/// 每个逻辑帧恢复的能量（约每秒 12 点）
+pub const ENERGY_REGEN: f32 = 0.2;
+/// 两次喷火之间至少间隔的逻辑帧数
+pub const FIRE_COOLDOWN: i32 = 15;
+/// 火球每个逻辑帧向右飞行的格数
+pub const FIREBALL_SPEED: i32 = 2;
+
+/// 火球可以摧毁的目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Target {
+    /// 可以烧毁的障碍物
+    Obstacle,
+    Hazard(HazardKind),
+    /// 把首领打到没有生命
+    Boss,
+}
+
+impl Target {
+    /// 摧毁目标得到的分数
+    pub fn score(&self) -> i32 {
+        match self {
+            Target::Obstacle => 2,
+            Target::Hazard(_) => 1,
+            Target::Boss => 20,
+        }
+    }
+}
+
+/// 龙喷出的火球，水平向右飞行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fireball {
+    pub x: i32,
+    pub y: i32,
+    prev_x: i32,
+    /// 喷火的玩家序号，摧毁目标的分数归他
+    pub owner: usize,
+    timer: i32,
+}
+
+impl Fireball {
+    pub fn new(x: i32, y: i32, owner: usize) -> Self {
+        Fireball { x, y, prev_x: x, owner, timer: 0 }
+    }
+
+    pub fn update(&mut self) {
+        self.prev_x = self.x;
+        self.x += FIREBALL_SPEED;
+        self.timer += 1;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.x < SCREEN_WIDTH
+    }
+
+    /// 这个逻辑帧飞过的范围是否和 `left..=right` 重叠，目标同时在向左移动时传入它扫过的范围
+    pub fn crosses(&self, left: i32, right: i32) -> bool {
+        self.prev_x <= right && left <= self.x
+    }
+
+    pub fn render(&self, ctx: &mut impl Canvas) {
+        let color = if self.timer % 4 < 2 { ORANGE } else { YELLOW };
+        ctx.set(self.x, self.y, color, BLACK, to_cp437('*'));
+        ctx.set(self.x - 1, self.y, RED, BLACK, to_cp437('~'));
+    }
+}
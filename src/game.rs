@@ -0,0 +1,1033 @@
+use bracket_lib::prelude::*;
+use crate::biome::{Biome, TRANSITION_TICKS};
+use crate::boss::{Boss, BossHit, BOSS_BONUS, BOSS_POINTS};
+use crate::fireball::{Fireball, Target, ENERGY_REGEN, FIRE_COOLDOWN, FIRE_COST, MAX_ENERGY};
+use crate::hazard::{Hazard, HazardKind};
+use crate::render::Canvas;
+use crate::difficulty::{Difficulty, DifficultySettings};
+use crate::level::{Level, LevelRun};
+use crate::obstacle::{Obstacle, ObstacleType, Reach};
+use crate::player::Player;
+use crate::powerup::{ActivePowerUp, PowerUp, PowerUpType};
+use crate::{FRAME_DURATION, SCREEN_HEIGHT, SCREEN_WIDTH, TICK_DURATION};
+use serde::{Deserialize, Serialize};
+
+/// 一个逻辑帧内的玩家输入
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TickInput {
+    pub flap: bool,
+    pub dive: bool,
+    pub left: bool,
+    pub right: bool,
+    pub fire: bool,
+}
+
+impl TickInput {
+    /// 压缩成一个字节，用于录像
+    pub fn to_bits(self) -> u8 {
+        (self.flap as u8) | (self.dive as u8) << 1 | (self.left as u8) << 2 | (self.right as u8) << 3 | (self.fire as u8) << 4
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        TickInput {
+            flap: bits & 1 != 0,
+            dive: bits & 1 << 1 != 0,
+            left: bits & 1 << 2 != 0,
+            right: bits & 1 << 3 != 0,
+            fire: bits & 1 << 4 != 0,
+        }
+    }
+
+    /// 合并同一逻辑帧内收到的多次输入
+    pub fn merge(&mut self, other: TickInput) {
+        self.flap |= other.flap;
+        self.dive |= other.dive;
+        self.left |= other.left;
+        self.right |= other.right;
+        self.fire |= other.fire;
+    }
+}
+
+/// 游戏过程中发生的事件，供统计等系统使用
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    Flapped,
+    ObstaclePassed(ObstacleType),
+    PowerUpCollected(PowerUpType),
+    /// 护盾抵挡了一次碰撞
+    ShieldConsumed,
+    LifeLost(DeathCause),
+    /// 喷出了一个火球
+    Fired,
+    TargetDestroyed(Target),
+}
+
+/// 失去生命的原因
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeathCause {
+    /// 掉出屏幕底部
+    Floor,
+    /// 贴着屏幕顶部撞上障碍物
+    Ceiling,
+    Obstacle(ObstacleType),
+    Hazard(HazardKind),
+    /// 撞上首领或者它的弹幕
+    Boss,
+}
+
+impl DeathCause {
+    /// 所有可能的原因，用于按固定顺序输出统计
+    pub fn all() -> Vec<DeathCause> {
+        let mut causes = vec![DeathCause::Floor, DeathCause::Ceiling];
+        causes.extend(ObstacleType::ALL.into_iter().map(DeathCause::Obstacle));
+        causes.extend(HazardKind::ALL.into_iter().filter(HazardKind::is_lethal).map(DeathCause::Hazard));
+        causes.push(DeathCause::Boss);
+        causes
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            DeathCause::Floor => "floor".to_string(),
+            DeathCause::Ceiling => "ceiling".to_string(),
+            DeathCause::Obstacle(obstacle_type) => format!("{:?}", obstacle_type).to_lowercase(),
+            DeathCause::Hazard(kind) => format!("{:?}", kind).to_lowercase(),
+            DeathCause::Boss => "boss".to_string(),
+        }
+    }
+}
+
+fn first_boss_score() -> i32 {
+    BOSS_POINTS
+}
+
+fn full_energy() -> f32 {
+    MAX_ENERGY
+}
+
+/// 新的危险和右边刚出现的障碍物至少相隔的格数
+const HAZARD_CLEARANCE: i32 = 6;
+
+/// 一条龙（一名玩家）自己的状态
+#[derive(Serialize, Deserialize)]
+pub struct Dragon {
+    pub player: Player,
+    pub score: i32,
+    pub lives: i32,
+    pub combo_count: i32,
+    pub best_combo: i32,
+    // 道具效果
+    pub active_powerups: Vec<ActivePowerUp>,
+    pub slow_motion_timer: f32,
+    pub shield_active: bool,
+    pub shield_timer: f32,
+    /// 喷火的能量和冷却（逻辑帧）
+    #[serde(default = "full_energy")]
+    pub energy: f32,
+    #[serde(default)]
+    pub fire_cooldown: i32,
+    /// 最近通过的障碍物编号
+    last_obstacle_passed: u64,
+    frame_time: f32,
+}
+
+impl Dragon {
+    fn new(player: Player, lives: i32) -> Self {
+        Dragon {
+            player,
+            score: 0,
+            lives,
+            combo_count: 0,
+            best_combo: 0,
+            active_powerups: Vec::new(),
+            slow_motion_timer: 0.0,
+            shield_active: false,
+            shield_timer: 0.0,
+            energy: MAX_ENERGY,
+            fire_cooldown: 0,
+            last_obstacle_passed: 0,
+            frame_time: 0.0,
+        }
+    }
+
+    pub fn is_out(&self) -> bool {
+        self.lives <= 0
+    }
+
+    /// 双倍积分道具生效时得分翻倍
+    fn score_multiplier(&self) -> i32 {
+        if self.active_powerups.iter().any(|p| matches!(p.power_type, PowerUpType::DoubleScore)) { 2 } else { 1 }
+    }
+
+    /// 能量足够并且冷却结束时可以喷火
+    pub fn can_fire(&self) -> bool {
+        self.energy >= FIRE_COST && self.fire_cooldown == 0
+    }
+
+    /// 两次重力更新之间的逻辑帧数，慢动作时更长
+    pub fn ticks_per_move(&self) -> i32 {
+        let effective_frame_time = if self.slow_motion_timer > 0.0 { TICK_DURATION * 0.5 } else { TICK_DURATION };
+        // 和 `tick_players` 一样累加，避免浮点误差差出一帧
+        let (mut frame_time, mut ticks) = (0.0, 0);
+        while frame_time <= FRAME_DURATION {
+            frame_time += effective_frame_time;
+            ticks += 1;
+        }
+        ticks
+    }
+}
+
+/// 玩家的出生位置和颜色
+struct Spawn {
+    x: i32,
+    y: i32,
+    color: (u8, u8, u8),
+    /// 快速移动时的颜色
+    fast_color: (u8, u8, u8),
+}
+
+const SPAWNS: [Spawn; 2] = [
+    Spawn { x: 5, y: 25, color: YELLOW, fast_color: ORANGE },
+    Spawn { x: 5, y: 20, color: MAGENTA, fast_color: PINK },
+];
+
+fn spawn_player(index: usize) -> Player {
+    let spawn = &SPAWNS[index];
+    Player::with_colors(spawn.x, spawn.y, spawn.color, spawn.fast_color)
+}
+
+/// 第 `index` 名玩家的颜色
+pub fn player_color(index: usize) -> (u8, u8, u8) {
+    SPAWNS[index].color
+}
+
+/// 不依赖窗口的游戏核心
+///
+/// 以固定的逻辑帧推进，所有随机数都来自对局种子，相同的种子和输入序列总会得到相同的结果。
+/// 整个状态（包括随机数生成器）都可以序列化，用于保存和继续对局。
+/// 可以有一到两名玩家，共用同一串障碍物和道具。
+#[derive(Serialize, Deserialize)]
+pub struct Game {
+    pub dragons: Vec<Dragon>,
+    pub obstacle: Obstacle,
+    /// 当前障碍物的编号，从 1 开始
+    obstacle_id: u64,
+    /// 按关卡进行时的进度，障碍物和道具来自关卡而不是随机生成
+    #[serde(default)]
+    pub level: Option<LevelRun>,
+    /// 无尽模式当前的生物群系，以及过渡效果中的上一个生物群系和剩余逻辑帧数
+    #[serde(default)]
+    pub biome: Biome,
+    #[serde(default)]
+    previous_biome: Biome,
+    #[serde(default)]
+    biome_transition: i32,
+    /// 生物群系的危险，关卡里没有
+    #[serde(default)]
+    pub hazards: Vec<Hazard>,
+    #[serde(default)]
+    hazard_timer: i32,
+    /// 正在进行的首领战，期间障碍物暂停
+    #[serde(default)]
+    pub boss: Option<Boss>,
+    /// 最高分达到这个分数后出现下一个首领
+    #[serde(default = "first_boss_score")]
+    next_boss_score: i32,
+    /// 龙喷出的火球
+    #[serde(default)]
+    pub fireballs: Vec<Fireball>,
+    pub difficulty: Difficulty,
+    pub settings: DifficultySettings,
+    // 道具系统
+    pub powerups: Vec<PowerUp>,
+    powerup_spawn_timer: f32,
+    #[serde(skip)]
+    events: Vec<(usize, GameEvent)>,
+    /// 已经推进的逻辑帧数
+    pub ticks: u64,
+    seed: u64,
+    random: RandomNumberGenerator,
+}
+
+impl Game {
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        Game::with_players(difficulty, seed, 1)
+    }
+
+    /// 多名玩家同屏的对局，`players` 为 1 或 2
+    pub fn with_players(difficulty: Difficulty, seed: u64, players: usize) -> Self {
+        let settings = DifficultySettings::new(difficulty.clone());
+        let mut random = RandomNumberGenerator::seeded(seed);
+        let players = players.clamp(1, SPAWNS.len());
+        let dragons: Vec<Dragon> = (0..players).map(|i| Dragon::new(spawn_player(i), settings.starting_lives)).collect();
+        let step = Obstacle::step(&settings);
+        let (reach, arrivals) = (Self::obstacle_reach(&dragons, step), Self::obstacle_arrivals(&dragons, step));
+        Game {
+            dragons,
+            obstacle: Obstacle::new(SCREEN_WIDTH, 0, &mut random, reach, Biome::Cave, &arrivals),
+            obstacle_id: 1,
+            level: None,
+            biome: Biome::Cave,
+            previous_biome: Biome::Cave,
+            biome_transition: 0,
+            hazards: Vec::new(),
+            hazard_timer: Biome::Cave.hazard_interval(),
+            boss: None,
+            next_boss_score: first_boss_score(),
+            fireballs: Vec::new(),
+            difficulty,
+            settings,
+            powerups: Vec::new(),
+            powerup_spawn_timer: 0.0,
+            events: Vec::new(),
+            ticks: 0,
+            seed,
+            random,
+        }
+    }
+
+    /// 按关卡进行的单人对局，使用关卡指定的难度
+    pub fn with_level(level: Level, seed: u64) -> Self {
+        Game::with_level_from(level, seed, 0)
+    }
+
+    /// 从关卡中途的滚动距离 `distance` 开始
+    pub fn with_level_from(level: Level, seed: u64, distance: i32) -> Self {
+        let mut game = Game::new(level.difficulty.clone(), seed);
+        game.level = Some(LevelRun::starting_at(level, distance));
+        game.spawn_obstacle();
+        game
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 所有玩家都没有生命或者飞过关卡终点时游戏结束
+    pub fn is_over(&self) -> bool {
+        self.dragons.iter().all(Dragon::is_out) || self.level_cleared()
+    }
+
+    /// 是否有还在场的玩家飞过了关卡的终点线
+    pub fn level_cleared(&self) -> bool {
+        self.level.as_ref().is_some_and(|run| {
+            self.dragons.iter().any(|dragon| !dragon.is_out() && dragon.player.x >= run.finish_x())
+        })
+    }
+
+    /// 障碍物和道具每个逻辑帧向左移动的格数，关卡可以改变速度
+    pub fn scroll_step(&self) -> i32 {
+        match &self.level {
+            Some(run) => run.speed,
+            None => Obstacle::step(&self.settings),
+        }
+    }
+
+    /// 已经进行的游戏时间（毫秒）
+    pub fn elapsed_ms(&self) -> f32 {
+        self.ticks as f32 * TICK_DURATION
+    }
+
+    /// 取出上次调用以来发生的事件及对应的玩家序号
+    pub fn drain_events(&mut self) -> Vec<(usize, GameEvent)> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// 单人对局推进一个逻辑帧
+    pub fn tick(&mut self, input: TickInput) {
+        self.tick_players(&[input]);
+    }
+
+    /// 推进一个逻辑帧，`inputs` 按玩家顺序排列，缺少的视为没有输入
+    pub fn tick_players(&mut self, inputs: &[TickInput]) {
+        if self.is_over() {
+            return;
+        }
+        self.ticks += 1;
+
+        for (i, dragon) in self.dragons.iter_mut().enumerate() {
+            if dragon.is_out() {
+                continue;
+            }
+            // 计算实际帧时间（考虑慢动作效果）
+            let effective_frame_time = if dragon.slow_motion_timer > 0.0 {
+                TICK_DURATION * 0.5 // 慢动作时减半速度
+            } else {
+                TICK_DURATION
+            };
+
+            dragon.frame_time += effective_frame_time;
+
+            // 更新计时器
+            if dragon.slow_motion_timer > 0.0 {
+                dragon.slow_motion_timer -= TICK_DURATION;
+            }
+            if dragon.shield_timer > 0.0 {
+                dragon.shield_timer -= TICK_DURATION;
+                if dragon.shield_timer <= 0.0 {
+                    dragon.shield_active = false;
+                }
+            }
+            dragon.energy = (dragon.energy + ENERGY_REGEN).min(MAX_ENERGY);
+            dragon.fire_cooldown = (dragon.fire_cooldown - 1).max(0);
+
+            // 重力
+            if dragon.frame_time > FRAME_DURATION {
+                dragon.frame_time = 0.0;
+                dragon.player.gravity_and_move();
+            }
+
+            // 处理输入
+            let input = inputs.get(i).copied().unwrap_or_default();
+            Self::apply_input(dragon, input, i, &mut self.events, &mut self.fireballs);
+        }
+
+        // 更新道具
+        self.update_powerups();
+
+        // 移动障碍物，首领战期间暂停
+        if self.boss.is_none() {
+            self.obstacle.update(self.scroll_step());
+        }
+        if let Some(run) = &mut self.level {
+            run.advance();
+        }
+
+        // 更新危险和首领
+        self.update_hazards();
+        self.update_boss();
+        self.update_fireballs();
+
+        // 碰撞检测
+        self.check_collisions();
+
+        self.update_biome();
+    }
+
+    fn apply_input(
+        dragon: &mut Dragon,
+        input: TickInput,
+        index: usize,
+        events: &mut Vec<(usize, GameEvent)>,
+        fireballs: &mut Vec<Fireball>,
+    ) {
+        if input.left {
+            dragon.player.move_left();
+        }
+        if input.right {
+            dragon.player.move_right();
+        }
+        if input.flap {
+            dragon.player.flap();
+            events.push((index, GameEvent::Flapped));
+        }
+        if input.dive {
+            dragon.player.move_down();
+        }
+        if input.fire && dragon.can_fire() {
+            dragon.energy -= FIRE_COST;
+            dragon.fire_cooldown = FIRE_COOLDOWN;
+            fireballs.push(Fireball::new(dragon.player.x + 1, dragon.player.y, index));
+            events.push((index, GameEvent::Fired));
+        }
+    }
+
+    fn update_powerups(&mut self) {
+        // 生成新道具：关卡按位置放置，否则定时随机生成
+        if let Some(run) = &mut self.level {
+            self.powerups.extend(run.due_powerups());
+        } else {
+            self.powerup_spawn_timer += TICK_DURATION;
+            if self.powerup_spawn_timer > self.settings.powerup_spawn_rate {
+                self.spawn_powerup();
+                self.powerup_spawn_timer = 0.0;
+            }
+        }
+
+        // 更新道具位置
+        let step = self.scroll_step();
+        for powerup in &mut self.powerups {
+            powerup.update(step);
+        }
+
+        // 移除超出屏幕的道具
+        self.powerups.retain(|p| p.x > -5);
+
+        // 每个道具只能被最先碰到的玩家拾取
+        let mut collected = Vec::new();
+        self.powerups.retain(|powerup| {
+            let collector = self.dragons.iter().position(|dragon| {
+                !dragon.is_out() && powerup.x == dragon.player.x && (powerup.y - dragon.player.y).abs() <= 1
+            });
+            match collector {
+                Some(i) => {
+                    collected.push((i, powerup.power_type.clone()));
+                    false
+                }
+                None => true,
+            }
+        });
+
+        for (i, power_type) in collected {
+            self.events.push((i, GameEvent::PowerUpCollected(power_type.clone())));
+            Self::activate_powerup(&mut self.dragons[i], power_type);
+        }
+
+        // 更新激活的道具效果
+        for dragon in &mut self.dragons {
+            dragon.active_powerups.retain_mut(|active| {
+                active.timer -= TICK_DURATION;
+                active.timer > 0.0
+            });
+        }
+    }
+
+    fn spawn_powerup(&mut self) {
+        let power_type = match self.random.range(0, 4) {
+            0 => PowerUpType::Shield,
+            1 => PowerUpType::SlowMotion,
+            2 => PowerUpType::DoubleScore,
+            _ => PowerUpType::ExtraLife,
+        };
+
+        let y = self.random.range(5, SCREEN_HEIGHT - 5);
+        self.powerups.push(PowerUp::new(SCREEN_WIDTH + 10, y, power_type));
+    }
+
+    fn activate_powerup(dragon: &mut Dragon, power_type: PowerUpType) {
+        match power_type {
+            PowerUpType::Shield => {
+                dragon.shield_active = true;
+                dragon.shield_timer = 5000.0; // 5秒护盾
+            }
+            PowerUpType::SlowMotion => {
+                dragon.slow_motion_timer = 3000.0; // 3秒慢动作
+            }
+            PowerUpType::DoubleScore => {
+                dragon.active_powerups.push(ActivePowerUp {
+                    power_type: PowerUpType::DoubleScore,
+                    timer: 10000.0, // 10秒双倍积分
+                });
+            }
+            PowerUpType::ExtraLife => {
+                dragon.lives += 1;
+            }
+        }
+    }
+
+    fn spawn_obstacle(&mut self) {
+        let best_score = self.dragons.iter().map(|d| d.score).max().unwrap_or(0);
+        if self.level.is_none() && self.boss.is_none() && best_score >= self.next_boss_score {
+            self.boss = Some(Boss::new(self.biome.palette().accent));
+            self.next_boss_score = best_score + BOSS_POINTS;
+        }
+        self.obstacle = match &mut self.level {
+            Some(run) => run.next_obstacle(),
+            // 首领战期间把障碍物停在屏幕外，首领离开后再生成
+            None if self.boss.is_some() => Obstacle::placed(SCREEN_WIDTH * 2, SCREEN_HEIGHT / 2, SCREEN_HEIGHT * 2, ObstacleType::Static),
+            None => {
+                let step = Obstacle::step(&self.settings);
+                let reach = Self::obstacle_reach(&self.dragons, step);
+                let arrivals = Self::obstacle_arrivals(&self.dragons, step);
+                Obstacle::new(SCREEN_WIDTH, best_score, &mut self.random, reach, self.biome, &arrivals)
+            }
+        };
+        self.obstacle_id += 1;
+    }
+
+    fn update_hazards(&mut self) {
+        let step = self.scroll_step();
+        for hazard in &mut self.hazards {
+            hazard.update(step);
+        }
+        self.hazards.retain(Hazard::is_active);
+
+        // 阵风把龙吹离原来的高度，但不会把龙吹出屏幕；没被吹到的龙照常可以掉出屏幕底部
+        for dragon in self.dragons.iter_mut().filter(|d| !d.is_out()) {
+            let push: i32 = self.hazards.iter().map(|hazard| hazard.push(&dragon.player)).sum();
+            if push != 0 {
+                dragon.player.y = (dragon.player.y + push).clamp(0, SCREEN_HEIGHT - 1);
+            }
+        }
+
+        if self.level.is_some() {
+            return;
+        }
+        // 不和刚出现的障碍物挤在一起，首领战期间不出现
+        self.hazard_timer -= 1;
+        if self.hazard_timer <= 0 && self.boss.is_none() && self.obstacle.x < SCREEN_WIDTH - HAZARD_CLEARANCE {
+            self.hazards.push(Hazard::spawn(self.biome.hazard(), &mut self.random));
+            let interval = self.biome.hazard_interval();
+            self.hazard_timer = interval + self.random.range(0, interval / 2);
+        }
+    }
+
+    /// 首领瞄准第一条还在场的龙；坚持到首领离开后发放奖励分，弹幕都飞走后恢复障碍物
+    fn update_boss(&mut self) {
+        let Some(boss) = &mut self.boss else {
+            return;
+        };
+        let target_y = self.dragons.iter().find(|d| !d.is_out()).map_or(SCREEN_HEIGHT / 2, |d| d.player.y);
+        if boss.update(target_y, &mut self.random) {
+            for dragon in self.dragons.iter_mut().filter(|d| !d.is_out()) {
+                dragon.score += BOSS_BONUS;
+            }
+        }
+        if boss.is_finished() {
+            self.boss = None;
+            self.spawn_obstacle();
+        }
+    }
+
+    /// 火球烧毁可以烧毁的障碍物、钟乳石和陨石，抵消首领的弹幕并打伤首领；每个火球只能命中一次
+    fn update_fireballs(&mut self) {
+        for fireball in &mut self.fireballs {
+            fireball.update();
+        }
+        self.fireballs.retain(Fireball::is_active);
+
+        let step = self.scroll_step();
+        let mut destroyed = Vec::new();
+        let mut boss_defeated = false;
+        self.fireballs.retain(|fireball| {
+            // 障碍物在这个逻辑帧内从 x + step 移动到了 x
+            let obstacle = &mut self.obstacle;
+            if obstacle.can_burn() && obstacle.blocks(fireball.y) && fireball.crosses(obstacle.x, obstacle.x + step) {
+                obstacle.destroyed = true;
+                destroyed.push((fireball.owner, Target::Obstacle));
+                return false;
+            }
+            let hazard = self.hazards.iter().position(|hazard| {
+                let (left, right) = hazard.span();
+                hazard.kind.is_destructible() && hazard.y == fireball.y && fireball.crosses(left, right)
+            });
+            if let Some(hazard) = hazard {
+                destroyed.push((fireball.owner, Target::Hazard(self.hazards.remove(hazard).kind)));
+                return false;
+            }
+            let Some(boss) = &mut self.boss else {
+                return true;
+            };
+            let projectile = boss.projectiles.iter().position(|projectile| {
+                let (left, right) = projectile.span();
+                projectile.y == fireball.y && fireball.crosses(left, right)
+            });
+            if let Some(projectile) = projectile {
+                boss.projectiles.remove(projectile);
+                return false;
+            }
+            if boss.is_vulnerable() && (fireball.x - 1..=fireball.x).any(|x| boss.covers(x, fireball.y)) {
+                if boss.damage() {
+                    boss_defeated = true;
+                    destroyed.push((fireball.owner, Target::Boss));
+                }
+                return false;
+            }
+            true
+        });
+
+        // 提前打败首领也能得到坚持到最后的奖励分
+        if boss_defeated {
+            for dragon in self.dragons.iter_mut().filter(|d| !d.is_out()) {
+                dragon.score += BOSS_BONUS;
+            }
+        }
+        for (owner, target) in destroyed {
+            let dragon = &mut self.dragons[owner];
+            if dragon.is_out() {
+                continue;
+            }
+            dragon.score += target.score() * dragon.score_multiplier();
+            self.events.push((owner, GameEvent::TargetDestroyed(target)));
+        }
+    }
+
+    /// 最高分达到下一个生物群系时切换，之后的障碍物和危险都来自新的生物群系
+    fn update_biome(&mut self) {
+        if self.biome_transition > 0 {
+            self.biome_transition -= 1;
+        }
+        if self.level.is_some() {
+            return;
+        }
+        let best_score = self.dragons.iter().map(|d| d.score).max().unwrap_or(0);
+        let biome = Biome::at_score(best_score);
+        if biome != self.biome {
+            self.previous_biome = self.biome;
+            self.biome = biome;
+            self.biome_transition = TRANSITION_TICKS;
+            self.hazard_timer = biome.hazard_interval();
+        }
+    }
+
+    /// 从屏幕右边出现的障碍物到达之前，所有还在场的玩家都能到达的高度范围
+    ///
+    /// 两名玩家离得太远、没有共同的范围时，只保证排在前面的玩家。
+    fn obstacle_reach(dragons: &[Dragon], step: i32) -> Reach {
+        dragons
+            .iter()
+            .filter(|dragon| !dragon.is_out())
+            .map(|dragon| {
+                let ticks = (SCREEN_WIDTH - dragon.player.x) / step;
+                Reach::from_player(dragon.player.y, ticks, dragon.ticks_per_move())
+            })
+            .reduce(|reach, other| reach.intersect(other).unwrap_or(reach))
+            .unwrap_or(Reach::ANYWHERE)
+    }
+
+    /// 还在场的玩家不左右移动时，从屏幕右边出现的障碍物到达各自面前所需的逻辑帧数
+    fn obstacle_arrivals(dragons: &[Dragon], step: i32) -> Vec<i32> {
+        dragons
+            .iter()
+            .filter(|dragon| !dragon.is_out())
+            .map(|dragon| (SCREEN_WIDTH - dragon.player.x) / step)
+            .collect()
+    }
+
+    fn check_collisions(&mut self) {
+        // 检查越过障碍物
+        for (i, dragon) in self.dragons.iter_mut().enumerate() {
+            if dragon.is_out() || dragon.player.x <= self.obstacle.x || dragon.last_obstacle_passed == self.obstacle_id {
+                continue;
+            }
+            dragon.score += self.obstacle.get_score_value() * dragon.score_multiplier();
+            dragon.combo_count += 1;
+            dragon.best_combo = dragon.best_combo.max(dragon.combo_count);
+            dragon.last_obstacle_passed = self.obstacle_id;
+            self.events.push((i, GameEvent::ObstaclePassed(self.obstacle.obstacle_type.clone())));
+        }
+
+        // 所有玩家都越过后生成新障碍物
+        let obstacle_id = self.obstacle_id;
+        if self.dragons.iter().all(|d| d.is_out() || d.last_obstacle_passed == obstacle_id) {
+            self.spawn_obstacle();
+        }
+
+        // 检查碰撞
+        for i in 0..self.dragons.len() {
+            let dragon = &mut self.dragons[i];
+            if dragon.is_out() {
+                continue;
+            }
+            let hit_obstacle = self.obstacle.hit_obstacle(&dragon.player);
+            let hazard = self.hazards.iter().position(|hazard| hazard.hits(&dragon.player));
+            let boss_hit = self.boss.as_ref().and_then(|boss| boss.hit(&dragon.player));
+            if dragon.player.y > SCREEN_HEIGHT || hit_obstacle || hazard.is_some() || boss_hit.is_some() {
+                if dragon.shield_active {
+                    // 护盾保护，不死亡但移除护盾（和撞上的危险）
+                    dragon.shield_active = false;
+                    dragon.shield_timer = 0.0;
+                    dragon.combo_count = 0; // 重置连击
+                    self.events.push((i, GameEvent::ShieldConsumed));
+                    if let Some(hazard) = hazard {
+                        self.hazards.remove(hazard);
+                    }
+                    if let (Some(boss), Some(BossHit::Projectile(projectile))) = (&mut self.boss, boss_hit) {
+                        boss.projectiles.remove(projectile);
+                    }
+                } else {
+                    dragon.lives -= 1;
+                    dragon.combo_count = 0;
+                    let cause = if dragon.player.y > SCREEN_HEIGHT {
+                        DeathCause::Floor
+                    } else if hit_obstacle && dragon.player.y <= 0 {
+                        DeathCause::Ceiling
+                    } else if hit_obstacle {
+                        DeathCause::Obstacle(self.obstacle.obstacle_type.clone())
+                    } else if let Some(hazard) = hazard {
+                        DeathCause::Hazard(self.hazards[hazard].kind)
+                    } else {
+                        DeathCause::Boss
+                    };
+                    self.events.push((i, GameEvent::LifeLost(cause)));
+
+                    if dragon.lives > 0 {
+                        // 重置玩家位置，继续游戏
+                        dragon.player = spawn_player(i);
+                        self.spawn_obstacle();
+                        self.hazards.clear();
+                        if let Some(boss) = &mut self.boss {
+                            boss.clear_projectiles();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn render(&mut self, ctx: &mut impl Canvas) {
+        self.render_world(ctx);
+        self.render_hud(ctx);
+    }
+
+    /// 只画游戏世界本身，坐标和碰撞检测一致
+    pub fn render_world(&mut self, ctx: &mut impl Canvas) {
+        // 渲染玩家
+        for dragon in self.dragons.iter_mut().filter(|d| !d.is_out()) {
+            dragon.player.render(ctx);
+        }
+
+        // 渲染障碍物和危险
+        self.obstacle.render(ctx, self.dragons[0].player.x, &self.settings);
+        for hazard in &self.hazards {
+            hazard.render(ctx);
+        }
+        if let Some(boss) = &self.boss {
+            boss.render(ctx);
+        }
+        for fireball in &self.fireballs {
+            fireball.render(ctx);
+        }
+
+        // 渲染道具
+        for powerup in &self.powerups {
+            powerup.render(ctx);
+        }
+
+        // 渲染关卡的终点线和进度
+        if let Some(run) = &self.level {
+            run.render(ctx);
+        }
+
+        // 渲染特效
+        for dragon in self.dragons.iter().filter(|d| !d.is_out() && d.shield_active) {
+            Self::render_shield_effect(dragon, ctx);
+        }
+    }
+
+    /// 分数、道具效果和生物群系名字等固定在屏幕上的信息
+    pub fn render_hud(&self, ctx: &mut impl Canvas) {
+        self.render_ui(ctx);
+        if self.dragons.iter().any(|d| !d.is_out() && d.slow_motion_timer > 0.0) {
+            ctx.print(0, 3, "SLOW MOTION!");
+        }
+
+        // 进入新生物群系时显示名字
+        if self.biome_transition > 0 {
+            let title = format!("~ {} ~", self.biome.name());
+            let x = (SCREEN_WIDTH - title.len() as i32) / 2;
+            ctx.print_color(x, 10, self.biome.palette().accent, BLACK, title);
+        }
+    }
+
+    /// 无尽模式按生物群系画背景，刚进入新生物群系时从右向左擦过去；关卡不画，保留窗口原来的背景
+    pub fn render_background(&self, ctx: &mut impl Canvas) {
+        if self.level.is_some() {
+            return;
+        }
+        let boundary = SCREEN_WIDTH * self.biome_transition / TRANSITION_TICKS;
+        let (previous, current) = (self.previous_biome.palette(), self.biome.palette());
+        for x in 0..SCREEN_WIDTH {
+            let palette = if x < boundary { &previous } else { &current };
+            palette.render_column(ctx, x, self.ticks);
+        }
+    }
+
+    fn render_ui(&self, ctx: &mut impl Canvas) {
+        if let [dragon] = self.dragons.as_slice() {
+            ctx.print(0, 2, format!("Score: {}  |  Lives: {}  |  Combo: {}  |  Fire: {}",
+                                     dragon.score, dragon.lives, dragon.combo_count, Self::energy_bar(dragon)));
+            ctx.print(0, 4, self.difficulty_line());
+            Self::render_effects(dragon, ctx, 0, 4);
+            return;
+        }
+
+        // 多人时每名玩家一列
+        for (i, dragon) in self.dragons.iter().enumerate() {
+            let x = i as i32 * SCREEN_WIDTH / 2;
+            let status = if dragon.is_out() {
+                format!("P{} OUT  |  Score: {}", i + 1, dragon.score)
+            } else {
+                format!("P{} Score: {}  |  Lives: {}  |  Combo: {}  |  {}",
+                        i + 1, dragon.score, dragon.lives, dragon.combo_count, Self::energy_bar(dragon))
+            };
+            ctx.print_color(x, 2, player_color(i), BLACK, status);
+            if !dragon.is_out() {
+                Self::render_effects(dragon, ctx, x, 5);
+            }
+        }
+        ctx.print(0, 4, self.difficulty_line());
+    }
+
+    /// 喷火能量条，每格代表一次喷火消耗的一半
+    fn energy_bar(dragon: &Dragon) -> String {
+        let cells = (MAX_ENERGY / FIRE_COST * 2.0) as usize;
+        let filled = (dragon.energy / MAX_ENERGY * cells as f32) as usize;
+        format!("[{}{}]", "=".repeat(filled), " ".repeat(cells - filled))
+    }
+
+    /// 难度，无尽模式还显示所在的生物群系
+    fn difficulty_line(&self) -> String {
+        match self.level {
+            Some(_) => format!("Current Difficulty: {:?}", self.difficulty),
+            None => format!("Current Difficulty: {:?}  |  Biome: {}", self.difficulty, self.biome.name()),
+        }
+    }
+
+    /// 显示激活的道具效果
+    fn render_effects(dragon: &Dragon, ctx: &mut impl Canvas, x: i32, mut y_offset: i32) {
+        for active in &dragon.active_powerups {
+            ctx.print(x, y_offset, format!("{:?}: {:.1}s",
+                                          active.power_type, active.timer / 1000.0));
+            y_offset += 1;
+        }
+
+        if dragon.shield_active {
+            ctx.print(x, y_offset, format!("Shield: {:.1}s", dragon.shield_timer / 1000.0));
+        }
+    }
+
+    fn render_shield_effect(dragon: &Dragon, ctx: &mut impl Canvas) {
+        // 在玩家周围渲染护盾效果
+        let player = &dragon.player;
+        let shield_char = if (dragon.shield_timer as i32 / 200) % 2 == 0 { 'O' } else { 'o' };
+        ctx.set(player.x - 1, player.y, CYAN, BLACK, to_cp437(shield_char));
+        ctx.set(player.x + 1, player.y, CYAN, BLACK, to_cp437(shield_char));
+        ctx.set(player.x, player.y - 1, CYAN, BLACK, to_cp437(shield_char));
+        ctx.set(player.x, player.y + 1, CYAN, BLACK, to_cp437(shield_char));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把障碍物放在第一条龙所在的列，下一个逻辑帧就被越过
+    fn passing(game: &mut Game, obstacle_type: ObstacleType) {
+        let player = &game.dragons[0].player;
+        game.obstacle = Obstacle::placed(player.x, player.y, 10, obstacle_type);
+    }
+
+    #[test]
+    fn passing_scores_the_obstacle_value() {
+        for obstacle_type in ObstacleType::ALL {
+            let mut game = Game::new(Difficulty::Normal, 1);
+            passing(&mut game, obstacle_type.clone());
+            let value = game.obstacle.get_score_value();
+            game.tick(TickInput::default());
+            assert_eq!(game.dragons[0].score, value, "{:?}", obstacle_type);
+
+            // 双倍积分时翻倍
+            let mut game = Game::new(Difficulty::Normal, 1);
+            Game::activate_powerup(&mut game.dragons[0], PowerUpType::DoubleScore);
+            passing(&mut game, obstacle_type.clone());
+            game.tick(TickInput::default());
+            assert_eq!(game.dragons[0].score, value * 2, "{:?}", obstacle_type);
+        }
+        assert_eq!(Obstacle::placed(0, 25, 10, ObstacleType::Laser).get_score_value(), 3);
+        assert_eq!(Obstacle::placed(0, 25, 10, ObstacleType::Crusher).get_score_value(), 3);
+        assert_eq!(Obstacle::placed(0, 25, 10, ObstacleType::SpikeWall).get_score_value(), 2);
+    }
+
+    /// 两名玩家的对局，障碍物停在屏幕外，清掉随机出现的道具和危险
+    fn two_players() -> Game {
+        let mut game = Game::with_players(Difficulty::Normal, 1, 2);
+        game.obstacle = Obstacle::placed(SCREEN_WIDTH * 2, SCREEN_HEIGHT / 2, 10, ObstacleType::Static);
+        game.powerups.clear();
+        game.hazards.clear();
+        game
+    }
+
+    /// 把障碍物放在下一个逻辑帧到达第 `index` 条龙所在的列，空隙只留给另一条龙
+    fn hitting(game: &mut Game, index: usize) {
+        let step = game.scroll_step();
+        let other = &game.dragons[1 - index].player;
+        game.obstacle = Obstacle::placed(other.x + step, other.y, 2, ObstacleType::Static);
+    }
+
+    #[test]
+    fn collisions_are_per_player() {
+        let mut game = two_players();
+        let lives = game.dragons[0].lives;
+        hitting(&mut game, 0);
+        let obstacle_id = game.obstacle_id;
+        game.tick_players(&[TickInput::default(); 2]);
+
+        assert_eq!(game.drain_events(), vec![(0, GameEvent::LifeLost(DeathCause::Obstacle(ObstacleType::Static)))]);
+        assert_eq!(game.dragons[0].lives, lives - 1);
+        assert_eq!(game.dragons[1].lives, lives);
+        // 撞上的玩家回到出生点，换一个新障碍物
+        assert_eq!((game.dragons[0].player.x, game.dragons[0].player.y), (5, 25));
+        assert_eq!(game.obstacle_id, obstacle_id + 1);
+    }
+
+    #[test]
+    fn knocked_out_players_sit_out_the_rest_of_the_game() {
+        let mut game = two_players();
+        game.dragons[0].lives = 1;
+        hitting(&mut game, 0);
+        game.tick_players(&[TickInput::default(); 2]);
+        assert!(game.dragons[0].is_out());
+        assert!(!game.is_over());
+        game.drain_events();
+
+        // 出局的玩家不再响应输入，也不再越过障碍物或者拾取道具
+        let y = game.dragons[0].player.y;
+        let step = game.scroll_step();
+        game.obstacle = Obstacle::placed(5, 20, 30, ObstacleType::Static);
+        game.powerups.push(PowerUp::new(5 + step, y, PowerUpType::ExtraLife));
+        let flap = TickInput { flap: true, ..TickInput::default() };
+        game.tick_players(&[flap, TickInput::default()]);
+        assert!(game.drain_events().iter().all(|(i, _)| *i == 1));
+        assert_eq!((game.dragons[0].player.y, game.dragons[0].score, game.dragons[0].lives), (y, 0, 0));
+        assert_eq!(game.dragons[1].score, 1);
+        assert_eq!(game.powerups.len(), 1);
+
+        // 最后一名玩家出局后游戏结束，不再推进
+        game.dragons[1].lives = 1;
+        hitting(&mut game, 1);
+        game.tick_players(&[TickInput::default(); 2]);
+        assert!(game.is_over());
+        let ticks = game.ticks;
+        game.tick_players(&[TickInput::default(); 2]);
+        assert_eq!(game.ticks, ticks);
+    }
+
+    #[test]
+    fn powerups_go_to_the_player_who_reaches_them() {
+        let mut game = two_players();
+        let step = game.scroll_step();
+        let lives = game.dragons[0].lives;
+        game.powerups.push(PowerUp::new(5 + step, 20, PowerUpType::ExtraLife));
+        game.tick_players(&[TickInput::default(); 2]);
+        assert_eq!(game.drain_events(), vec![(1, GameEvent::PowerUpCollected(PowerUpType::ExtraLife))]);
+        assert_eq!((game.dragons[0].lives, game.dragons[1].lives), (lives, lives + 1));
+
+        // 两名玩家同时碰到时归序号小的玩家
+        game.dragons[1].player.y = game.dragons[0].player.y - 1;
+        game.powerups.push(PowerUp::new(5 + step, game.dragons[0].player.y, PowerUpType::DoubleScore));
+        game.tick_players(&[TickInput::default(); 2]);
+        assert_eq!(game.drain_events(), vec![(0, GameEvent::PowerUpCollected(PowerUpType::DoubleScore))]);
+        assert!(game.powerups.is_empty());
+        assert!(game.dragons[1].active_powerups.is_empty());
+    }
+
+    #[test]
+    fn next_obstacle_waits_for_every_player() {
+        let mut game = two_players();
+        let step = game.scroll_step();
+        // 第二条龙落后一步，障碍物移动后正好和它在同一列
+        game.dragons[1].player.x = 5 - step;
+        game.obstacle = Obstacle::placed(5, 22, 10, ObstacleType::Static);
+        let obstacle_id = game.obstacle_id;
+
+        game.tick_players(&[TickInput::default(); 2]);
+        assert_eq!(game.drain_events(), vec![(0, GameEvent::ObstaclePassed(ObstacleType::Static))]);
+        assert_eq!(game.obstacle_id, obstacle_id);
+        assert_eq!(game.obstacle.x, 5 - step);
+
+        game.tick_players(&[TickInput::default(); 2]);
+        assert_eq!(game.drain_events(), vec![(1, GameEvent::ObstaclePassed(ObstacleType::Static))]);
+        assert_eq!(game.obstacle_id, obstacle_id + 1);
+        assert_eq!((game.dragons[0].score, game.dragons[1].score), (1, 1));
+    }
+
+    #[test]
+    fn falling_off_the_screen_costs_a_life() {
+        let mut game = two_players();
+        let lives = game.dragons[1].lives;
+        game.dragons[1].player.y = SCREEN_HEIGHT + 1;
+        game.tick_players(&[TickInput::default(); 2]);
+        assert_eq!(game.drain_events(), vec![(1, GameEvent::LifeLost(DeathCause::Floor))]);
+        assert_eq!(game.dragons[1].lives, lives - 1);
+    }
+}
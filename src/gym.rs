@@ -0,0 +1,233 @@
+use crate::difficulty::Difficulty;
+use crate::game::{Game, GameEvent, TickInput};
+use crate::SCREEN_WIDTH;
+
+/// 每个存活的逻辑帧的奖励
+pub const ALIVE_REWARD: f32 = 0.01;
+/// 失去一条生命的惩罚
+pub const LIFE_LOST_PENALTY: f32 = 1.0;
+
+/// 智能体每个逻辑帧可以选择的动作，和玩家的输入一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnvAction {
+    Idle,
+    Flap,
+    Dive,
+    Left,
+    Right,
+    Fire,
+}
+
+impl EnvAction {
+    pub const ALL: [EnvAction; 6] = [
+        EnvAction::Idle,
+        EnvAction::Flap,
+        EnvAction::Dive,
+        EnvAction::Left,
+        EnvAction::Right,
+        EnvAction::Fire,
+    ];
+
+    pub fn input(self) -> TickInput {
+        let mut input = TickInput::default();
+        match self {
+            EnvAction::Idle => {}
+            EnvAction::Flap => input.flap = true,
+            EnvAction::Dive => input.dive = true,
+            EnvAction::Left => input.left = true,
+            EnvAction::Right => input.right = true,
+            EnvAction::Fire => input.fire = true,
+        }
+        input
+    }
+}
+
+/// 智能体看到的状态，距离的单位都是格，向下和向右为正
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Observation {
+    pub player_y: f32,
+    pub velocity: f32,
+    pub lives: i32,
+    /// 到下一个障碍物的水平距离
+    pub obstacle_dx: f32,
+    /// 到空隙上沿和下沿的垂直距离，两者之间（含边界）是安全的
+    pub gap_top_dy: f32,
+    pub gap_bottom_dy: f32,
+    /// 前方最近的道具的水平和垂直距离
+    pub powerup: Option<(f32, f32)>,
+}
+
+impl Observation {
+    /// 第 `player` 名玩家看到的状态
+    pub fn from_game(game: &Game, player: usize) -> Self {
+        let dragon = &game.dragons[player];
+        let (x, y) = (dragon.player.x, dragon.player.y);
+        let obstacle = &game.obstacle;
+        let half = obstacle.size / 2;
+        let powerup = game
+            .powerups
+            .iter()
+            .filter(|powerup| powerup.x >= x)
+            .min_by_key(|powerup| powerup.x)
+            .map(|powerup| ((powerup.x - x) as f32, (powerup.y - y) as f32));
+        Observation {
+            player_y: y as f32,
+            velocity: dragon.player.get_status().velocity,
+            lives: dragon.lives,
+            obstacle_dx: (obstacle.x - x) as f32,
+            gap_top_dy: (obstacle.gap_y - half - y) as f32,
+            gap_bottom_dy: (obstacle.gap_y + half - y) as f32,
+            powerup,
+        }
+    }
+
+    /// 固定长度的特征向量，没有道具时用一个屏幕宽的距离表示
+    pub fn to_vec(&self) -> Vec<f32> {
+        let (has_powerup, (powerup_dx, powerup_dy)) = match self.powerup {
+            Some(powerup) => (1.0, powerup),
+            None => (0.0, (SCREEN_WIDTH as f32, 0.0)),
+        };
+        vec![
+            self.player_y,
+            self.velocity,
+            self.lives as f32,
+            self.obstacle_dx,
+            self.gap_top_dy,
+            self.gap_bottom_dy,
+            has_powerup,
+            powerup_dx,
+            powerup_dy,
+        ]
+    }
+}
+
+/// 类似 gym 的训练环境：每次 `step` 推进一个逻辑帧
+///
+/// 奖励是这一帧得到的分数，加上存活奖励，减去失去生命的惩罚。
+pub struct Env {
+    difficulty: Difficulty,
+    game: Game,
+}
+
+impl Env {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Env {
+            game: Game::new(difficulty.clone(), 0),
+            difficulty,
+        }
+    }
+
+    /// 用新的种子开始一局
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::new(self.difficulty.clone(), seed);
+        self.observe()
+    }
+
+    /// 执行一个动作，返回新的状态、奖励和这一局是否结束
+    pub fn step(&mut self, action: EnvAction) -> (Observation, f32, bool) {
+        if self.game.is_over() {
+            return (self.observe(), 0.0, true);
+        }
+        let score = self.game.dragons[0].score;
+        self.game.tick(action.input());
+        let lives_lost = self
+            .game
+            .drain_events()
+            .iter()
+            .filter(|(_, event)| matches!(event, GameEvent::LifeLost(_)))
+            .count();
+
+        let done = self.game.is_over();
+        let mut reward = (self.game.dragons[0].score - score) as f32 - lives_lost as f32 * LIFE_LOST_PENALTY;
+        if !done {
+            reward += ALIVE_REWARD;
+        }
+        (self.observe(), reward, done)
+    }
+
+    pub fn observe(&self) -> Observation {
+        Observation::from_game(&self.game, 0)
+    }
+
+    /// 当前的对局，用于统计分数或渲染
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obstacle::{Obstacle, ObstacleType};
+    use crate::SCREEN_HEIGHT;
+
+    /// 障碍物停在屏幕外，清掉道具和危险
+    fn quiet_env() -> Env {
+        let mut env = Env::new(Difficulty::Normal);
+        env.reset(1);
+        env.game.obstacle = Obstacle::placed(SCREEN_WIDTH * 2, SCREEN_HEIGHT / 2, 10, ObstacleType::Static);
+        env.game.powerups.clear();
+        env.game.hazards.clear();
+        env
+    }
+
+    /// 第一条龙下一帧掉出屏幕
+    fn falling(env: &mut Env) {
+        env.game.dragons[0].player.y = SCREEN_HEIGHT + 1;
+    }
+
+    #[test]
+    fn reward_is_score_change_plus_alive_reward() {
+        let mut env = quiet_env();
+        let (_, reward, done) = env.step(EnvAction::Idle);
+        assert_eq!((reward, done), (ALIVE_REWARD, false));
+
+        let player = &env.game.dragons[0].player;
+        env.game.obstacle = Obstacle::placed(player.x, player.y, 10, ObstacleType::Laser);
+        let value = env.game.obstacle.get_score_value();
+        let (_, reward, done) = env.step(EnvAction::Idle);
+        assert_eq!((reward, done), (value as f32 + ALIVE_REWARD, false));
+    }
+
+    #[test]
+    fn losing_a_life_is_penalized() {
+        let mut env = quiet_env();
+        let lives = env.game.dragons[0].lives;
+        falling(&mut env);
+        let (observation, reward, done) = env.step(EnvAction::Idle);
+        assert_eq!((reward, done), (ALIVE_REWARD - LIFE_LOST_PENALTY, false));
+        assert_eq!(observation.lives, lives - 1);
+    }
+
+    #[test]
+    fn done_stays_true_once_the_game_is_over() {
+        let mut env = quiet_env();
+        env.game.dragons[0].lives = 1;
+        falling(&mut env);
+        // 最后一条命没有存活奖励
+        let (observation, reward, done) = env.step(EnvAction::Flap);
+        assert_eq!((reward, done, observation.lives), (-LIFE_LOST_PENALTY, true, 0));
+
+        let ticks = env.game().ticks;
+        for action in EnvAction::ALL {
+            assert_eq!(env.step(action), (observation, 0.0, true));
+        }
+        assert_eq!(env.game().ticks, ticks);
+    }
+
+    #[test]
+    fn reset_with_a_seed_is_deterministic() {
+        let play = |env: &mut Env| {
+            let mut steps = vec![(env.reset(42), 0.0, false)];
+            for i in 0..600 {
+                steps.push(env.step(EnvAction::ALL[i * 7 % EnvAction::ALL.len()]));
+            }
+            steps
+        };
+        let mut env = Env::new(Difficulty::Hard);
+        let first = play(&mut env);
+        assert_eq!(play(&mut env), first);
+        assert_eq!(play(&mut Env::new(Difficulty::Hard)), first);
+        assert_ne!(env.reset(43), Env::new(Difficulty::Hard).reset(42), "different seeds should differ");
+    }
+}
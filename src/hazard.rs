@@ -0,0 +1,188 @@
+use bracket_lib::prelude::*;
+use crate::player::Player;
+use crate::render::Canvas;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use serde::{Deserialize, Serialize};
+
+/// 阵风和陨石比障碍物多移动的格数
+const GUST_SPEED: i32 = 2;
+const METEOR_SPEED: i32 = 2;
+/// 阵风把龙吹动的格数，以及阵风的半高
+const GUST_PUSH: i32 = 3;
+const GUST_HALF_HEIGHT: i32 = 2;
+/// 闪电的长度和周期（逻辑帧）：每个周期最后一段时间劈下来
+const LIGHTNING_LENGTH: i32 = 8;
+const LIGHTNING_CYCLE: i32 = 90;
+const LIGHTNING_STRIKE: i32 = 25;
+/// 劈下来之前闪烁提示的逻辑帧数
+const LIGHTNING_WARNING: i32 = 20;
+/// 陨石出现在屏幕右侧外的距离，进入屏幕之前在右边缘提示
+const METEOR_LEAD: i32 = 20;
+
+/// 各个生物群系特有的危险
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HazardKind {
+    Stalactite, // 钟乳石 - 滚动到一定位置后落下
+    Gust,       // 阵风 - 把龙向上或向下吹，不会致命
+    Lightning,  // 闪电 - 周期性地劈下来
+    Meteor,     // 陨石 - 比障碍物更快地横穿屏幕
+}
+
+impl HazardKind {
+    pub const ALL: [HazardKind; 4] = [HazardKind::Stalactite, HazardKind::Gust, HazardKind::Lightning, HazardKind::Meteor];
+
+    /// 碰到后会失去生命
+    pub fn is_lethal(&self) -> bool {
+        *self != HazardKind::Gust
+    }
+
+    /// 可以被火球摧毁
+    pub fn is_destructible(&self) -> bool {
+        matches!(self, HazardKind::Stalactite | HazardKind::Meteor)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub x: i32,
+    pub y: i32,
+    /// 上一个逻辑帧的横坐标，移动中扫过的格子都算碰到
+    prev_x: i32,
+    timer: i32,
+    /// 阵风吹动的方向
+    direction: i32,
+    /// 钟乳石开始下落的横坐标
+    fall_x: i32,
+}
+
+impl Hazard {
+    /// 在屏幕右侧生成一个危险
+    pub fn spawn(kind: HazardKind, random: &mut RandomNumberGenerator) -> Self {
+        let mut hazard = Hazard {
+            kind,
+            x: SCREEN_WIDTH - 1,
+            y: 0,
+            prev_x: SCREEN_WIDTH - 1,
+            timer: 0,
+            direction: 1,
+            fall_x: 0,
+        };
+        match kind {
+            HazardKind::Stalactite => hazard.fall_x = random.range(15, 60),
+            HazardKind::Gust => {
+                hazard.y = random.range(8, SCREEN_HEIGHT - 8);
+                hazard.direction = if random.range(0, 2) == 0 { -1 } else { 1 };
+            }
+            HazardKind::Lightning => {
+                hazard.y = random.range(5, SCREEN_HEIGHT - 5 - LIGHTNING_LENGTH);
+                hazard.timer = random.range(0, LIGHTNING_CYCLE);
+            }
+            HazardKind::Meteor => {
+                hazard.x = SCREEN_WIDTH + METEOR_LEAD;
+                hazard.y = random.range(5, SCREEN_HEIGHT - 5);
+            }
+        }
+        hazard.prev_x = hazard.x;
+        hazard
+    }
+
+    /// 每个逻辑帧调用一次，`step` 是障碍物移动的格数
+    pub fn update(&mut self, step: i32) {
+        self.prev_x = self.x;
+        self.timer += 1;
+        match self.kind {
+            HazardKind::Stalactite => {
+                self.x -= step;
+                if self.x <= self.fall_x && self.timer % 2 == 0 {
+                    self.y += 1;
+                }
+            }
+            HazardKind::Gust => self.x -= step + GUST_SPEED,
+            HazardKind::Lightning => self.x -= step,
+            HazardKind::Meteor => self.x -= step + METEOR_SPEED,
+        }
+    }
+
+    /// 离开屏幕后移除
+    pub fn is_active(&self) -> bool {
+        self.x > -3 && self.y < SCREEN_HEIGHT
+    }
+
+    /// 这个逻辑帧扫过的列（含两端）
+    pub fn span(&self) -> (i32, i32) {
+        (self.x, self.prev_x)
+    }
+
+    /// 这个逻辑帧是否扫过了龙所在的列
+    fn swept(&self, player: &Player) -> bool {
+        (self.x..=self.prev_x).contains(&player.x)
+    }
+
+    fn is_striking(&self) -> bool {
+        self.timer % LIGHTNING_CYCLE >= LIGHTNING_CYCLE - LIGHTNING_STRIKE
+    }
+
+    pub fn hits(&self, player: &Player) -> bool {
+        if !self.swept(player) {
+            return false;
+        }
+        match self.kind {
+            HazardKind::Stalactite => (self.y - 1..=self.y).contains(&player.y),
+            HazardKind::Gust => false,
+            HazardKind::Lightning => self.is_striking() && (self.y..self.y + LIGHTNING_LENGTH).contains(&player.y),
+            HazardKind::Meteor => player.y == self.y,
+        }
+    }
+
+    /// 阵风扫过龙时把它吹动的格数（向下为正）
+    pub fn push(&self, player: &Player) -> i32 {
+        if self.kind == HazardKind::Gust && self.swept(player) && (player.y - self.y).abs() <= GUST_HALF_HEIGHT {
+            self.direction * GUST_PUSH
+        } else {
+            0
+        }
+    }
+
+    pub fn render(&self, ctx: &mut impl Canvas) {
+        match self.kind {
+            HazardKind::Stalactite => {
+                if self.y > 0 {
+                    ctx.set(self.x, self.y - 1, GRAY, BLACK, to_cp437('|'));
+                }
+                ctx.set(self.x, self.y, LIGHT_GRAY, BLACK, to_cp437('V'));
+            }
+            HazardKind::Gust => {
+                for dy in -GUST_HALF_HEIGHT..=GUST_HALF_HEIGHT {
+                    ctx.set(self.x + dy.abs(), self.y + dy, LIGHT_CYAN, BLACK, to_cp437('~'));
+                }
+                let arrow = if self.direction < 0 { '^' } else { 'v' };
+                ctx.set(self.x + GUST_HALF_HEIGHT + 1, self.y, WHITE, BLACK, to_cp437(arrow));
+            }
+            HazardKind::Lightning => {
+                let phase = self.timer % LIGHTNING_CYCLE;
+                ctx.set(self.x, self.y - 1, GRAY, BLACK, to_cp437('≈'));
+                if self.is_striking() {
+                    for y in self.y..self.y + LIGHTNING_LENGTH {
+                        let (glyph, color) = if (y + self.timer / 3) % 2 == 0 { ('/', YELLOW) } else { ('\\', WHITE) };
+                        ctx.set(self.x, y, color, BLACK, to_cp437(glyph));
+                    }
+                } else if phase >= LIGHTNING_CYCLE - LIGHTNING_STRIKE - LIGHTNING_WARNING && phase % 4 < 2 {
+                    ctx.set(self.x, self.y, YELLOW, BLACK, to_cp437('!'));
+                }
+            }
+            HazardKind::Meteor => {
+                if self.x >= SCREEN_WIDTH {
+                    // 还没进入屏幕时在右边缘闪烁提示
+                    if self.timer % 6 < 3 {
+                        ctx.set(SCREEN_WIDTH - 1, self.y, RED, BLACK, to_cp437('!'));
+                    }
+                    return;
+                }
+                ctx.set(self.x, self.y, ORANGE, BLACK, to_cp437('*'));
+                ctx.set(self.x + 1, self.y, RED, BLACK, to_cp437('-'));
+                ctx.set(self.x + 2, self.y, DARK_RED, BLACK, to_cp437('-'));
+            }
+        }
+    }
+}
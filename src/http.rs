@@ -0,0 +1,237 @@
+//! 排行榜用的极简 HTTP/1.1 实现，只支持带 Content-Length 的短连接请求
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// 请求体大小上限
+const MAX_BODY: usize = 4 * 1024 * 1024;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    /// 从连接中读取一个完整请求
+    pub fn read_from(stream: impl Read) -> io::Result<Request> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+            _ => return Err(invalid("malformed request line")),
+        };
+
+        let content_length = read_headers(&mut reader)?;
+        let body = read_body(&mut reader, content_length)?;
+
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), parse_query(query)),
+            None => (target, Vec::new()),
+        };
+        Ok(Request { method, path, query, body })
+    }
+
+    pub fn query_param(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// 写出一个 JSON 响应并关闭连接
+pub fn write_response(mut stream: impl Write, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        409 => "Conflict",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// `http://host:port/prefix` 形式的服务地址
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    host: String,
+    port: u16,
+    prefix: String,
+}
+
+impl Endpoint {
+    pub fn parse(url: &str) -> io::Result<Endpoint> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("only http:// endpoints are supported"))?;
+        let (authority, prefix) = match rest.find('/') {
+            Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid("invalid port"))?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        Ok(Endpoint {
+            host: host.to_string(),
+            port,
+            prefix: prefix.to_string(),
+        })
+    }
+
+    /// 发送请求，返回状态码和响应体
+    pub fn send(&self, method: &str, path: &str, body: Option<&str>, timeout: Duration) -> io::Result<(u16, String)> {
+        let addr = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| invalid("could not resolve host"))?;
+        let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let body = body.unwrap_or("");
+        write!(
+            stream,
+            "{} {}{} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            self.prefix,
+            path,
+            self.host,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| invalid("malformed status line"))?;
+        let content_length = read_headers(&mut reader)?;
+        let body = read_body(&mut reader, content_length)?;
+        Ok((status, body))
+    }
+}
+
+/// 读取请求头，返回 Content-Length
+fn read_headers(reader: &mut impl BufRead) -> io::Result<usize> {
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| invalid("invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(invalid("body too large"));
+    }
+    Ok(content_length)
+}
+
+fn read_body(reader: &mut impl Read, content_length: usize) -> io::Result<String> {
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body).map_err(|_| invalid("body is not UTF-8"))
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) => (k.to_string(), v.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(raw: &str) -> io::Result<Request> {
+        Request::read_from(raw.as_bytes())
+    }
+
+    #[test]
+    fn parses_request_line_and_query() {
+        let request = read("GET /top?limit=5&difficulty=Hard&flag HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/top"));
+        assert_eq!(request.query_param("limit"), Some("5"));
+        assert_eq!(request.query_param("difficulty"), Some("Hard"));
+        assert_eq!(request.query_param("flag"), Some(""));
+        assert_eq!(request.query_param("missing"), None);
+        assert!(request.body.is_empty());
+
+        let request = read("GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(request.path, "/");
+        assert!(request.query.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_request_lines() {
+        for raw in ["", "\r\n\r\n", "GET\r\n\r\n"] {
+            let err = read(raw).err().expect(raw);
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn reads_exactly_content_length_bytes() {
+        let request = read("POST /runs HTTP/1.1\r\ncontent-length: 5\r\n\r\nhello world").unwrap();
+        assert_eq!(request.body, "hello");
+
+        // 请求体比声明的短、长度不是数字、超过上限或者不是 UTF-8
+        assert_eq!(read("POST /runs HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort").err().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(read("POST /runs HTTP/1.1\r\nContent-Length: ten\r\n\r\n").err().unwrap().kind(), io::ErrorKind::InvalidData);
+        let too_large = format!("POST /runs HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1);
+        assert_eq!(read(&too_large).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        let mut raw = b"POST /runs HTTP/1.1\r\nContent-Length: 2\r\n\r\n".to_vec();
+        raw.extend([0xff, 0xfe]);
+        assert_eq!(Request::read_from(raw.as_slice()).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn parses_endpoints() {
+        let endpoint = Endpoint::parse("http://example.com:8080/api/").unwrap();
+        assert_eq!((endpoint.host.as_str(), endpoint.port, endpoint.prefix.as_str()), ("example.com", 8080, "/api"));
+        assert_eq!(Endpoint::parse("http://localhost").unwrap().port, 80);
+        for url in ["https://example.com", "http://:80", "http://host:port"] {
+            assert!(Endpoint::parse(url).is_err(), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,489 @@
+use crate::game::TickInput;
+use bracket_lib::prelude::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// 可以重新绑定按键的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Flap,
+    Dive,
+    Left,
+    Right,
+    Fire,
+    Pause,
+    Confirm,
+    Back,
+    // 双人模式中第二名玩家的动作
+    P2Flap,
+    P2Dive,
+    P2Left,
+    P2Right,
+    P2Fire,
+}
+
+/// 动作生效的场合，同一场合内的动作不能共用按键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputContext {
+    Gameplay,
+    Menu,
+}
+
+impl Action {
+    pub const ALL: [Action; 13] = [
+        Action::Flap,
+        Action::Dive,
+        Action::Left,
+        Action::Right,
+        Action::Fire,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::P2Flap,
+        Action::P2Dive,
+        Action::P2Left,
+        Action::P2Right,
+        Action::P2Fire,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Flap => "Flap",
+            Action::Dive => "Dive",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Fire => "Fire",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::P2Flap => "P2 Flap",
+            Action::P2Dive => "P2 Dive",
+            Action::P2Left => "P2 Left",
+            Action::P2Right => "P2 Right",
+            Action::P2Fire => "P2 Fire",
+        }
+    }
+
+    pub fn context(self) -> InputContext {
+        match self {
+            Action::Confirm | Action::Back => InputContext::Menu,
+            _ => InputContext::Gameplay,
+        }
+    }
+
+    fn default_keys(self) -> Vec<VirtualKeyCode> {
+        match self {
+            Action::Flap => vec![VirtualKeyCode::Up, VirtualKeyCode::Space],
+            Action::Dive => vec![VirtualKeyCode::Down],
+            Action::Left => vec![VirtualKeyCode::Left],
+            Action::Right => vec![VirtualKeyCode::Right],
+            Action::Fire => vec![VirtualKeyCode::X],
+            Action::Pause => vec![VirtualKeyCode::Escape],
+            Action::Confirm => vec![VirtualKeyCode::Return],
+            Action::Back => vec![VirtualKeyCode::Escape],
+            Action::P2Flap => vec![VirtualKeyCode::W],
+            Action::P2Dive => vec![VirtualKeyCode::S],
+            Action::P2Left => vec![VirtualKeyCode::A],
+            Action::P2Right => vec![VirtualKeyCode::D],
+            Action::P2Fire => vec![VirtualKeyCode::E],
+        }
+    }
+}
+
+/// 每名玩家的扇动、下冲、左移、右移、喷火动作
+pub const PLAYER_ACTIONS: [[Action; 5]; 2] = [
+    [Action::Flap, Action::Dive, Action::Left, Action::Right, Action::Fire],
+    [Action::P2Flap, Action::P2Dive, Action::P2Left, Action::P2Right, Action::P2Fire],
+];
+
+/// 可以绑定的按键，设置文件里按名字保存
+const BINDABLE_KEYS: [VirtualKeyCode; 60] = {
+    use VirtualKeyCode::*;
+    [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+        Up, Down, Left, Right, Space, Return, Escape, Tab, Back,
+        LShift, RShift, LControl, RControl, LAlt, RAlt,
+        Numpad0, Numpad2, Numpad4, Numpad6, Numpad8,
+        Comma, Period, Slash, Semicolon,
+    ]
+};
+
+/// 按键在设置文件和界面上显示的名字
+pub fn key_name(key: VirtualKeyCode) -> String {
+    format!("{:?}", key)
+}
+
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|&key| key_name(key) == name)
+}
+
+pub fn is_bindable(key: VirtualKeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+/// 菜单里固定使用的按键：上下移动光标，字母是各菜单项的快捷键（包括返回主菜单的 M）
+fn is_menu_hotkey(key: VirtualKeyCode) -> bool {
+    use VirtualKeyCode::*;
+    matches!(key, Up | Down) || BINDABLE_KEYS[..26].contains(&key)
+}
+
+/// 无法绑定按键的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindError {
+    /// 按键已被同一场合的其他动作使用
+    Conflict(Action),
+    /// 菜单动作不能使用菜单的固定按键
+    MenuHotkey,
+}
+
+/// 每个动作对应的按键
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "KeyNames", from = "KeyNames")]
+pub struct KeyBindings {
+    keys: HashMap<Action, Vec<VirtualKeyCode>>,
+}
+
+/// 设置文件中的格式：动作 -> 按键名
+type KeyNames = HashMap<Action, Vec<String>>;
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            keys: Action::ALL.iter().map(|&action| (action, action.default_keys())).collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> &[VirtualKeyCode] {
+        self.keys.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// 按键是否触发了动作
+    pub fn matches(&self, action: Action, key: VirtualKeyCode) -> bool {
+        self.keys(action).contains(&key)
+    }
+
+    /// 在某个场合下按键对应的动作
+    pub fn action(&self, key: VirtualKeyCode, context: InputContext) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|&action| action.context() == context && self.matches(action, key))
+    }
+
+    /// 当前按下的按键触发的所有动作（不区分场合）
+    pub fn active_actions<'a>(&self, keys_down: impl IntoIterator<Item = &'a VirtualKeyCode>) -> HashSet<Action> {
+        let keys_down: Vec<VirtualKeyCode> = keys_down.into_iter().copied().collect();
+        Action::ALL
+            .iter()
+            .copied()
+            .filter(|&action| self.keys(action).iter().any(|key| keys_down.contains(key)))
+            .collect()
+    }
+
+    /// 把动作绑定到一个按键上，替换原来的按键
+    pub fn bind(&mut self, action: Action, key: VirtualKeyCode) -> Result<(), BindError> {
+        if action.context() == InputContext::Menu && is_menu_hotkey(key) {
+            return Err(BindError::MenuHotkey);
+        }
+        if let Some(other) = self.action(key, action.context()) {
+            if other != action {
+                return Err(BindError::Conflict(other));
+            }
+        }
+        self.keys.insert(action, vec![key]);
+        Ok(())
+    }
+
+    /// 添加没有被同一场合其他动作占用、也不是菜单固定按键的按键
+    fn add_free_keys(&mut self, action: Action, keys: Vec<VirtualKeyCode>) {
+        for key in keys {
+            let reserved = action.context() == InputContext::Menu && is_menu_hotkey(key);
+            if !reserved && self.action(key, action.context()).is_none() {
+                self.keys.entry(action).or_default().push(key);
+            }
+        }
+    }
+}
+
+impl From<KeyBindings> for KeyNames {
+    fn from(bindings: KeyBindings) -> Self {
+        bindings
+            .keys
+            .into_iter()
+            .map(|(action, keys)| (action, keys.into_iter().map(key_name).collect()))
+            .collect()
+    }
+}
+
+impl From<KeyNames> for KeyBindings {
+    /// 先应用保存的按键，无法识别或冲突的按键被忽略；之后缺少的动作（例如新版本加入的）和
+    /// 一个可用按键都没有的动作使用不冲突的默认按键
+    fn from(names: KeyNames) -> Self {
+        let mut bindings = KeyBindings { keys: HashMap::new() };
+        for action in Action::ALL {
+            if let Some(names) = names.get(&action) {
+                let keys = names.iter().filter_map(|name| parse_key(name)).collect();
+                bindings.add_free_keys(action, keys);
+            }
+        }
+        for action in Action::ALL {
+            if bindings.keys(action).is_empty() {
+                bindings.add_free_keys(action, action.default_keys());
+            }
+        }
+        bindings
+    }
+}
+
+/// 按住移动或下冲键时，每隔多少个逻辑帧重复一次（约每秒 15 次）
+pub const HOLD_REPEAT_TICKS: u32 = 4;
+
+/// 各动作在当前帧的按下/按住/松开状态
+#[derive(Debug, Clone, Default)]
+pub struct ActionState {
+    held: HashSet<Action>,
+    pressed: HashSet<Action>,
+    released: HashSet<Action>,
+}
+
+impl ActionState {
+    /// 每帧调用一次，传入这一帧处于按下状态的所有动作
+    pub fn update(&mut self, active: HashSet<Action>) {
+        self.pressed = active.difference(&self.held).copied().collect();
+        self.released = self.held.difference(&active).copied().collect();
+        self.held = active;
+    }
+
+    /// 这一帧刚按下
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// 这一帧处于按下状态（包括刚按下）
+    pub fn held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    /// 这一帧刚松开
+    pub fn released(&self, action: Action) -> bool {
+        self.released.contains(&action)
+    }
+
+    /// 某名玩家这一帧刚按下的游戏动作，按下的瞬间立即生效
+    pub fn pressed_input(&self, player: usize) -> TickInput {
+        let [flap, dive, left, right, fire] = PLAYER_ACTIONS[player];
+        TickInput {
+            flap: self.pressed(flap),
+            dive: self.pressed(dive),
+            left: self.pressed(left),
+            right: self.pressed(right),
+            fire: self.pressed(fire),
+        }
+    }
+}
+
+/// 把按住的移动和下冲键转换成按固定间隔重复的逻辑帧输入，不依赖系统的按键重复
+///
+/// 扇动翅膀和喷火只在按下时触发一次，按住不会连续触发。
+#[derive(Debug, Clone, Default)]
+pub struct HoldRepeat {
+    countdown: HashMap<Action, u32>,
+}
+
+impl HoldRepeat {
+    /// 每个逻辑帧对每名玩家调用一次；`input` 是这一帧已有的输入（包括刚按下的动作），返回补上重复输入后的结果
+    pub fn tick(&mut self, state: &ActionState, player: usize, mut input: TickInput) -> TickInput {
+        let [_, dive, left, right, _] = PLAYER_ACTIONS[player];
+        for (action, bit) in [(dive, &mut input.dive), (left, &mut input.left), (right, &mut input.right)] {
+            let countdown = self.countdown.entry(action).or_default();
+            if !state.held(action) {
+                *countdown = 0;
+            } else if *bit {
+                *countdown = HOLD_REPEAT_TICKS;
+            } else if *countdown > 1 {
+                *countdown -= 1;
+            } else {
+                *bit = true;
+                *countdown = HOLD_REPEAT_TICKS;
+            }
+        }
+        input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VirtualKeyCode::*;
+
+    #[test]
+    fn rebinding_replaces_keys() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(bindings.bind(Action::Flap, J), Ok(()));
+        assert_eq!(bindings.keys(Action::Flap), &[J]);
+        assert!(!bindings.matches(Action::Flap, Space));
+        assert_eq!(bindings.action(J, InputContext::Gameplay), Some(Action::Flap));
+
+        // 重新绑定到自己已有的按键不算冲突
+        assert_eq!(bindings.bind(Action::Flap, J), Ok(()));
+        // 换下来的按键可以给别的动作用
+        assert_eq!(bindings.bind(Action::Dive, Space), Ok(()));
+        assert_eq!(bindings.active_actions(&[J, Space]), HashSet::from([Action::Flap, Action::Dive]));
+    }
+
+    #[test]
+    fn conflicts_are_checked_per_context() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(bindings.bind(Action::Dive, Up), Err(BindError::Conflict(Action::Flap)));
+        assert_eq!(bindings.bind(Action::P2Flap, X), Err(BindError::Conflict(Action::Fire)));
+        assert_eq!(bindings.bind(Action::Back, Return), Err(BindError::Conflict(Action::Confirm)));
+        assert_eq!(bindings, KeyBindings::default());
+
+        // 菜单和游戏中的动作可以共用按键
+        assert_eq!(bindings.bind(Action::Confirm, Space), Ok(()));
+        assert_eq!(bindings.action(Space, InputContext::Menu), Some(Action::Confirm));
+        assert_eq!(bindings.action(Space, InputContext::Gameplay), Some(Action::Flap));
+        assert_eq!(bindings.action(Escape, InputContext::Menu), Some(Action::Back));
+        assert_eq!(bindings.action(Escape, InputContext::Gameplay), Some(Action::Pause));
+    }
+
+    #[test]
+    fn menu_actions_cannot_take_menu_hotkeys() {
+        let mut bindings = KeyBindings::default();
+        for key in [Up, Down, M, D, Q] {
+            assert_eq!(bindings.bind(Action::Confirm, key), Err(BindError::MenuHotkey), "{:?}", key);
+            assert_eq!(bindings.bind(Action::Back, key), Err(BindError::MenuHotkey), "{:?}", key);
+        }
+        assert_eq!(bindings, KeyBindings::default());
+        assert_eq!(bindings.bind(Action::Back, Back), Ok(()));
+        assert_eq!(bindings.bind(Action::Confirm, Space), Ok(()));
+        // 游戏中的动作可以使用字母
+        assert_eq!(bindings.bind(Action::Pause, M), Ok(()));
+
+        let saved: KeyBindings = serde_json::from_str(r#"{"Confirm": ["Q"], "Back": ["Down", "Tab"]}"#).unwrap();
+        assert_eq!(saved.keys(Action::Confirm), &[Return]);
+        assert_eq!(saved.keys(Action::Back), &[Tab]);
+    }
+
+    #[test]
+    fn saved_keys_win_over_defaults_of_new_actions() {
+        // 旧版本的设置文件里还没有喷火和第二名玩家的动作
+        let json = r#"{"Flap": ["X"], "Dive": ["W"], "Left": ["E"]}"#;
+        let bindings: KeyBindings = serde_json::from_str(json).unwrap();
+        assert_eq!(bindings.keys(Action::Flap), &[X]);
+        assert_eq!(bindings.keys(Action::Dive), &[W]);
+        assert_eq!(bindings.keys(Action::Left), &[E]);
+        // 默认按键冲突的新动作先不绑定，不冲突的照常使用默认按键
+        assert!(bindings.keys(Action::Fire).is_empty());
+        assert!(bindings.keys(Action::P2Flap).is_empty());
+        assert!(bindings.keys(Action::P2Fire).is_empty());
+        assert_eq!(bindings.keys(Action::P2Dive), &[S]);
+        assert_eq!(bindings.keys(Action::Right), &[VirtualKeyCode::Right]);
+    }
+
+    #[test]
+    fn bindings_round_trip_through_key_names() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Flap, Key1).unwrap();
+        bindings.bind(Action::P2Fire, Numpad0).unwrap();
+
+        let json = serde_json::to_string(&bindings).unwrap();
+        assert!(json.contains(r#""Flap":["Key1"]"#), "{}", json);
+        assert_eq!(serde_json::from_str::<KeyBindings>(&json).unwrap(), bindings);
+    }
+
+    #[test]
+    fn loading_repairs_bad_key_names() {
+        let json = r#"{
+            "Flap": ["F", "NotAKey"],
+            "Dive": ["F"],
+            "Left": ["F13"],
+            "Right": []
+        }"#;
+        let bindings: KeyBindings = serde_json::from_str(json).unwrap();
+        // 无法识别的按键被忽略
+        assert_eq!(bindings.keys(Action::Flap), &[F]);
+        // 和同场合动作冲突、或者一个可用按键都没有时退回默认按键
+        assert_eq!(bindings.keys(Action::Dive), &[Down]);
+        assert_eq!(bindings.keys(Action::Left), &[VirtualKeyCode::Left]);
+        assert_eq!(bindings.keys(Action::Right), &[VirtualKeyCode::Right]);
+        // 文件里没有的动作使用默认按键
+        assert_eq!(bindings.keys(Action::Pause), &[Escape]);
+        assert_eq!(bindings.keys(Action::P2Flap), &[W]);
+    }
+
+    fn update(state: &mut ActionState, active: &[Action]) {
+        state.update(active.iter().copied().collect());
+    }
+
+    #[test]
+    fn action_state_tracks_transitions() {
+        let mut state = ActionState::default();
+        update(&mut state, &[Action::Flap]);
+        assert!(state.pressed(Action::Flap) && state.held(Action::Flap) && !state.released(Action::Flap));
+
+        // 按住时只有第一帧算按下
+        update(&mut state, &[Action::Flap, Action::Dive]);
+        assert!(!state.pressed(Action::Flap) && state.held(Action::Flap));
+        assert!(state.pressed(Action::Dive));
+
+        update(&mut state, &[Action::Dive]);
+        assert!(state.released(Action::Flap) && !state.held(Action::Flap));
+        assert!(!state.pressed(Action::Dive) && state.held(Action::Dive));
+
+        update(&mut state, &[]);
+        assert!(state.released(Action::Dive));
+        update(&mut state, &[]);
+        assert!(!state.released(Action::Dive));
+    }
+
+    #[test]
+    fn press_and_release_in_the_same_frame() {
+        // 同一帧内按下又松开的键只出现在这一帧的按键事件里，和按住的键一起算作按下
+        let bindings = KeyBindings::default();
+        let tapped = bindings.keys(Action::P2Flap)[0];
+        let held = bindings.keys(Action::P2Fire)[0];
+        let mut state = ActionState::default();
+        state.update(bindings.active_actions([held].iter().chain(Some(&tapped))));
+        let input = state.pressed_input(1);
+        assert!(input.flap && input.fire && !input.dive);
+        assert_eq!(state.pressed_input(0), TickInput::default());
+
+        // 下一帧没有按键事件，敲击的键随即松开，按住的键保持
+        state.update(bindings.active_actions(&[held]));
+        assert!(state.released(Action::P2Flap) && !state.held(Action::P2Flap));
+        assert!(state.held(Action::P2Fire) && !state.released(Action::P2Fire));
+        assert_eq!(state.pressed_input(1), TickInput::default());
+    }
+
+    #[test]
+    fn held_keys_repeat_after_a_delay() {
+        let mut state = ActionState::default();
+        let mut repeat = HoldRepeat::default();
+        update(&mut state, &[Action::Left, Action::Flap]);
+
+        // 按下的一帧立即生效，之后每 HOLD_REPEAT_TICKS 帧重复一次
+        let mut fired = Vec::new();
+        for tick in 0..HOLD_REPEAT_TICKS * 3 + 1 {
+            let pressed = if tick == 0 { state.pressed_input(0) } else { TickInput::default() };
+            let input = repeat.tick(&state, 0, pressed);
+            if input.left {
+                fired.push(tick);
+            }
+            // 扇动翅膀按住不重复
+            assert_eq!(input.flap, tick == 0);
+        }
+        let interval = HOLD_REPEAT_TICKS;
+        assert_eq!(fired, vec![0, interval, interval * 2, interval * 3]);
+
+        // 松开后计时重置，再次按下立即生效
+        update(&mut state, &[]);
+        assert!(!repeat.tick(&state, 0, TickInput::default()).left);
+        update(&mut state, &[Action::Left]);
+        assert!(repeat.tick(&state, 0, state.pressed_input(0)).left);
+        assert!(!repeat.tick(&state, 0, TickInput::default()).left);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::difficulty::Difficulty;
+use crate::http::Endpoint;
+use crate::replay::Replay;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::time::Duration;
+
+/// 排行榜地址的环境变量，例如 `http://127.0.0.1:7878`
+pub const ENDPOINT_ENV: &str = "FLAPPY_LEADERBOARD_URL";
+/// 本地排行榜服务的默认监听地址
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+/// 网络请求超时，服务不可用时尽快退回本地分数
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 提交到在线排行榜的一局游戏，服务端会重新模拟录像来验证分数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSubmission {
+    pub run_id: String,
+    pub score: i32,
+    pub difficulty: Difficulty,
+    pub seed: u64,
+    pub replay_hash: String,
+    pub replay: Replay,
+}
+
+impl RunSubmission {
+    pub fn new(run_id: String, score: i32, replay: Replay) -> Self {
+        RunSubmission {
+            run_id,
+            score,
+            difficulty: replay.difficulty.clone(),
+            seed: replay.seed,
+            replay_hash: replay.hash(),
+            replay,
+        }
+    }
+
+    /// 不含录像的排行榜条目
+    pub fn entry(&self) -> LeaderboardEntry {
+        LeaderboardEntry {
+            run_id: self.run_id.clone(),
+            score: self.score,
+            difficulty: self.difficulty.clone(),
+            seed: self.seed,
+            replay_hash: self.replay_hash.clone(),
+        }
+    }
+}
+
+/// 排行榜上的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub run_id: String,
+    pub score: i32,
+    pub difficulty: Difficulty,
+    pub seed: u64,
+    pub replay_hash: String,
+}
+
+/// 在线排行榜客户端
+#[derive(Clone)]
+pub struct LeaderboardClient {
+    endpoint: Endpoint,
+}
+
+impl LeaderboardClient {
+    pub fn new(url: &str) -> io::Result<Self> {
+        Ok(LeaderboardClient {
+            endpoint: Endpoint::parse(url)?,
+        })
+    }
+
+    /// 从环境变量读取排行榜地址，没有配置时返回 `None`
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var(ENDPOINT_ENV).ok()?;
+        match Self::new(&url) {
+            Ok(client) => Some(client),
+            Err(err) => {
+                eprintln!("Ignoring invalid {}={}: {}", ENDPOINT_ENV, url, err);
+                None
+            }
+        }
+    }
+
+    /// 提交一局游戏
+    pub fn submit(&self, run: &RunSubmission) -> io::Result<()> {
+        let body = serde_json::to_string(run).map_err(io::Error::other)?;
+        let (status, response) = self.endpoint.send("POST", "/runs", Some(&body), TIMEOUT)?;
+        match status {
+            200 | 201 => Ok(()),
+            _ => Err(io::Error::other(format!("server returned {}: {}", status, response))),
+        }
+    }
+
+    /// 获取全局排行，`difficulty` 为 `None` 时不区分难度
+    pub fn top(&self, difficulty: Option<&Difficulty>, limit: usize) -> io::Result<Vec<LeaderboardEntry>> {
+        let path = match difficulty {
+            Some(difficulty) => format!("/top?limit={}&difficulty={:?}", limit, difficulty),
+            None => format!("/top?limit={}", limit),
+        };
+        let (status, body) = self.endpoint.send("GET", &path, None, TIMEOUT)?;
+        if status != 200 {
+            return Err(io::Error::other(format!("server returned {}", status)));
+        }
+        serde_json::from_str(&body).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{write_response, Request};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// 回环地址上只处理一个请求的服务，返回收到的请求
+    fn serve_once(status: u16, body: &str) -> (LeaderboardClient, JoinHandle<Request>) {
+        let body = body.to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let request = Request::read_from(&stream).unwrap();
+            write_response(&stream, status, &body).unwrap();
+            request
+        });
+        (LeaderboardClient::new(&url).unwrap(), server)
+    }
+
+    fn run() -> RunSubmission {
+        let mut replay = Replay::new(3, Difficulty::Hard);
+        replay.inputs = vec![0, 1, 0];
+        RunSubmission::new("run-1".to_string(), 0, replay)
+    }
+
+    #[test]
+    fn submit_posts_the_run() {
+        let (client, server) = serve_once(201, "{}");
+        client.submit(&run()).unwrap();
+        let request = server.join().unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/api/runs"));
+        assert_eq!(serde_json::from_str::<RunSubmission>(&request.body).unwrap(), run());
+
+        // 重复提交返回 200 也算成功，被拒绝时带上服务端的原因
+        let (client, _) = serve_once(200, "{}");
+        client.submit(&run()).unwrap();
+        let (client, _) = serve_once(422, r#"{"error":"replay hash mismatch"}"#);
+        let err = client.submit(&run()).unwrap_err();
+        assert!(err.to_string().contains("422") && err.to_string().contains("replay hash mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn top_fetches_entries() {
+        let entries = serde_json::to_string(&[run().entry()]).unwrap();
+        let (client, server) = serve_once(200, &entries);
+        assert_eq!(client.top(Some(&Difficulty::Hard), 5).unwrap(), vec![run().entry()]);
+        let request = server.join().unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/api/top"));
+        assert_eq!(request.query_param("limit"), Some("5"));
+        assert_eq!(request.query_param("difficulty"), Some("Hard"));
+
+        let (client, server) = serve_once(200, "[]");
+        assert!(client.top(None, 10).unwrap().is_empty());
+        assert_eq!(server.join().unwrap().query_param("difficulty"), None);
+
+        let (client, _) = serve_once(500, "{}");
+        assert!(client.top(None, 10).is_err());
+        let (client, _) = serve_once(200, "not json");
+        assert_eq!(client.top(None, 10).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
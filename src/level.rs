@@ -0,0 +1,490 @@
+use bracket_lib::prelude::*;
+use crate::difficulty::Difficulty;
+use crate::obstacle::{Obstacle, ObstacleType};
+use crate::powerup::{PowerUp, PowerUpType};
+use crate::render::Canvas;
+use crate::storage::{write_atomic, JsonStore};
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// 战役关卡所在的目录，按文件名顺序组成战役
+pub const CAMPAIGN_DIR: &str = "assets/levels";
+/// 战役进度文件名，和玩家的其他数据放在同一目录
+const PROGRESS_FILE: &str = "campaign.json";
+/// 最小的空隙，和随机生成的障碍物一样
+pub const MIN_GAP_SIZE: i32 = 4;
+/// 变速点允许的速度（每个逻辑帧滚动的格数）
+pub const MAX_SPEED: i32 = 3;
+/// 道具提前出现在屏幕右侧外的距离，和随机生成的道具一致
+const POWERUP_LEAD: i32 = 10;
+
+/// 手工编写的关卡，以 JSON 文件保存
+///
+/// 所有位置都是从关卡开头算起的水平距离（格），开局时和屏幕坐标一致。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    /// 决定生命数和重力等，滚动速度由关卡自己指定
+    #[serde(default = "default_difficulty")]
+    pub difficulty: Difficulty,
+    /// 开局时的滚动速度
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    /// 终点线的位置，龙飞过终点线即通关
+    pub finish: i32,
+    #[serde(default)]
+    pub obstacles: Vec<LevelObstacle>,
+    #[serde(default)]
+    pub powerups: Vec<LevelPowerUp>,
+    #[serde(default)]
+    pub speed_changes: Vec<SpeedChange>,
+}
+
+fn default_difficulty() -> Difficulty {
+    Difficulty::Normal
+}
+
+fn default_speed() -> i32 {
+    1
+}
+
+/// 关卡中的一个障碍物
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelObstacle {
+    pub x: i32,
+    pub gap_y: i32,
+    pub gap_size: i32,
+    #[serde(default = "default_obstacle_type")]
+    pub obstacle_type: ObstacleType,
+}
+
+fn default_obstacle_type() -> ObstacleType {
+    ObstacleType::Static
+}
+
+/// 关卡中的一个道具
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelPowerUp {
+    pub x: i32,
+    pub y: i32,
+    pub power_type: PowerUpType,
+}
+
+/// 滚动距离达到 `at` 时改变滚动速度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeedChange {
+    pub at: i32,
+    pub speed: i32,
+}
+
+impl Level {
+    /// 只有终点线的空关卡，用于在编辑器里新建关卡
+    pub fn empty(name: &str) -> Self {
+        Level {
+            name: name.to_string(),
+            difficulty: default_difficulty(),
+            speed: default_speed(),
+            finish: SCREEN_WIDTH * 4,
+            obstacles: Vec::new(),
+            powerups: Vec::new(),
+            speed_changes: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))
+    }
+
+    /// 解析并检查关卡，各列表按位置排序
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut level: Level = serde_json::from_str(content).map_err(|err| err.to_string())?;
+        level.sort();
+        level.validate()?;
+        Ok(level)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        write_atomic(path, content.as_bytes())
+    }
+
+    pub fn sort(&mut self) {
+        self.obstacles.sort_by_key(|obstacle| obstacle.x);
+        self.powerups.sort_by_key(|powerup| powerup.x);
+        self.speed_changes.sort_by_key(|change| change.at);
+    }
+
+    /// 检查位置和大小是否都在合理范围内
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Level name cannot be empty".to_string());
+        }
+        if self.finish <= 0 {
+            return Err("Finish line must be after the start".to_string());
+        }
+        for speed in std::iter::once(self.speed).chain(self.speed_changes.iter().map(|change| change.speed)) {
+            if !(1..=MAX_SPEED).contains(&speed) {
+                return Err(format!("Speed {} is not between 1 and {}", speed, MAX_SPEED));
+            }
+        }
+        for obstacle in &self.obstacles {
+            if !(0..self.finish).contains(&obstacle.x) {
+                return Err(format!("Obstacle at {} is outside the level", obstacle.x));
+            }
+            if !(0..SCREEN_HEIGHT).contains(&obstacle.gap_y) {
+                return Err(format!("Obstacle at {} has its gap off screen", obstacle.x));
+            }
+            if obstacle.gap_size < MIN_GAP_SIZE {
+                return Err(format!("Obstacle at {} has a gap smaller than {}", obstacle.x, MIN_GAP_SIZE));
+            }
+        }
+        if let Some(pair) = self.obstacles.windows(2).find(|pair| pair[0].x == pair[1].x) {
+            return Err(format!("Two obstacles at {}", pair[0].x));
+        }
+        for powerup in &self.powerups {
+            if !(0..self.finish).contains(&powerup.x) || !(0..SCREEN_HEIGHT).contains(&powerup.y) {
+                return Err(format!("Power-up at {},{} is outside the level", powerup.x, powerup.y));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 按关卡进行的对局的进度，保存在 `Game` 里
+///
+/// 同一时间只有一个障碍物在场：前一个被越过（或者玩家失去生命）后，下一个出现在它在关卡中的位置。
+#[derive(Serialize, Deserialize)]
+pub struct LevelRun {
+    pub level: Level,
+    /// 已经滚动的距离
+    pub distance: i32,
+    /// 当前的滚动速度
+    pub speed: i32,
+    next_obstacle: usize,
+    next_powerup: usize,
+    next_speed_change: usize,
+}
+
+impl LevelRun {
+    pub fn new(level: Level) -> Self {
+        LevelRun {
+            speed: level.speed,
+            level,
+            distance: 0,
+            next_obstacle: 0,
+            next_powerup: 0,
+            next_speed_change: 0,
+        }
+    }
+
+    /// 从滚动距离 `distance` 开始，之前的障碍物、道具和变速点都已经过去，用于编辑器里从中途试玩
+    pub fn starting_at(level: Level, distance: i32) -> Self {
+        let mut run = LevelRun::new(level);
+        run.distance = distance;
+        run.next_obstacle = run.level.obstacles.iter().take_while(|obstacle| obstacle.x < distance).count();
+        run.next_powerup = run.level.powerups.iter().take_while(|powerup| powerup.x < distance).count();
+        run.apply_speed_changes();
+        run
+    }
+
+    /// 和障碍物一起向前滚动一个逻辑帧
+    pub fn advance(&mut self) {
+        self.distance += self.speed;
+        self.apply_speed_changes();
+    }
+
+    fn apply_speed_changes(&mut self) {
+        while let Some(change) = self.level.speed_changes.get(self.next_speed_change).filter(|change| change.at <= self.distance) {
+            self.speed = change.speed;
+            self.next_speed_change += 1;
+        }
+    }
+
+    /// 即将滚进屏幕的道具，屏幕坐标和关卡位置保持一致
+    pub fn due_powerups(&mut self) -> Vec<PowerUp> {
+        let mut powerups = Vec::new();
+        while let Some(powerup) = self.level.powerups.get(self.next_powerup) {
+            let x = powerup.x - self.distance;
+            if x > SCREEN_WIDTH + POWERUP_LEAD {
+                break;
+            }
+            powerups.push(PowerUp::new(x, powerup.y, powerup.power_type.clone()));
+            self.next_powerup += 1;
+        }
+        powerups
+    }
+
+    /// 下一个障碍物；关卡里的障碍物用完后，在终点线之后放一个永远到不了的障碍物
+    pub fn next_obstacle(&mut self) -> Obstacle {
+        let obstacle = match self.level.obstacles.get(self.next_obstacle) {
+            Some(obstacle) => obstacle.clone(),
+            None => LevelObstacle {
+                x: self.level.finish + SCREEN_WIDTH,
+                gap_y: SCREEN_HEIGHT / 2,
+                gap_size: SCREEN_HEIGHT * 2,
+                obstacle_type: ObstacleType::Static,
+            },
+        };
+        self.next_obstacle = (self.next_obstacle + 1).min(self.level.obstacles.len());
+        Obstacle::placed(obstacle.x - self.distance, obstacle.gap_y, obstacle.gap_size, obstacle.obstacle_type)
+    }
+
+    /// 终点线在屏幕上的横坐标
+    pub fn finish_x(&self) -> i32 {
+        self.level.finish - self.distance
+    }
+
+    /// 完成的比例（0 到 1）
+    pub fn progress(&self) -> f32 {
+        (self.distance as f32 / self.level.finish as f32).clamp(0.0, 1.0)
+    }
+
+    pub fn render(&self, ctx: &mut impl Canvas) {
+        let x = self.finish_x();
+        if (0..SCREEN_WIDTH).contains(&x) {
+            for y in 0..SCREEN_HEIGHT {
+                let color = if y % 2 == 0 { WHITE } else { GRAY };
+                ctx.set(x, y, color, BLACK, to_cp437('#'));
+            }
+        }
+        ctx.print(0, 1, format!("Level: {}  |  {:.0}%", self.level.name, self.progress() * 100.0));
+    }
+}
+
+/// 战役：一个目录下按文件名排列的关卡
+#[derive(Debug, Clone, Default)]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CampaignLevel {
+    /// 文件名（不含扩展名），用于记录进度
+    pub id: String,
+    pub level: Level,
+}
+
+impl Campaign {
+    /// 读取目录下所有的 `.json` 关卡，任何一个关卡有错误都会失败
+    pub fn load_dir(dir: &Path) -> io::Result<Self> {
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<_>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        paths.sort();
+        let levels = paths
+            .iter()
+            .map(|path| {
+                let id = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                Level::load(path).map(|level| CampaignLevel { id, level })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Campaign { levels })
+    }
+
+    /// 第一关总是解锁的，之后每一关要先通过前一关
+    pub fn is_unlocked(&self, index: usize, progress: &CampaignProgress) -> bool {
+        match index {
+            0 => true,
+            _ => self.levels.get(index - 1).is_some_and(|previous| progress.is_completed(&previous.id)),
+        }
+    }
+}
+
+/// 一个关卡的最好成绩
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LevelRecord {
+    pub best_score: i32,
+}
+
+/// 玩家通过的关卡
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CampaignProgress {
+    pub completed: BTreeMap<String, LevelRecord>,
+}
+
+impl CampaignProgress {
+    pub fn is_completed(&self, id: &str) -> bool {
+        self.completed.contains_key(id)
+    }
+
+    /// 记录一次通关，保留最好成绩
+    pub fn record(&mut self, id: &str, score: i32) {
+        let record = self.completed.entry(id.to_string()).or_default();
+        record.best_score = record.best_score.max(score);
+    }
+}
+
+pub type CampaignStore = JsonStore<CampaignProgress>;
+
+impl CampaignStore {
+    /// 打开 `dir` 目录下的文件
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(PROGRESS_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    fn obstacle(x: i32) -> LevelObstacle {
+        LevelObstacle { x, gap_y: 25, gap_size: 8, obstacle_type: ObstacleType::Static }
+    }
+
+    fn sample() -> Level {
+        let mut level = Level::empty("Test");
+        level.finish = 300;
+        level.obstacles = vec![obstacle(100), obstacle(40), obstacle(200)];
+        level
+    }
+
+    #[test]
+    fn campaign_levels_parse() {
+        let campaign = Campaign::load_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join(CAMPAIGN_DIR)).unwrap();
+        assert!(!campaign.levels.is_empty());
+        for entry in &campaign.levels {
+            assert_eq!(entry.level.validate(), Ok(()), "{}", entry.id);
+            assert!(entry.level.obstacles.windows(2).all(|pair| pair[0].x < pair[1].x), "{}", entry.id);
+        }
+    }
+
+    #[test]
+    fn parse_sorts_and_round_trips() {
+        let content = serde_json::to_string(&sample()).unwrap();
+        let parsed = Level::parse(&content).unwrap();
+        let xs: Vec<i32> = parsed.obstacles.iter().map(|obstacle| obstacle.x).collect();
+        assert_eq!(xs, vec![40, 100, 200]);
+        assert_eq!(Level::parse(&serde_json::to_string(&parsed).unwrap()), Ok(parsed));
+        assert!(Level::parse("{\"name\": \"Broken\"}").is_err());
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_obstacles() {
+        let broken: [fn(&mut LevelObstacle); 5] = [
+            |obstacle| obstacle.x = -1,
+            |obstacle| obstacle.x = 300,
+            |obstacle| obstacle.gap_y = SCREEN_HEIGHT,
+            |obstacle| obstacle.gap_y = -1,
+            |obstacle| obstacle.gap_size = MIN_GAP_SIZE - 1,
+        ];
+        for (i, breaking) in broken.iter().enumerate() {
+            let mut level = sample();
+            breaking(&mut level.obstacles[0]);
+            level.sort();
+            assert!(level.validate().is_err(), "case {}", i);
+        }
+
+        let mut level = sample();
+        level.powerups.push(LevelPowerUp { x: 50, y: SCREEN_HEIGHT, power_type: PowerUpType::Shield });
+        assert!(level.validate().is_err());
+        let mut level = sample();
+        level.finish = 0;
+        assert!(level.validate().is_err());
+        let mut level = sample();
+        level.name = "  ".to_string();
+        assert!(level.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_duplicate_obstacles() {
+        let mut level = sample();
+        level.obstacles.push(obstacle(100));
+        level.sort();
+        assert_eq!(level.validate(), Err("Two obstacles at 100".to_string()));
+    }
+
+    #[test]
+    fn validate_rejects_bad_speeds() {
+        for speed in [0, MAX_SPEED + 1] {
+            let mut level = sample();
+            level.speed = speed;
+            assert!(level.validate().is_err(), "speed {}", speed);
+
+            let mut level = sample();
+            level.speed_changes.push(SpeedChange { at: 50, speed });
+            assert!(level.validate().is_err(), "speed change to {}", speed);
+        }
+        let mut level = sample();
+        level.speed = MAX_SPEED;
+        level.speed_changes.push(SpeedChange { at: 50, speed: 1 });
+        assert_eq!(level.validate(), Ok(()));
+    }
+
+    #[test]
+    fn campaign_unlocks_levels_in_order() {
+        let campaign = Campaign {
+            levels: ["a", "b"].map(|id| CampaignLevel { id: id.to_string(), level: sample() }).to_vec(),
+        };
+        let mut progress = CampaignProgress::default();
+        assert!(campaign.is_unlocked(0, &progress));
+        assert!(!campaign.is_unlocked(1, &progress));
+        progress.record("a", 12);
+        progress.record("a", 5);
+        assert!(campaign.is_unlocked(1, &progress));
+        assert!(!campaign.is_unlocked(2, &progress));
+        assert_eq!(progress.completed["a"].best_score, 12);
+    }
+
+    /// 带道具和变速点的关卡
+    fn course() -> Level {
+        let mut level = sample();
+        level.sort();
+        level.powerups = vec![
+            LevelPowerUp { x: 30, y: 20, power_type: PowerUpType::Shield },
+            LevelPowerUp { x: 120, y: 30, power_type: PowerUpType::ExtraLife },
+        ];
+        level.speed_changes = vec![SpeedChange { at: 50, speed: 2 }, SpeedChange { at: 150, speed: 3 }];
+        level
+    }
+
+    #[test]
+    fn run_places_everything_at_its_level_position() {
+        let mut run = LevelRun::new(course());
+        assert_eq!(run.speed, 1);
+        assert_eq!(run.next_obstacle().x, 40);
+        // 只有即将滚进屏幕的道具出现
+        let due = run.due_powerups();
+        assert_eq!(due.iter().map(|powerup| (powerup.x, powerup.y)).collect::<Vec<_>>(), vec![(30, 20)]);
+
+        while run.distance < 50 {
+            run.advance();
+        }
+        assert_eq!(run.speed, 2);
+        assert_eq!(run.next_obstacle().x, 100 - run.distance);
+        assert_eq!(run.due_powerups()[0].x, 120 - run.distance);
+        assert_eq!(run.next_obstacle().x, 200 - run.distance);
+        // 障碍物用完后放在终点线之后
+        assert_eq!(run.next_obstacle().x, 300 + SCREEN_WIDTH - run.distance);
+        assert_eq!(run.finish_x(), 300 - run.distance);
+    }
+
+    #[test]
+    fn starting_midway_skips_what_is_behind() {
+        let mut run = LevelRun::starting_at(course(), 110);
+        assert_eq!((run.distance, run.speed), (110, 2));
+        assert_eq!(run.next_obstacle().x, 90);
+        let due = run.due_powerups();
+        assert_eq!(due.len(), 1);
+        assert_eq!((due[0].x, due[0].power_type.clone()), (10, PowerUpType::ExtraLife));
+        assert!((run.progress() - 110.0 / 300.0).abs() < 1e-6);
+
+        // 正好在障碍物和变速点上开始时，它们还没有过去
+        let mut run = LevelRun::starting_at(course(), 150);
+        assert_eq!(run.speed, 3);
+        assert_eq!(run.next_obstacle().x, 50);
+        let mut run = LevelRun::starting_at(course(), 40);
+        assert_eq!(run.speed, 1);
+        assert_eq!(run.next_obstacle().x, 0);
+
+        let game = Game::with_level_from(course(), 1, 110);
+        assert_eq!((game.obstacle.x, game.scroll_step()), (90, 2));
+    }
+}
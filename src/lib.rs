@@ -0,0 +1,35 @@
+pub mod achievements;
+pub mod biome;
+pub mod boss;
+pub mod bot;
+pub mod difficulty;
+pub mod fireball;
+pub mod game;
+pub mod gym;
+pub mod hazard;
+pub mod http;
+pub mod input;
+pub mod leaderboard;
+pub mod level;
+pub mod netplay;
+pub mod obstacle;
+pub mod player;
+pub mod powerup;
+pub mod profile;
+pub mod render;
+pub mod replay;
+pub mod savegame;
+pub mod scores;
+pub mod simulate;
+pub mod spectate;
+pub mod stats;
+pub mod storage;
+
+/// 游戏屏幕宽度
+pub const SCREEN_WIDTH: i32 = 90;
+/// 游戏屏幕高度
+pub const SCREEN_HEIGHT: i32 = 50;
+/// 每隔75毫秒做一些事情
+pub const FRAME_DURATION: f32 = 75.0;
+/// 逻辑帧时长（毫秒），游戏核心以固定的 60 帧每秒推进
+pub const TICK_DURATION: f32 = 1000.0 / 60.0;
@@ -1,21 +1,48 @@
-mod obstacle;
-mod player;
-mod powerup;
-mod difficulty;
 mod audio;
+mod autopilot;
+mod campaign;
+mod controls_menu;
+mod editor;
+mod menu;
+mod online;
+mod profile_menu;
+mod race;
+mod versus;
+mod watch;
 
 use bracket_lib::prelude::*;
 use image::*;
-use obstacle::Obstacle;
-use player::Player;
-use powerup::{PowerUp, PowerUpType, ActivePowerUp};
-use difficulty::{Difficulty, DifficultySettings};
-use std::fs::{self, OpenOptions};
-use std::io::{self, Write};
+use flappy::achievements::{Achievement, AchievementProgress, AchievementTracker};
+use flappy::difficulty::Difficulty;
+use flappy::game::{Game, GameEvent, TickInput};
+use flappy::input::{Action, ActionState, HoldRepeat, KeyBindings, PLAYER_ACTIONS};
+use flappy::obstacle::ObstacleType;
+use flappy::powerup::PowerUpType;
+use flappy::profile::{Profile, ProfileList, ProfileSettings, ProfileStore};
+use flappy::leaderboard::{LeaderboardClient, LeaderboardEntry, RunSubmission};
+use flappy::replay::Replay;
+use flappy::savegame::SavedRun;
+use flappy::spectate::{Broadcaster, DEFAULT_SPECTATE_ADDR};
+use flappy::scores::{ExportFormat, ScoreEntry};
+use flappy::simulate::{format_reports, simulate, SimulationConfig};
+use flappy::stats::{LifetimeStats, RunStats};
+use flappy::storage::data_dir;
+use flappy::{SCREEN_HEIGHT, SCREEN_WIDTH, TICK_DURATION};
+use std::io;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
 use audio::AudioSystem;
+use autopilot::{Autopilot, Idle};
+use editor::Editor;
+use campaign::CampaignMenu;
+use controls_menu::ControlsMenu;
+use menu::{MenuItem, MenuState};
+use online::NetSession;
+use profile_menu::ProfileMenu;
+use watch::Watch;
 use std::path::Path;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 enum GameMode {
     Menu,
     DifficultySelect,
@@ -23,36 +50,88 @@ enum GameMode {
     End,
     HighScores,
     Paused,
+    Statistics,
+    Achievements,
+    Profiles,
+    Controls,
+    Results,
+    Race,
+    Lobby,
+    Spectate,
+    Campaign,
+    LevelEnd,
+    Editor,
 }
 
-/// 游戏屏幕宽度
-const SCREEN_WIDTH: i32 = 90;
-/// 游戏屏幕高度
-const SCREEN_HEIGHT: i32 = 50;
-/// 每隔75毫秒做一些事情
-const FRAME_DURATION: f32 = 75.0;
+/// 一帧内最多追赶的逻辑帧数，防止卡顿后瞬间快进
+pub(crate) const MAX_TICKS_PER_FRAME: u32 = 5;
+
+/// 成就提示的显示时间（毫秒）
+const TOAST_DURATION: f32 = 3000.0;
+
+/// 游戏中弹出的成就提示
+struct Toast {
+    achievement: Achievement,
+    remaining_ms: f32,
+}
+
+/// 在线排行榜的加载状态
+enum OnlineScores {
+    Disabled,
+    Loading(Receiver<io::Result<Vec<LeaderboardEntry>>>),
+    Loaded(Vec<LeaderboardEntry>),
+    Unavailable,
+}
 
 struct State {
-    player: Player,
-    frame_time: f32,
+    game: Game,
+    /// 分屏竞速中第二名玩家的那一局，第一名玩家使用 `game`
+    race: Option<Game>,
+    /// 联机对战的连接，对手的那一局放在 `race` 里
+    net: Option<NetSession>,
+    // 本地玩家
+    profiles: ProfileList,
+    profile_cursor: usize,
+    profile_menu: ProfileMenu,
+    profile_message: Option<String>,
     mode: GameMode,
-    obstacle: Obstacle,
-    score: i32,
+    /// 上一帧的界面，切换界面时重置菜单光标
+    last_mode: GameMode,
+    menu: MenuState,
     score_saved: bool,
     // 新增：难度系统
     selected_difficulty: Difficulty,
-    difficulty_settings: DifficultySettings,
-    // 新增：道具系统
-    powerups: Vec<PowerUp>,
-    active_powerups: Vec<ActivePowerUp>,
-    powerup_spawn_timer: f32,
-    // 新增：游戏效果
-    slow_motion_timer: f32,
-    shield_active: bool,
-    shield_timer: f32,
-    lives: i32,
-    combo_count: i32,
-    last_obstacle_passed: i32,
+    // 固定逻辑帧：累计的帧时间和还没交给游戏核心的输入
+    tick_accumulator: f32,
+    /// 每名玩家下一个逻辑帧的输入
+    pending_inputs: [TickInput; 2],
+    actions: ActionState,
+    hold_repeat: HoldRepeat,
+    replay: Replay,
+    run_stats: RunStats,
+    lifetime_stats: LifetimeStats,
+    // 成就系统
+    achievement_tracker: AchievementTracker,
+    achievement_progress: AchievementProgress,
+    toasts: Vec<Toast>,
+    // 在线排行榜
+    leaderboard: Option<LeaderboardClient>,
+    online_scores: OnlineScores,
+    /// 设置了观战地址时向观众广播对局
+    broadcaster: Option<Broadcaster>,
+    watch: Option<Watch>,
+    /// 机器人代替一号玩家操作时的设置
+    autopilot: Option<Autopilot>,
+    idle: Idle,
+    campaign: CampaignMenu,
+    /// 打开的关卡编辑器，试玩时保留
+    editor: Option<Editor>,
+    /// 当前玩家保存的对局（难度和分数），用于主菜单的继续选项
+    saved_run: Option<String>,
+    key_bindings: KeyBindings,
+    controls_cursor: usize,
+    controls_menu: ControlsMenu,
+    controls_message: Option<String>,
     audio: AudioSystem,
     // bgm_playing: bool,
 }
@@ -71,50 +150,142 @@ impl State {
 
             audio.play_bgm(bgm_path);
         }
+        let profiles = ProfileStore::open_default().load_or_init().unwrap_or_else(|err| {
+            eprintln!("Failed to load profiles: {}", err);
+            ProfileList::with_default(&data_dir())
+        });
+        let mut state = State::with_profiles(profiles, audio);
+        state.load_profile_data();
+        state
+    }
+
+    /// 不播放音乐、不读取玩家设置的初始状态
+    fn with_profiles(profiles: ProfileList, audio: AudioSystem) -> Self {
         State {
-            player: Player::new(5, 25),
-            frame_time: 0.0,
+            game: Game::new(Difficulty::Normal, 0),
+            race: None,
+            net: None,
+            profiles,
+            profile_cursor: 0,
+            profile_menu: ProfileMenu::Browse,
+            profile_message: None,
             mode: GameMode::Menu,
-            obstacle: Obstacle::new(SCREEN_WIDTH, 0),
-            score: 0,
+            last_mode: GameMode::Menu,
+            menu: MenuState::default(),
             score_saved: false,
             selected_difficulty: Difficulty::Normal,
-            difficulty_settings: DifficultySettings::new(Difficulty::Normal),
-            powerups: Vec::new(),
-            active_powerups: Vec::new(),
-            powerup_spawn_timer: 0.0,
-            slow_motion_timer: 0.0,
-            shield_active: false,
-            shield_timer: 0.0,
-            lives: 3,
-            combo_count: 0,
-            last_obstacle_passed: -1,
+            tick_accumulator: 0.0,
+            pending_inputs: Default::default(),
+            actions: ActionState::default(),
+            hold_repeat: HoldRepeat::default(),
+            replay: Replay::new(0, Difficulty::Normal),
+            run_stats: RunStats::default(),
+            lifetime_stats: LifetimeStats::default(),
+            achievement_tracker: AchievementTracker::new(&AchievementProgress::default()),
+            achievement_progress: AchievementProgress::default(),
+            toasts: Vec::new(),
+            leaderboard: LeaderboardClient::from_env(),
+            broadcaster: Broadcaster::from_env(),
+            watch: None,
+            autopilot: None,
+            idle: Idle::default(),
+            campaign: CampaignMenu::default(),
+            editor: None,
+            online_scores: OnlineScores::Disabled,
+            saved_run: None,
+            key_bindings: KeyBindings::default(),
+            controls_cursor: 0,
+            controls_menu: ControlsMenu::Browse,
+            controls_message: None,
             audio,
             // bgm_playing: false,
         }
     }
 
+    /// 当前选中的玩家
+    fn profile(&self) -> Profile {
+        self.profiles.active().clone()
+    }
+
+    /// 读取当前玩家的设置和存档信息
+    fn load_profile_data(&mut self) {
+        let settings = self.profile().settings().load().unwrap_or_else(|err| {
+            eprintln!("Failed to load settings: {}", err);
+            ProfileSettings::default()
+        });
+        self.selected_difficulty = settings.preferred_difficulty;
+        self.key_bindings = settings.key_bindings;
+        self.saved_run = match self.profile().saved_run().load() {
+            Ok(saved) => saved.map(|run| saved_run_label(&run.game)),
+            Err(err) => {
+                eprintln!("Failed to load saved run: {}", err);
+                None
+            }
+        };
+    }
+
+    /// 返回菜单：M 键或绑定的返回键
+    fn back_pressed(&self, ctx: &BTerm) -> bool {
+        ctx.key.is_some_and(|key| key == VirtualKeyCode::M || self.key_bindings.matches(Action::Back, key))
+    }
+
+    /// 记住当前玩家选择的难度
+    fn save_preferred_difficulty(&self) {
+        let difficulty = self.selected_difficulty.clone();
+        if let Err(err) = self.profile().settings().update(|settings| settings.preferred_difficulty = difficulty) {
+            eprintln!("Failed to save settings: {}", err);
+        }
+    }
+
     fn main_menu(&mut self, ctx: &mut BTerm) {
         ctx.cls();
         self.set_background(ctx, "assets/menu_bg.png");
         
         ctx.print_centered(5, "Welcome to Flappy Dragon!");
-        ctx.print(30,8, "(P) Play Game");
-        ctx.print(30,10, "(D) Select Difficulty");
-        ctx.print(30,12, "(H) High Scores");
-        ctx.print(30,14, "(Q) Quit Game");
+        let mut entries = Vec::new();
+        if let Some(label) = &self.saved_run {
+            entries.push((VirtualKeyCode::C, format!("(C) Continue ({})", label)));
+        }
+        entries.extend([
+            (VirtualKeyCode::P, "(P) Play Game".to_string()),
+            (VirtualKeyCode::L, "(L) Campaign".to_string()),
+            (VirtualKeyCode::T, "(T) Two Players".to_string()),
+            (VirtualKeyCode::R, "(R) Split-Screen Race".to_string()),
+            (VirtualKeyCode::N, "(N) Online Race".to_string()),
+            (VirtualKeyCode::D, "(D) Select Difficulty".to_string()),
+            (VirtualKeyCode::H, "(H) High Scores".to_string()),
+            (VirtualKeyCode::S, "(S) Statistics".to_string()),
+            (VirtualKeyCode::A, "(A) Achievements".to_string()),
+            (VirtualKeyCode::O, "(O) Profiles".to_string()),
+            (VirtualKeyCode::K, "(K) Controls".to_string()),
+            (VirtualKeyCode::Q, "(Q) Quit Game".to_string()),
+        ]);
+        let items: Vec<MenuItem> = entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, label))| MenuItem::new(30, 8 + i as i32 * 2, key, label))
+            .collect();
         
-        // 显示当前难度
-        ctx.print_centered(17, &format!("Current Difficulty: {:?}", self.selected_difficulty));
+        // 显示当前玩家和难度
+        let footer = 9 + items.len() as i32 * 2;
+        ctx.print_centered(footer, format!("Player: {}", self.profile().name));
+        ctx.print_centered(footer + 1, format!("Current Difficulty: {:?}", self.selected_difficulty));
         
-        if let Some(key) = ctx.key {
+        self.track_idle(ctx);
+        if let Some(key) = self.menu.show(ctx, &items, &self.key_bindings) {
             match key {
-                VirtualKeyCode::P => {
-                    self.restart();
-                    self.apply_difficulty_settings();
-                }
+                VirtualKeyCode::P => self.restart(),
+                VirtualKeyCode::L => self.show_campaign(),
+                VirtualKeyCode::T => self.start_game(2),
+                VirtualKeyCode::R => self.start_race(),
+                VirtualKeyCode::N => self.start_online(),
+                VirtualKeyCode::C if self.saved_run.is_some() => self.continue_run(),
                 VirtualKeyCode::D => self.mode = GameMode::DifficultySelect,
-                VirtualKeyCode::H => self.mode = GameMode::HighScores,
+                VirtualKeyCode::H => self.show_high_scores(),
+                VirtualKeyCode::S => self.show_statistics(),
+                VirtualKeyCode::A => self.show_achievements(),
+                VirtualKeyCode::O => self.show_profiles(),
+                VirtualKeyCode::K => self.show_controls(),
                 VirtualKeyCode::Q => ctx.quitting = true,
                 _ => {}
             }
@@ -126,19 +297,17 @@ impl State {
         self.set_background(ctx, "assets/menu_bg.png");
         
         ctx.print(25,5, "Select Difficulty:");
-        ctx.print(30,8, "(E) Easy");
+        let items = [
+            MenuItem::new(30, 8, VirtualKeyCode::E, "(E) Easy"),
+            MenuItem::new(30, 11, VirtualKeyCode::N, "(N) Normal"),
+            MenuItem::new(30, 14, VirtualKeyCode::H, "(H) Hard"),
+            MenuItem::new(30, 17, VirtualKeyCode::I, "(I) Insane"),
+            MenuItem::new(30, 22, VirtualKeyCode::M, "(M) Back to Menu"),
+        ];
         ctx.print(30,9, "    - More lives, slower obstacles");
-
-        ctx.print(30,11, "(N) Normal");
         ctx.print(30,12, "    - Balanced gameplay");
-
-        ctx.print(30,14, "(H) Hard");
         ctx.print(30,15, "    - Faster obstacles, smaller gaps");
-
-        ctx.print(30,17, "(I) Insane");
         ctx.print(30,18, "    - Maximum challenge!");
-
-        ctx.print(30,22, "(M) Back to Menu");
         
         // 高亮当前选择
         let highlight_y = match self.selected_difficulty {
@@ -147,25 +316,27 @@ impl State {
             Difficulty::Hard => 14,
             Difficulty::Insane => 17,
         };
-        ctx.print(30,highlight_y, ">>> SELECTED <<<");
+        ctx.print(45,highlight_y, "<<< SELECTED");
         
-        if let Some(key) = ctx.key {
+        if ctx.key.is_some_and(|key| self.key_bindings.matches(Action::Back, key)) {
+            self.mode = GameMode::Menu;
+        } else if let Some(key) = self.menu.show(ctx, &items, &self.key_bindings) {
             match key {
                 VirtualKeyCode::E => {
                     self.selected_difficulty = Difficulty::Easy;
-                    self.difficulty_settings = DifficultySettings::new(Difficulty::Easy);
+                    self.save_preferred_difficulty();
                 }
                 VirtualKeyCode::N => {
                     self.selected_difficulty = Difficulty::Normal;
-                    self.difficulty_settings = DifficultySettings::new(Difficulty::Normal);
+                    self.save_preferred_difficulty();
                 }
                 VirtualKeyCode::H => {
                     self.selected_difficulty = Difficulty::Hard;
-                    self.difficulty_settings = DifficultySettings::new(Difficulty::Hard);
+                    self.save_preferred_difficulty();
                 }
                 VirtualKeyCode::I => {
                     self.selected_difficulty = Difficulty::Insane;
-                    self.difficulty_settings = DifficultySettings::new(Difficulty::Insane);
+                    self.save_preferred_difficulty();
                 }
                 VirtualKeyCode::M => self.mode = GameMode::Menu,
                 _ => {}
@@ -176,229 +347,148 @@ impl State {
     fn play(&mut self, ctx: &mut BTerm) {
         ctx.cls();
         self.set_background(ctx, "assets/game_bg.png");
-        
-        // 计算实际帧时间（考虑慢动作效果）
-        let effective_frame_time = if self.slow_motion_timer > 0.0 {
-            ctx.frame_time_ms * 0.5 // 慢动作时减半速度
-        } else {
-            ctx.frame_time_ms
-        };
-        
-        self.frame_time += effective_frame_time;
-        
-        // 更新计时器
-        if self.slow_motion_timer > 0.0 {
-            self.slow_motion_timer -= ctx.frame_time_ms;
-        }
-        if self.shield_timer > 0.0 {
-            self.shield_timer -= ctx.frame_time_ms;
-            if self.shield_timer <= 0.0 {
-                self.shield_active = false;
-            }
-        }
+        self.game.render_background(ctx);
 
-        // 游戏主循环
-        if self.frame_time > FRAME_DURATION {
-            self.frame_time = 0.0;
-            self.player.gravity_and_move();
+        // 处理输入
+        if self.attract_interrupted(ctx) {
+            return;
         }
+        self.handle_input();
 
-        // 处理输入
-        self.handle_input(ctx);
+        // 游戏主循环：按固定逻辑帧推进游戏核心
+        self.tick_accumulator += ctx.frame_time_ms;
+        self.tick_accumulator = self.tick_accumulator.min(TICK_DURATION * MAX_TICKS_PER_FRAME as f32);
+        while self.tick_accumulator >= TICK_DURATION && !self.game.is_over() {
+            self.tick_accumulator -= TICK_DURATION;
+            let inputs: Vec<TickInput> = (0..self.game.dragons.len())
+                .map(|i| {
+                    let input = self.hold_repeat.tick(&self.actions, i, std::mem::take(&mut self.pending_inputs[i]));
+                    match &self.autopilot {
+                        Some(autopilot) if i == 0 => autopilot.bot.input(&self.game),
+                        _ => input,
+                    }
+                })
+                .collect();
+            self.game.tick_players(&inputs);
+            if let Some(broadcaster) = &self.broadcaster {
+                broadcaster.publish(&self.game);
+            }
 
-        // 更新道具
-        self.update_powerups(ctx);
+            // 双人对局、机器人的对局和编辑器里的试玩不计入录像、统计和成就
+            if self.is_two_player() || self.autopilot.is_some() || self.testing_level() {
+                self.game.drain_events();
+                continue;
+            }
+            self.replay.record(inputs[0]);
+            let events: Vec<GameEvent> = self.game.drain_events().into_iter().map(|(_, event)| event).collect();
+            for event in &events {
+                self.run_stats.record(event);
+            }
+            let unlocked = self.achievement_tracker.observe(&self.game, &events);
+            if !unlocked.is_empty() {
+                self.save_achievements();
+            }
+            self.toasts.extend(unlocked.into_iter().map(|achievement| Toast {
+                achievement,
+                remaining_ms: TOAST_DURATION,
+            }));
+        }
 
         // 渲染
-        self.render_game(ctx);
-
-        // 碰撞检测
-        self.check_collisions();
-    }
-
-    fn handle_input(&mut self, ctx: &mut BTerm) {
-        if let Some(key) = ctx.key {
-            match key {
-                VirtualKeyCode::Left => self.player.move_left(),
-                VirtualKeyCode::Right => self.player.move_right(),
-                VirtualKeyCode::Up | VirtualKeyCode::Space => self.player.flap(),
-                VirtualKeyCode::Down => self.player.move_down(),
-                VirtualKeyCode::Escape => self.mode = GameMode::Paused,
-                _ => {}
+        self.game.render(ctx);
+        if let Some(autopilot) = &self.autopilot {
+            if autopilot.attract {
+                ctx.print_centered(0, "DEMO - press any key");
+            } else {
+                ctx.print(0, 0, format!("BOT | Pause {}", self.key_label(Action::Pause)));
             }
+        } else if self.testing_level() {
+            ctx.print(0, 0, format!("TEST | Pause {} to return to the editor", self.key_label(Action::Pause)));
+        } else if self.is_two_player() {
+            let [p1, p2] = PLAYER_ACTIONS.map(|[flap, ..]| self.key_label(flap));
+            ctx.print(0, 0, format!("P1 Flap {} | P2 Flap {} | Pause {}", p1, p2, self.key_label(Action::Pause)));
+        } else {
+            ctx.print(0, 0, format!(
+                "Controls: Flap {} | Dive {} | Move {}/{} | Fire {} | Pause {}",
+                self.key_label(Action::Flap),
+                self.key_label(Action::Dive),
+                self.key_label(Action::Left),
+                self.key_label(Action::Right),
+                self.key_label(Action::Fire),
+                self.key_label(Action::Pause)
+            ));
         }
-    }
-
-    fn update_powerups(&mut self, ctx: &mut BTerm) {
-        // 生成新道具
-        self.powerup_spawn_timer += ctx.frame_time_ms;
-        if self.powerup_spawn_timer > self.difficulty_settings.powerup_spawn_rate {
-            self.spawn_powerup();
-            self.powerup_spawn_timer = 0.0;
+        if let Some(broadcaster) = &self.broadcaster {
+            let line = format!("Broadcasting on {} ({} watching)", broadcaster.addr(), broadcaster.viewers());
+            ctx.print(0, SCREEN_HEIGHT - 1, line);
         }
+        self.render_toasts(ctx);
 
-        // 更新道具位置
-        for powerup in &mut self.powerups {
-            powerup.update();
-        }
-
-        // 移除超出屏幕的道具
-        self.powerups.retain(|p| p.x > -5);
-
-        let mut collected_powerups = Vec::new();
-        let mut power_types_to_activate = Vec::new();
-
-        for (i, powerup) in self.powerups.iter().enumerate() {
-            if powerup.x == self.player.x && (powerup.y - self.player.y).abs() <= 1 {
-                collected_powerups.push(i);
-                power_types_to_activate.push(powerup.power_type.clone());
+        if self.game.is_over() {
+            // // 播放碰撞音效
+            // if let Err(err) = self.audio.play_sfx("collision") {
+            //     eprintln!("Failed to play collision sound: {}", err);
+            // }
+            if self.autopilot.is_some() {
+                self.autopilot_finished();
+            } else if self.testing_level() {
+                self.return_to_editor();
+            } else if self.game.level.is_some() {
+                self.mode = GameMode::LevelEnd;
+            } else {
+                self.mode = if self.is_two_player() { GameMode::Results } else { GameMode::End };
             }
         }
+    }
 
-        // Now activate all collected power-ups
-        for power_type in power_types_to_activate {
-            self.activate_powerup(power_type);
-        }
-
-        // 移除被收集的道具
-        for &i in collected_powerups.iter().rev() {
-            self.powerups.remove(i);
+    /// 在右上角依次显示成就提示
+    fn render_toasts(&mut self, ctx: &mut BTerm) {
+        for toast in &mut self.toasts {
+            toast.remaining_ms -= ctx.frame_time_ms;
         }
+        self.toasts.retain(|toast| toast.remaining_ms > 0.0);
 
-        // 更新激活的道具效果
-        self.active_powerups.retain_mut(|active| {
-            active.timer -= ctx.frame_time_ms;
-            active.timer > 0.0
-        });
-    }
-
-    fn spawn_powerup(&mut self) {
-        let mut random = RandomNumberGenerator::new();
-        let power_type = match random.range(0, 4) {
-            0 => PowerUpType::Shield,
-            1 => PowerUpType::SlowMotion,
-            2 => PowerUpType::DoubleScore,
-            _ => PowerUpType::ExtraLife,
-        };
-        
-        self.powerups.push(PowerUp::new(
-            SCREEN_WIDTH + 10,
-            random.range(5, SCREEN_HEIGHT - 5),
-            power_type,
-        ));
-    }
-
-    fn activate_powerup(&mut self, power_type: PowerUpType) {
-        match power_type {
-            PowerUpType::Shield => {
-                self.shield_active = true;
-                self.shield_timer = 5000.0; // 5秒护盾
-            }
-            PowerUpType::SlowMotion => {
-                self.slow_motion_timer = 3000.0; // 3秒慢动作
-            }
-            PowerUpType::DoubleScore => {
-                self.active_powerups.push(ActivePowerUp {
-                    power_type: PowerUpType::DoubleScore,
-                    timer: 10000.0, // 10秒双倍积分
-                });
-            }
-            PowerUpType::ExtraLife => {
-                self.lives += 1;
-            }
+        for (i, toast) in self.toasts.iter().enumerate() {
+            let y = 1 + i as i32 * 4;
+            let width = toast.achievement.description().len().max(24) as i32 + 3;
+            let x = SCREEN_WIDTH - width - 2;
+            ctx.draw_box(x, y, width, 3, GOLD, BLACK);
+            ctx.print_color(x + 2, y + 1, GOLD, BLACK, format!("Achievement: {}", toast.achievement.name()));
+            ctx.print_color(x + 2, y + 2, WHITE, BLACK, toast.achievement.description());
         }
     }
 
-    fn render_game(&mut self, ctx: &mut BTerm) {
-        // 渲染玩家
-        self.player.render(ctx);
-        
-        // 渲染障碍物
-        self.obstacle.render(ctx, self.player.x, &self.difficulty_settings);
-        
-        // 渲染道具
-        for powerup in &self.powerups {
-            powerup.render(ctx);
+    /// 把这一帧刚按下的动作加入下一个逻辑帧的输入
+    fn handle_input(&mut self) {
+        let players = if self.race.is_some() { 2 } else { self.game.dragons.len() };
+        for (i, pending) in self.pending_inputs.iter_mut().enumerate().take(players) {
+            pending.merge(self.actions.pressed_input(i));
         }
-        
-        // 渲染UI
-        self.render_ui(ctx);
-        
-        // 渲染特效
-        if self.shield_active {
-            self.render_shield_effect(ctx);
-        }
-        if self.slow_motion_timer > 0.0 {
-            ctx.print(0, 3, "SLOW MOTION!");
+        if self.actions.pressed(Action::Pause) {
+            // 联机对战无法暂停，按暂停键离开对局
+            if self.net.is_some() {
+                self.quit_to_menu();
+            } else if self.testing_level() {
+                self.return_to_editor();
+            } else {
+                self.mode = GameMode::Paused;
+            }
         }
     }
 
-    fn render_ui(&mut self, ctx: &mut BTerm) {
-        ctx.print(0, 0, "Controls: Arrow Keys/Space, ESC to Pause");
-        ctx.print(0, 2, &format!("Score: {}  |  Lives: {}  |  Combo: {}", 
-                                 self.score, self.lives, self.combo_count));
-        ctx.print(0, 4, &format!("Current Difficulty: {:?}", self.selected_difficulty));
-        
-        // 显示激活的道具效果
-        let mut y_offset = 4;
-        for active in &self.active_powerups {
-            ctx.print(0, y_offset, &format!("{:?}: {:.1}s", 
-                                          active.power_type, active.timer / 1000.0));
-            y_offset += 1;
+    /// 根据当前按住的按键和鼠标更新动作状态，每帧调用一次
+    fn update_actions(&mut self, key: Option<VirtualKeyCode>) {
+        let input = INPUT.lock();
+        // 同一帧内按下又松开的键不在按住集合里，靠 `ctx.key` 补上这次按下
+        let mut active = self.key_bindings.active_actions(input.key_pressed_set().iter().chain(key.as_ref()));
+        // 鼠标左键也可以扇动翅膀，右键喷火
+        if input.is_mouse_button_pressed(0) {
+            active.insert(Action::Flap);
         }
-        
-        if self.shield_active {
-            ctx.print(0, y_offset, &format!("Shield: {:.1}s", self.shield_timer / 1000.0));
-        }
-    }
-
-    fn render_shield_effect(&mut self, ctx: &mut BTerm) {
-        // 在玩家周围渲染护盾效果
-        let shield_char = if (self.shield_timer as i32 / 200) % 2 == 0 { 'O' } else { 'o' };
-        ctx.set(self.player.x - 1, self.player.y, CYAN, BLACK, to_cp437(shield_char));
-        ctx.set(self.player.x + 1, self.player.y, CYAN, BLACK, to_cp437(shield_char));
-        ctx.set(self.player.x, self.player.y - 1, CYAN, BLACK, to_cp437(shield_char));
-        ctx.set(self.player.x, self.player.y + 1, CYAN, BLACK, to_cp437(shield_char));
-    }
-
-    fn check_collisions(&mut self) {
-        // 检查越过障碍物
-        if self.player.x > self.obstacle.x && self.last_obstacle_passed != self.obstacle.x {
-            let score_multiplier = if self.active_powerups.iter()
-                .any(|p| matches!(p.power_type, PowerUpType::DoubleScore)) { 2 } else { 1 };
-            
-            self.score += score_multiplier;
-            self.combo_count += 1;
-            self.last_obstacle_passed = self.obstacle.x;
-            
-            // 生成新障碍物
-            self.obstacle = Obstacle::new(SCREEN_WIDTH, self.score);
-        }
-
-        // 检查碰撞
-        if self.player.y > SCREEN_HEIGHT || self.obstacle.hit_obstacle(&self.player) {
-            if self.shield_active {
-                // 护盾保护，不死亡但移除护盾
-                self.shield_active = false;
-                self.shield_timer = 0.0;
-                self.combo_count = 0; // 重置连击
-            } else {
-                // // 播放碰撞音效
-                // if let Err(err) = self.audio.play_sfx("collision") {
-                //     eprintln!("Failed to play collision sound: {}", err);
-                // }
-                self.lives -= 1;
-                self.combo_count = 0;
-                
-                if self.lives <= 0 {
-                    self.mode = GameMode::End;
-                } else {
-                    // 重置玩家位置，继续游戏
-                    self.player = Player::new(5, 25);
-                    self.obstacle = Obstacle::new(SCREEN_WIDTH, self.score);
-                }
-            }
+        if input.is_mouse_button_pressed(1) {
+            active.insert(Action::Fire);
         }
+        self.actions.update(active);
     }
 
     fn paused(&mut self, ctx: &mut BTerm) {
@@ -413,48 +503,88 @@ impl State {
         }
         
         ctx.print_centered(20, "GAME PAUSED!");
-        ctx.print(35,23, "(R) Resume");
-        ctx.print(35,25, "(M) Main Menu");
-        ctx.print(35,27, "(Q) Quit");
+        let items: Vec<MenuItem> = self
+            .pause_entries()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, label))| MenuItem::new(35, 23 + i as i32 * 2, key, label))
+            .collect();
         
-        if let Some(key) = ctx.key {
+        let resume = ctx.key.is_some_and(|key| {
+            self.key_bindings.matches(Action::Pause, key) || self.key_bindings.matches(Action::Back, key)
+        });
+        if resume {
+            self.resume();
+        } else if let Some(key) = self.menu.show(ctx, &items, &self.key_bindings) {
             match key {
-                VirtualKeyCode::R | VirtualKeyCode::Escape => self.mode = GameMode::Playing,
-                VirtualKeyCode::M => self.mode = GameMode::Menu,
-                VirtualKeyCode::Q => ctx.quitting = true,
+                VirtualKeyCode::R => self.resume(),
+                VirtualKeyCode::S => self.save_run(),
+                VirtualKeyCode::M => self.pause_to_menu(),
+                VirtualKeyCode::Q => {
+                    self.save_achievements();
+                    ctx.quitting = true;
+                }
                 _ => {}
             }
         }
     }
 
+    /// 暂停菜单的选项；分屏竞速、关卡和机器人的对局不能存档
+    fn pause_entries(&self) -> Vec<(VirtualKeyCode, &'static str)> {
+        let mut entries = vec![(VirtualKeyCode::R, "(R) Resume")];
+        if self.race.is_none() && self.game.level.is_none() && self.autopilot.is_none() {
+            entries.push((VirtualKeyCode::S, "(S) Save & Quit"));
+        }
+        entries.extend([(VirtualKeyCode::M, "(M) Main Menu"), (VirtualKeyCode::Q, "(Q) Quit")]);
+        entries
+    }
+
+    /// 从暂停菜单放弃对局回到主菜单
+    fn pause_to_menu(&mut self) {
+        self.save_achievements();
+        self.autopilot = None;
+        self.mode = GameMode::Menu;
+    }
+
+    fn resume(&mut self) {
+        self.mode = if self.race.is_some() { GameMode::Race } else { GameMode::Playing };
+    }
+
     fn dead(&mut self, ctx: &mut BTerm) {
         self.audio.stop_bgm();
         self.set_background(ctx, "assets/end_bg.png");
         ctx.print_centered(8, "Game Over!");
-        ctx.print_centered(9, &format!("You earned {} points", self.score));
-        ctx.print_centered(10, &format!("Best combo: {}", self.combo_count));
-        ctx.print_centered(11, &format!("Difficulty: {:?}", self.selected_difficulty));
-        ctx.print(35,14, "(P) Play Again");
-        ctx.print(35,16, "(M) Main Menu");
-        ctx.print(35,18, "(H) High Scores");
-        ctx.print(35,20, "(Q) Quit Game");
+        let dragon = &self.game.dragons[0];
+        ctx.print_centered(9, format!("You earned {} points", dragon.score));
+        ctx.print_centered(10, format!("Best combo: {}", dragon.best_combo));
+        ctx.print_centered(11, format!("Difficulty: {:?}", self.game.difficulty));
+        let items = [
+            MenuItem::new(35, 14, VirtualKeyCode::P, "(P) Play Again"),
+            MenuItem::new(35, 16, VirtualKeyCode::M, "(M) Main Menu"),
+            MenuItem::new(35, 18, VirtualKeyCode::H, "(H) High Scores"),
+            MenuItem::new(35, 20, VirtualKeyCode::Q, "(Q) Quit Game"),
+        ];
 
         if !self.score_saved {
-            if let Err(err) = Self::save_score(self.score, self.selected_difficulty.clone()) {
-                ctx.print_centered(15, &format!("Error saving score: {}", err));
+            let entry = ScoreEntry::new(self.game.dragons[0].score, self.game.difficulty.clone());
+            self.submit_online(&entry);
+            if let Err(err) = self.save_score(entry) {
+                ctx.print_centered(15, format!("Error saving score: {}", err));
+            }
+            if let Err(err) = self.save_statistics() {
+                ctx.print_centered(16, format!("Error saving statistics: {}", err));
             }
+            self.save_achievements();
             self.score_saved = true;
         }
 
-        if let Some(key) = ctx.key {
+        if ctx.key.is_some_and(|key| self.key_bindings.matches(Action::Back, key)) {
+            self.mode = GameMode::Menu;
+        } else if let Some(key) = self.menu.show(ctx, &items, &self.key_bindings) {
             match key {
-                VirtualKeyCode::P => {
-                    self.restart();
-                    self.apply_difficulty_settings();
-                    self.score_saved = false;
-                }
+                VirtualKeyCode::P => self.restart(),
                 VirtualKeyCode::M => self.mode = GameMode::Menu,
-                VirtualKeyCode::H => self.mode = GameMode::HighScores,
+                VirtualKeyCode::H => self.show_high_scores(),
                 VirtualKeyCode::Q => ctx.quitting = true,
                 _ => {}
             }
@@ -462,25 +592,232 @@ impl State {
     }
 
     fn restart(&mut self) {
-        self.player = Player::new(5, 25);
-        self.frame_time = 0.0;
+        self.start_game(1);
+    }
+
+    /// 以选中的难度开始一局新游戏
+    fn start_game(&mut self, players: usize) {
+        let seed = RandomNumberGenerator::new().next_u64();
+        self.game = Game::with_players(self.selected_difficulty.clone(), seed, players);
+        self.race = None;
+        self.autopilot = None;
+        self.campaign.playing = None;
+        self.replay = Replay::new(seed, self.selected_difficulty.clone());
+        self.tick_accumulator = 0.0;
+        self.pending_inputs = Default::default();
+        self.run_stats = RunStats::default();
+        let progress = self.profile().achievements().load().unwrap_or_else(|err| {
+            eprintln!("Failed to load achievements: {}", err);
+            AchievementProgress::default()
+        });
+        self.achievement_tracker = AchievementTracker::new(&progress);
+        self.toasts.clear();
         self.mode = GameMode::Playing;
-        self.obstacle = Obstacle::new(SCREEN_WIDTH, 0);
-        self.score = 0;
         self.score_saved = false;
-        self.powerups.clear();
-        self.active_powerups.clear();
-        self.powerup_spawn_timer = 0.0;
-        self.slow_motion_timer = 0.0;
-        self.shield_active = false;
-        self.shield_timer = 0.0;
-        self.combo_count = 0;
-        self.last_obstacle_passed = -1;
     }
 
-    fn apply_difficulty_settings(&mut self) {
-        self.lives = self.difficulty_settings.starting_lives;
-        // 其他难度设置将在游戏过程中应用
+    /// 保存当前对局并回到主菜单
+    fn save_run(&mut self) {
+        self.save_achievements();
+        let run = SavedRun {
+            game: std::mem::replace(&mut self.game, Game::new(Difficulty::Normal, 0)),
+            replay: std::mem::replace(&mut self.replay, Replay::new(0, Difficulty::Normal)),
+            run_stats: std::mem::take(&mut self.run_stats),
+            achievements: std::mem::replace(
+                &mut self.achievement_tracker,
+                AchievementTracker::new(&AchievementProgress::default()),
+            ),
+        };
+        self.saved_run = Some(saved_run_label(&run.game));
+        if let Err(err) = self.profile().saved_run().save(run) {
+            eprintln!("Failed to save run: {}", err);
+            self.saved_run = None;
+        }
+        self.mode = GameMode::Menu;
+    }
+
+    /// 恢复保存的对局，存档随即作废
+    fn continue_run(&mut self) {
+        self.saved_run = None;
+        self.autopilot = None;
+        let run = match self.profile().saved_run().take() {
+            Ok(Some(run)) => run,
+            Ok(None) => return,
+            Err(err) => {
+                eprintln!("Failed to load saved run: {}", err);
+                return;
+            }
+        };
+        let progress = self.profile().achievements().load().unwrap_or_else(|err| {
+            eprintln!("Failed to load achievements: {}", err);
+            AchievementProgress::default()
+        });
+        self.game = run.game;
+        self.race = None;
+        self.replay = run.replay;
+        self.run_stats = run.run_stats;
+        self.achievement_tracker = run.achievements;
+        self.achievement_tracker.sync(&progress);
+        self.tick_accumulator = 0.0;
+        self.pending_inputs = Default::default();
+        self.toasts.clear();
+        self.score_saved = false;
+        // 先暂停，让玩家准备好再继续
+        self.mode = GameMode::Paused;
+    }
+
+    /// 在后台线程连同录像提交到在线排行榜，失败时只记录日志，本地分数不受影响
+    fn submit_online(&self, entry: &ScoreEntry) {
+        if let Some(client) = self.leaderboard.clone() {
+            let run = RunSubmission::new(entry.run_id.clone(), entry.score, self.replay.clone());
+            thread::spawn(move || {
+                if let Err(err) = client.submit(&run) {
+                    eprintln!("Failed to submit score to leaderboard: {}", err);
+                }
+            });
+        }
+    }
+
+    /// 把刚结束的一局计入累计统计
+    fn save_statistics(&self) -> io::Result<()> {
+        let game = &self.game;
+        self.profile().stats().update(|stats| {
+            stats.record_run(
+                &self.run_stats,
+                &game.difficulty,
+                game.dragons[0].score,
+                game.dragons[0].best_combo,
+                game.elapsed_ms() as u64,
+            );
+        })
+    }
+
+    /// 写入本局还没保存的成就进度
+    fn save_achievements(&mut self) {
+        if !self.achievement_tracker.has_unsaved() {
+            return;
+        }
+        let store = self.profile().achievements();
+        let tracker = &mut self.achievement_tracker;
+        if let Err(err) = store.update(|progress| progress.merge(tracker)) {
+            eprintln!("Failed to save achievements: {}", err);
+        }
+    }
+
+    fn show_achievements(&mut self) {
+        self.achievement_progress = self.profile().achievements().load().unwrap_or_else(|err| {
+            eprintln!("Failed to load achievements: {}", err);
+            AchievementProgress::default()
+        });
+        self.mode = GameMode::Achievements;
+    }
+
+    fn display_achievements(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/scores_bg.png");
+        let progress = &self.achievement_progress;
+        let unlocked = Achievement::ALL.iter().filter(|a| progress.is_unlocked(**a)).count();
+        ctx.print_centered(4, format!("Achievements ({}/{})", unlocked, Achievement::ALL.len()));
+
+        for (i, &achievement) in Achievement::ALL.iter().enumerate() {
+            let y = 8 + i as i32 * 3;
+            let (mark, color) = if progress.is_unlocked(achievement) { ("[X]", GOLD) } else { ("[ ]", GRAY) };
+            ctx.print_color(18, y, color, BLACK, format!("{} {}", mark, achievement.name()));
+            let description = if achievement == Achievement::RotatingTen && !progress.is_unlocked(achievement) {
+                format!("{} ({}/10)", achievement.description(), progress.rotating_passed)
+            } else {
+                achievement.description().to_string()
+            };
+            ctx.print_color(22, y + 1, color, BLACK, description);
+        }
+
+        ctx.print_centered(32, "(M) Back to Menu");
+
+        if self.back_pressed(ctx) {
+            self.mode = GameMode::Menu;
+        }
+    }
+
+    fn show_statistics(&mut self) {
+        self.lifetime_stats = self.profile().stats().load().unwrap_or_else(|err| {
+            eprintln!("Failed to load statistics: {}", err);
+            LifetimeStats::default()
+        });
+        self.mode = GameMode::Statistics;
+    }
+
+    fn display_statistics(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/scores_bg.png");
+        ctx.print_centered(4, "Lifetime Statistics");
+
+        let stats = &self.lifetime_stats;
+        let play_secs = stats.play_time_ms / 1000;
+        ctx.print(20, 7, format!("Runs finished:    {}", stats.runs));
+        ctx.print(20, 8, format!("Total play time:  {}h {:02}m {:02}s",
+                                 play_secs / 3600, play_secs / 60 % 60, play_secs % 60));
+        ctx.print(20, 9, format!("Total flaps:      {}", stats.flaps));
+        ctx.print(20, 10, format!("Longest combo:    {}", stats.longest_combo));
+        ctx.print(20, 11, format!("Shields consumed: {}", stats.shields_consumed));
+        ctx.print(20, 12, format!("Lives lost:       {}", stats.lives_lost));
+        ctx.print(20, 13, format!("Fireballs / hits: {} / {}", stats.fireballs, stats.targets_destroyed));
+
+        ctx.print(20, 14, "Obstacles passed:");
+        for (i, obstacle_type) in ObstacleType::ALL.iter().enumerate() {
+            let count = stats.obstacles_passed.get(obstacle_type).copied().unwrap_or(0);
+            ctx.print(22, 15 + i as i32, format!("{:<12} {}", format!("{:?}", obstacle_type), count));
+        }
+
+        let powerups_y = 16 + ObstacleType::ALL.len() as i32;
+        ctx.print(20, powerups_y, "Power-ups collected:");
+        for (i, power_type) in PowerUpType::ALL.iter().enumerate() {
+            let count = stats.powerups_collected.get(power_type).copied().unwrap_or(0);
+            ctx.print(22, powerups_y + 1 + i as i32, format!("{:<12} {}", format!("{:?}", power_type), count));
+        }
+
+        let difficulty_y = powerups_y + 2 + PowerUpType::ALL.len() as i32;
+        ctx.print(20, difficulty_y, "Per difficulty:   runs  avg score  best");
+        for (i, difficulty) in Difficulty::ALL.iter().enumerate() {
+            let entry = stats.per_difficulty.get(difficulty).cloned().unwrap_or_default();
+            ctx.print(22, difficulty_y + 1 + i as i32, format!("{:<12} {:>8}  {:>9.1}  {:>4}",
+                                                format!("{:?}", difficulty),
+                                                entry.runs, entry.average_score(), entry.best_score));
+        }
+
+        ctx.print_centered(difficulty_y + 3 + Difficulty::ALL.len() as i32, "(M) Back to Menu");
+
+        if self.back_pressed(ctx) {
+            self.mode = GameMode::Menu;
+        }
+    }
+
+    /// 进入高分榜，同时在后台拉取在线排行
+    fn show_high_scores(&mut self) {
+        self.mode = GameMode::HighScores;
+        self.online_scores = match self.leaderboard.clone() {
+            Some(client) => {
+                let (tx, rx) = mpsc::channel();
+                thread::spawn(move || {
+                    let _ = tx.send(client.top(None, 10));
+                });
+                OnlineScores::Loading(rx)
+            }
+            None => OnlineScores::Disabled,
+        };
+    }
+
+    fn poll_online_scores(&mut self) {
+        if let OnlineScores::Loading(rx) = &self.online_scores {
+            self.online_scores = match rx.try_recv() {
+                Ok(Ok(runs)) => OnlineScores::Loaded(runs),
+                Ok(Err(err)) => {
+                    eprintln!("Leaderboard unavailable: {}", err);
+                    OnlineScores::Unavailable
+                }
+                Err(TryRecvError::Disconnected) => OnlineScores::Unavailable,
+                Err(TryRecvError::Empty) => return,
+            };
+        }
     }
 
     pub fn set_background(&mut self, ctx: &mut BTerm, url: &str) {
@@ -497,70 +834,78 @@ impl State {
         }
     }
 
-    fn save_score(score: i32, difficulty: Difficulty) -> io::Result<()> {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("scores.txt")?;
-        writeln!(file, "{}:{:?}", score, difficulty)?;
-        Ok(())
-    }
-
-    fn load_scores() -> Vec<(i32, Difficulty)> {
-        if let Ok(content) = fs::read_to_string("scores.txt") {
-            let mut scores: Vec<(i32, Difficulty)> = content
-                .lines()
-                .filter_map(|line| {
-                    let parts: Vec<&str> = line.split(':').collect();
-                    if parts.len() == 2 {
-                        if let (Ok(score), Ok(difficulty)) = (
-                            parts[0].parse::<i32>(),
-                            parts[1].parse::<Difficulty>(),
-                        ) {
-                            Some((score, difficulty))
-                        } else {
-                            None
-                        }
-                    } else {
-                        // 兼容旧格式（只有分数）
-                        line.parse::<i32>().ok().map(|score| (score, Difficulty::Normal))
-                    }
-                })
-                .collect();
-            scores.sort_unstable_by(|a, b| b.0.cmp(&a.0));
-            scores
-        } else {
-            vec![]
+    fn save_score(&self, entry: ScoreEntry) -> io::Result<()> {
+        self.profile().scores().append(entry)
+    }
+
+    fn load_scores(&self) -> Vec<ScoreEntry> {
+        match self.profile().scores().load() {
+            Ok(mut scores) => {
+                scores.sort_unstable_by_key(|entry| std::cmp::Reverse(entry.score));
+                scores
+            }
+            Err(err) => {
+                eprintln!("Failed to load scores: {}", err);
+                vec![]
+            }
         }
     }
 
     fn display_high_scores(&mut self, ctx: &mut BTerm) {
-        let scores = Self::load_scores();
+        let scores = self.load_scores();
+        self.poll_online_scores();
         ctx.cls();
         self.set_background(ctx, "assets/scores_bg.png");
-        ctx.print_centered(10, "High Scores:");
-        
-        for (i, (score, difficulty)) in scores.iter().enumerate().take(10) {
+        ctx.print_centered(10, format!("High Scores: {}", self.profile().name));
+
+        // 没有配置在线排行榜时保持原来的单列布局
+        let local_x = if matches!(self.online_scores, OnlineScores::Disabled) { 35 } else { 12 };
+        if local_x != 35 {
+            ctx.print(local_x, 12, "Local");
+        }
+        for (i, entry) in scores.iter().enumerate().take(10) {
             ctx.print(
-                35,
+                local_x,
                 13 + i as i32,
-                &format!("{}. {} ({:?})", i + 1, score, difficulty),
+                format!("{}. {} ({:?})", i + 1, entry.score, entry.difficulty),
             );
         }
-        
-        ctx.print_centered(27, "(M) Back to Menu");
 
-        if let Some(key) = ctx.key {
-            match key {
-                VirtualKeyCode::M => self.mode = GameMode::Menu,
-                _ => {}
+        match &self.online_scores {
+            OnlineScores::Disabled => {}
+            OnlineScores::Loading(_) => ctx.print(48, 12, "Global: loading..."),
+            OnlineScores::Loaded(runs) => {
+                ctx.print(48, 12, "Global");
+                for (i, run) in runs.iter().enumerate().take(10) {
+                    ctx.print(
+                        48,
+                        13 + i as i32,
+                        format!("{}. {} ({:?})", i + 1, run.score, run.difficulty),
+                    );
+                }
+            }
+            OnlineScores::Unavailable => {
+                ctx.print(48, 12, "Global: offline");
+                ctx.print(48, 13, "Showing local scores only");
             }
         }
+        
+        let items = [MenuItem::centered(27, VirtualKeyCode::M, "(M) Back to Menu")];
+        let chosen = self.menu.show(ctx, &items, &self.key_bindings);
+        if chosen.is_some() || self.back_pressed(ctx) {
+            self.mode = GameMode::Menu;
+        }
     }
 }
 
 impl GameState for State {
     fn tick(&mut self, ctx: &mut BTerm) {
+        if self.mode != self.last_mode {
+            self.last_mode = self.mode.clone();
+            self.menu.reset();
+            self.idle = Idle::default();
+        }
+        self.update_actions(ctx.key);
         match self.mode {
             GameMode::Menu => self.main_menu(ctx),
             GameMode::DifficultySelect => self.difficulty_select(ctx),
@@ -568,11 +913,133 @@ impl GameState for State {
             GameMode::Paused => self.paused(ctx),
             GameMode::End => self.dead(ctx),
             GameMode::HighScores => self.display_high_scores(ctx),
+            GameMode::Statistics => self.display_statistics(ctx),
+            GameMode::Achievements => self.display_achievements(ctx),
+            GameMode::Profiles => self.display_profiles(ctx),
+            GameMode::Controls => self.display_controls(ctx),
+            GameMode::Results => self.results(ctx),
+            GameMode::Race => self.race_play(ctx),
+            GameMode::Lobby => self.lobby(ctx),
+            GameMode::Spectate => self.spectate(ctx),
+            GameMode::Campaign => self.display_campaign(ctx),
+            GameMode::LevelEnd => self.level_end(ctx),
+            GameMode::Editor => self.editor(ctx),
+        }
+    }
+}
+
+/// 主菜单继续选项上显示的存档信息
+fn saved_run_label(game: &Game) -> String {
+    if game.dragons.len() > 1 {
+        format!("Two Players, {:?}", game.difficulty)
+    } else {
+        format!("{:?}, {} points", game.difficulty, game.dragons[0].score)
+    }
+}
+
+/// 命令行子命令：导出/导入高分记录，批量模拟
+///
+/// 返回 `None` 表示没有子命令，正常启动游戏
+fn run_cli(args: &[String]) -> Option<Result<(), String>> {
+    let command = args.get(1)?;
+    // 观战、机器人和关卡编辑器需要打开窗口，由 main 处理
+    if command == "spectate" || command == "--bot" || command == "edit" {
+        return None;
+    }
+    let usage = "Usage: flappy [export <file.json|file.csv> | import <file.json|file.csv> | simulate [options] | spectate [host:port] | edit <level.json> | --bot]";
+    // 导出/导入当前选中玩家的记录
+    let scores = match ProfileStore::open_default().load_or_init() {
+        Ok(profiles) => profiles.active().scores(),
+        Err(err) => return Some(Err(format!("Failed to load profiles: {}", err))),
+    };
+    let result = match (command.as_str(), args.get(2)) {
+        ("export", Some(path)) => {
+            let path = Path::new(path);
+            scores
+                .export(path, ExportFormat::from_path(path))
+                .map(|count| println!("Exported {} scores to {}", count, path.display()))
+                .map_err(|err| format!("Export failed: {}", err))
+        }
+        ("import", Some(path)) => {
+            let path = Path::new(path);
+            scores
+                .import(path, ExportFormat::from_path(path))
+                .map(|added| println!("Imported {} new scores from {}", added, path.display()))
+                .map_err(|err| format!("Import failed: {}", err))
+        }
+        ("simulate", _) => simulate_command(&args[2..]),
+        _ => Err(usage.to_string()),
+    };
+    Some(result)
+}
+
+/// `simulate` 子命令：不开窗口让机器人批量游戏，输出各难度的分数分布、死因和道具影响
+fn simulate_command(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: flappy simulate [--games N] [--difficulty Easy|Normal|Hard|Insane]... [--threads N] \
+                 [--max-ticks N] [--seed N] [--format json|csv] [--output <file.json|file.csv>]";
+    fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+        value.parse().map_err(|_| format!("Invalid number: {}", value))
+    }
+
+    let mut config = SimulationConfig::default();
+    let mut difficulties = Vec::new();
+    let mut format = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(usage)?;
+        match arg.as_str() {
+            "--games" => config.games = number(value)?,
+            "--difficulty" => difficulties.push(value.parse().map_err(|_| format!("Unknown difficulty: {}", value))?),
+            "--threads" => config.threads = number(value)?,
+            "--max-ticks" => config.max_ticks = number(value)?,
+            "--seed" => config.seed = number(value)?,
+            "--format" => {
+                format = Some(match value.as_str() {
+                    "json" => ExportFormat::Json,
+                    "csv" => ExportFormat::Csv,
+                    _ => return Err(format!("Unknown format: {}", value)),
+                })
+            }
+            "--output" => output = Some(value.clone()),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    if !difficulties.is_empty() {
+        config.difficulties = difficulties;
+    }
+    let format = format.unwrap_or_else(|| output.as_deref().map_or(ExportFormat::Json, |path| ExportFormat::from_path(Path::new(path))));
+
+    let started = std::time::Instant::now();
+    let reports = simulate(&config);
+    eprintln!(
+        "Simulated {} games in {:.1}s on {} threads",
+        config.games * config.difficulties.len(),
+        started.elapsed().as_secs_f32(),
+        config.threads
+    );
+    let content = format_reports(&reports, format).map_err(|err| format!("Failed to format report: {}", err))?;
+    match output {
+        Some(path) => std::fs::write(&path, content)
+            .map(|()| println!("Wrote simulation report to {}", path))
+            .map_err(|err| format!("Failed to write {}: {}", path, err)),
+        None => {
+            print!("{}", content);
+            Ok(())
         }
     }
 }
 
 fn main() -> BError {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(result) = run_cli(&args) {
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // 最小化音频测试
     println!("=== 开始音频测试 ===");
     
@@ -591,11 +1058,37 @@ fn main() -> BError {
         std::thread::sleep(std::time::Duration::from_secs(5));
     }
 
+    // 先打开关卡文件，有错误时不用打开窗口
+    let editor = match (args.get(1).map(String::as_str), args.get(2)) {
+        (Some("edit"), Some(path)) => match Editor::open(Path::new(path)) {
+            Ok(editor) => Some(editor),
+            Err(err) => {
+                eprintln!("Failed to open level: {}", err);
+                std::process::exit(1);
+            }
+        },
+        (Some("edit"), None) => {
+            eprintln!("Usage: flappy edit <level.json>");
+            std::process::exit(1);
+        }
+        _ => None,
+    };
+
     let context = BTermBuilder::simple80x50()
         .with_title("Flappy Dragon - Enhanced Edition")
         .build()?;
 
-    let state = State::new();
+    let mut state = State::new();
+    if args.get(1).is_some_and(|command| command == "spectate") {
+        let addr = args.get(2).cloned().unwrap_or_else(|| DEFAULT_SPECTATE_ADDR.to_string());
+        state.start_watching(addr);
+    }
+    if args.get(1).is_some_and(|command| command == "--bot") {
+        state.start_autopilot(false);
+    }
+    if let Some(editor) = editor {
+        state.open_editor(editor, false);
+    }
     // 使用 bracket-lib 提供的主循环
     main_loop(context, state)
 
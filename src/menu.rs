@@ -0,0 +1,99 @@
+use bracket_lib::prelude::*;
+use flappy::input::{Action, KeyBindings};
+use flappy::SCREEN_WIDTH;
+
+/// 菜单中的一项，可以用快捷键、方向键加确认键或鼠标选择
+pub struct MenuItem {
+    pub x: i32,
+    pub y: i32,
+    pub hotkey: VirtualKeyCode,
+    pub label: String,
+}
+
+impl MenuItem {
+    pub fn new(x: i32, y: i32, hotkey: VirtualKeyCode, label: impl Into<String>) -> Self {
+        MenuItem {
+            x,
+            y,
+            hotkey,
+            label: label.into(),
+        }
+    }
+
+    /// 水平居中的菜单项
+    pub fn centered(y: i32, hotkey: VirtualKeyCode, label: impl Into<String>) -> Self {
+        let label = label.into();
+        let x = (SCREEN_WIDTH - label.len() as i32) / 2;
+        MenuItem::new(x, y, hotkey, label)
+    }
+
+    /// 鼠标是否在这一项上（包括前面的光标位置）
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        y == self.y && x >= self.x - 2 && x < self.x + self.label.len() as i32
+    }
+}
+
+/// 当前界面菜单的光标
+#[derive(Default)]
+pub struct MenuState {
+    cursor: usize,
+    /// 上一帧的鼠标位置，鼠标移动时才用悬停改变光标，避免覆盖键盘操作
+    mouse: (i32, i32),
+    /// 上一帧鼠标左键是否按下；`BTerm::left_click` 在按下和松开时都会触发，所以自己判断按下的瞬间
+    mouse_down: bool,
+}
+
+impl MenuState {
+    /// 切换界面时调用；切换前就按着的鼠标键不算点击
+    pub fn reset(&mut self) {
+        self.cursor = 0;
+        self.mouse_down = true;
+    }
+
+    /// 绘制菜单并处理输入，返回被选中的菜单项的快捷键
+    pub fn show(&mut self, ctx: &mut BTerm, items: &[MenuItem], bindings: &KeyBindings) -> Option<VirtualKeyCode> {
+        if items.is_empty() {
+            return None;
+        }
+        self.cursor = self.cursor.min(items.len() - 1);
+
+        let mouse = ctx.mouse_pos();
+        let hovered = items.iter().position(|item| item.contains(mouse));
+        if mouse != self.mouse {
+            self.mouse = mouse;
+            if let Some(i) = hovered {
+                self.cursor = i;
+            }
+        }
+
+        let mouse_down = INPUT.lock().is_mouse_button_pressed(0);
+        let clicked = mouse_down && !self.mouse_down;
+        self.mouse_down = mouse_down;
+
+        let mut chosen = None;
+        if clicked {
+            if let Some(i) = hovered {
+                self.cursor = i;
+                chosen = Some(items[i].hotkey);
+            }
+        }
+        if let Some(key) = ctx.key {
+            match key {
+                key if bindings.matches(Action::Confirm, key) => chosen = Some(items[self.cursor].hotkey),
+                VirtualKeyCode::Up => self.cursor = (self.cursor + items.len() - 1) % items.len(),
+                VirtualKeyCode::Down => self.cursor = (self.cursor + 1) % items.len(),
+                key if items.iter().any(|item| item.hotkey == key) => chosen = Some(key),
+                _ => {}
+            }
+        }
+
+        for (i, item) in items.iter().enumerate() {
+            if i == self.cursor {
+                ctx.print_color(item.x - 2, item.y, YELLOW, BLACK, format!("> {}", item.label));
+            } else {
+                ctx.print(item.x, item.y, &item.label);
+            }
+        }
+        chosen
+    }
+}
@@ -0,0 +1,389 @@
+use crate::difficulty::Difficulty;
+use crate::game::{Game, TickInput};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// 中继服务地址的环境变量，例如 `127.0.0.1:7879`
+pub const RELAY_ENV: &str = "FLAPPY_RELAY_ADDR";
+/// 本地中继服务的默认监听地址
+pub const DEFAULT_RELAY_ADDR: &str = "127.0.0.1:7879";
+/// 本地输入延迟几个逻辑帧生效，给网络传输留出时间
+pub const INPUT_DELAY: u64 = 4;
+/// 每隔多少个逻辑帧交换一次状态指纹
+pub const HASH_INTERVAL: u64 = 60;
+/// 连接中继服务的超时，地址错误时界面最多卡住这么久
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 客户端与中继服务之间的消息，每条一行 JSON
+///
+/// 对局开始后中继服务只负责把一方的消息原样转发给另一方。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Message {
+    /// 客户端连接后发送，先到的一方决定难度
+    Join { difficulty: Difficulty },
+    /// 中继服务凑齐两名玩家后发给双方
+    Start { seed: u64, difficulty: Difficulty, player: usize },
+    /// 某名玩家在某个逻辑帧的输入
+    Input { tick: u64, bits: u8 },
+    /// 某个逻辑帧结束后的状态指纹
+    Hash { tick: u64, hash: u64 },
+    /// 对手断开连接
+    Left,
+}
+
+impl Message {
+    pub fn read_from(reader: &mut impl BufRead) -> io::Result<Option<Message>> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        serde_json::from_str(&line)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut line = serde_json::to_string(self).map_err(io::Error::other)?;
+        line.push('\n');
+        writer.write_all(line.as_bytes())
+    }
+}
+
+/// 与中继服务的连接，后台线程负责读取消息
+pub struct RelayClient {
+    stream: TcpStream,
+    incoming: Receiver<io::Result<Message>>,
+}
+
+impl RelayClient {
+    /// 连接中继服务并请求加入对局
+    pub fn connect(addr: &str, difficulty: Difficulty) -> io::Result<RelayClient> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "could not resolve relay address"))?;
+        let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        Message::Join { difficulty }.write_to(&stream)?;
+
+        let (sender, incoming) = mpsc::channel();
+        let mut reader = BufReader::new(stream.try_clone()?);
+        thread::spawn(move || loop {
+            let message = match Message::read_from(&mut reader) {
+                Ok(Some(message)) => Ok(message),
+                Ok(None) => Ok(Message::Left),
+                Err(err) => Err(err),
+            };
+            let done = !matches!(message, Ok(Message::Start { .. } | Message::Input { .. } | Message::Hash { .. }));
+            if sender.send(message).is_err() || done {
+                break;
+            }
+        });
+        Ok(RelayClient { stream, incoming })
+    }
+
+    /// 从环境变量读取中继地址，没有配置时使用默认地址
+    pub fn addr_from_env() -> String {
+        std::env::var(RELAY_ENV).unwrap_or_else(|_| DEFAULT_RELAY_ADDR.to_string())
+    }
+
+    pub fn send(&self, message: &Message) -> io::Result<()> {
+        Message::write_to(message, &self.stream)
+    }
+
+    /// 取出一条已经收到的消息，没有时返回 `None`；连接断开时返回 `Left`
+    pub fn try_recv(&self) -> Option<io::Result<Message>> {
+        match self.incoming.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Ok(Message::Left)),
+        }
+    }
+}
+
+impl Drop for RelayClient {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// 各局游戏状态的指纹（对序列化结果做 FNV-1a 64位），双方在同一帧的指纹不同说明模拟已经不同步
+pub fn state_hash(games: &[&Game]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    games
+        .iter()
+        .flat_map(|game| serde_json::to_vec(game).unwrap_or_default())
+        .fold(OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+/// 锁步同步：两名玩家都有某一帧的输入后才推进这一帧
+///
+/// 本地输入延迟 `INPUT_DELAY` 帧生效，双方在各自推进到同一帧时总是用同样的输入。
+#[derive(Debug, Clone)]
+pub struct Lockstep {
+    player: usize,
+    /// 下一个要推进的逻辑帧
+    tick: u64,
+    inputs: [BTreeMap<u64, TickInput>; 2],
+    local_hashes: BTreeMap<u64, u64>,
+    remote_hashes: BTreeMap<u64, u64>,
+    desync: Option<u64>,
+}
+
+impl Lockstep {
+    pub fn new(player: usize) -> Self {
+        let delay: BTreeMap<u64, TickInput> = (0..INPUT_DELAY).map(|tick| (tick, TickInput::default())).collect();
+        Lockstep {
+            player,
+            tick: 0,
+            inputs: [delay.clone(), delay],
+            local_hashes: BTreeMap::new(),
+            remote_hashes: BTreeMap::new(),
+            desync: None,
+        }
+    }
+
+    /// 本地玩家的编号
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// 第一次发现状态不一致的逻辑帧
+    pub fn desync(&self) -> Option<u64> {
+        self.desync
+    }
+
+    /// 对手这一帧的输入是否已经到达
+    pub fn ready(&self) -> bool {
+        self.inputs[1 - self.player].contains_key(&self.tick)
+    }
+
+    /// 处理对手发来的输入和指纹
+    pub fn receive(&mut self, message: &Message) {
+        match *message {
+            Message::Input { tick, bits } => {
+                self.inputs[1 - self.player].insert(tick, TickInput::from_bits(bits));
+            }
+            Message::Hash { tick, hash } => {
+                self.remote_hashes.insert(tick, hash);
+                self.check_hash(tick);
+            }
+            _ => {}
+        }
+    }
+
+    /// 对手的输入到达时推进一帧：本地输入排到 `INPUT_DELAY` 帧之后，返回这一帧双方的输入和要发给对手的消息
+    pub fn advance(&mut self, local: TickInput) -> Option<([TickInput; 2], Message)> {
+        if !self.ready() {
+            return None;
+        }
+        let scheduled = self.tick + INPUT_DELAY;
+        self.inputs[self.player].insert(scheduled, local);
+        let message = Message::Input {
+            tick: scheduled,
+            bits: local.to_bits(),
+        };
+        let inputs = [0, 1].map(|player| self.inputs[player].remove(&self.tick).unwrap_or_default());
+        self.tick += 1;
+        Some((inputs, message))
+    }
+
+    /// 推进一帧后调用，到了交换指纹的帧时记录本地指纹并返回要发给对手的消息
+    pub fn record_hash(&mut self, hash: impl FnOnce() -> u64) -> Option<Message> {
+        if !self.tick.is_multiple_of(HASH_INTERVAL) {
+            return None;
+        }
+        let tick = self.tick;
+        let hash = hash();
+        self.local_hashes.insert(tick, hash);
+        self.check_hash(tick);
+        Some(Message::Hash { tick, hash })
+    }
+
+    fn check_hash(&mut self, tick: u64) {
+        if let (Some(local), Some(remote)) = (self.local_hashes.get(&tick), self.remote_hashes.get(&tick)) {
+            if local != remote && self.desync.is_none() {
+                self.desync = Some(tick);
+            }
+            self.local_hashes.remove(&tick);
+            self.remote_hashes.remove(&tick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Shutdown, TcpListener};
+    use std::time::Instant;
+
+    /// 在回环地址上模拟中继服务：凑齐两个连接后开局，然后双向原样转发
+    fn relay() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let mut peers = Vec::new();
+            for (player, stream) in listener.incoming().take(2).enumerate() {
+                let stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let difficulty = match Message::read_from(&mut reader).unwrap() {
+                    Some(Message::Join { difficulty }) => difficulty,
+                    other => panic!("expected join, got {:?}", other),
+                };
+                peers.push((stream, reader, difficulty, player));
+            }
+            let difficulty = peers[0].2.clone();
+            let mut outputs = Vec::new();
+            for (stream, _, _, player) in &peers {
+                Message::Start { seed: 9, difficulty: difficulty.clone(), player: *player }.write_to(stream).unwrap();
+                outputs.push(stream.try_clone().unwrap());
+            }
+            outputs.reverse();
+            for ((_, mut reader, _, _), mut to) in peers.into_iter().zip(outputs) {
+                thread::spawn(move || {
+                    let mut line = String::new();
+                    while matches!(reader.read_line(&mut line), Ok(n) if n > 0) {
+                        if to.write_all(line.as_bytes()).is_err() {
+                            break;
+                        }
+                        line.clear();
+                    }
+                    let _ = Message::Left.write_to(&to);
+                    let _ = to.shutdown(Shutdown::Both);
+                });
+            }
+        });
+        addr
+    }
+
+    /// 一方的客户端、锁步状态和双人对局
+    struct Side {
+        client: RelayClient,
+        lockstep: Lockstep,
+        game: Game,
+    }
+
+    fn recv(client: &RelayClient) -> Message {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(message) = client.try_recv() {
+                return message.unwrap();
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for the relay");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn connect_pair() -> [Side; 2] {
+        let addr = relay();
+        let clients = [Difficulty::Hard, Difficulty::Easy].map(|difficulty| RelayClient::connect(&addr, difficulty).unwrap());
+        clients.map(|client| match recv(&client) {
+            Message::Start { seed, difficulty, player } => {
+                assert_eq!((seed, &difficulty), (9, &Difficulty::Hard));
+                Side {
+                    client,
+                    lockstep: Lockstep::new(player),
+                    game: Game::with_players(difficulty, seed, 2),
+                }
+            }
+            other => panic!("expected start, got {:?}", other),
+        })
+    }
+
+    /// 双方轮流推进，直到都到达 `ticks`；`tamper` 让第一方在交换指纹时报告错误的指纹
+    fn play(sides: &mut [Side; 2], ticks: u64, tamper: bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while sides.iter().any(|side| side.lockstep.tick() < ticks) {
+            assert!(Instant::now() < deadline, "lockstep stalled");
+            for (i, side) in sides.iter_mut().enumerate() {
+                while let Some(message) = side.client.try_recv() {
+                    side.lockstep.receive(&message.unwrap());
+                }
+                if side.lockstep.tick() >= ticks {
+                    continue;
+                }
+                let tick = side.lockstep.tick();
+                let local = TickInput {
+                    flap: tick % (7 + i as u64) == 0,
+                    ..Default::default()
+                };
+                let Some((inputs, message)) = side.lockstep.advance(local) else {
+                    continue;
+                };
+                side.client.send(&message).unwrap();
+                side.game.tick_players(&inputs);
+                let game = &side.game;
+                let hash = side.lockstep.record_hash(|| state_hash(&[game]) + (tamper && i == 0) as u64);
+                if let Some(message) = hash {
+                    side.client.send(&message).unwrap();
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn lockstep_keeps_both_sides_in_sync() {
+        // 对手的输入到达之前最多推进输入延迟的帧数
+        let mut alone = Lockstep::new(0);
+        for _ in 0..INPUT_DELAY {
+            assert!(alone.advance(TickInput::default()).is_some());
+        }
+        assert!(alone.advance(TickInput::default()).is_none());
+
+        let mut sides = connect_pair();
+        assert_eq!([sides[0].lockstep.player(), sides[1].lockstep.player()], [0, 1]);
+
+        play(&mut sides, HASH_INTERVAL * 3 + 1, false);
+        let [a, b] = &sides;
+        assert_eq!(serde_json::to_string(&a.game).unwrap(), serde_json::to_string(&b.game).unwrap());
+        assert_eq!(state_hash(&[&a.game]), state_hash(&[&b.game]));
+        assert_eq!((a.lockstep.desync(), b.lockstep.desync()), (None, None));
+    }
+
+    #[test]
+    fn mismatched_hashes_are_reported() {
+        let mut sides = connect_pair();
+        play(&mut sides, HASH_INTERVAL * 2 + INPUT_DELAY, true);
+        for side in &mut sides {
+            while let Some(message) = side.client.try_recv() {
+                side.lockstep.receive(&message.unwrap());
+            }
+        }
+        // 第一次交换指纹就发现不一致，之后只记录最早的一帧
+        assert_eq!(sides[0].lockstep.desync(), Some(HASH_INTERVAL));
+        assert_eq!(sides[1].lockstep.desync(), Some(HASH_INTERVAL));
+    }
+
+    #[test]
+    fn peer_disconnect_is_reported() {
+        let [a, b] = connect_pair();
+        drop(b);
+        assert_eq!(recv(&a.client), Message::Left);
+        // 连接关闭后一直报告对手已离开
+        assert_eq!(a.client.try_recv().map(Result::unwrap), Some(Message::Left));
+    }
+
+    #[test]
+    fn unreachable_relay_fails_fast() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let started = Instant::now();
+        assert!(RelayClient::connect(&addr, Difficulty::Normal).is_err());
+        assert!(RelayClient::connect("not an address", Difficulty::Normal).is_err());
+        assert!(started.elapsed() < CONNECT_TIMEOUT * 2);
+    }
+}
@@ -1,55 +1,226 @@
 use bracket_lib::prelude::*;
-use crate::{player::Player, difficulty::DifficultySettings, SCREEN_HEIGHT};
+use crate::biome::Biome;
+use crate::render::Canvas;
+use crate::{player::Player, difficulty::DifficultySettings, SCREEN_HEIGHT, SCREEN_WIDTH};
+use serde::{Deserialize, Serialize};
 
+/// 空隙中心的随机范围 `GAP_MIN..GAP_MAX`
+const GAP_MIN: i32 = 10;
+const GAP_MAX: i32 = 40;
+/// 移动障碍物每 20 个逻辑帧移动一格，穿过整个屏幕期间最多偏离的格数
+const MAX_DRIFT: i32 = SCREEN_WIDTH / 20 + 1;
+/// 刚扇动过时下冲要先抵消向上的速度，这几个逻辑帧里不一定能往下走
+const DIVE_WINDUP: i32 = 8;
+/// 激光的周期（逻辑帧）：每个周期最后一段时间射出光束封住空隙，之前闪烁提示
+const LASER_CYCLE: i32 = 96;
+const LASER_ON: i32 = 24;
+const LASER_WARNING: i32 = 18;
+/// 压碎机的周期：张开一段时间后合拢，停顿一下再张开
+const CRUSHER_CYCLE: i32 = 120;
+const CRUSHER_OPEN: i32 = 70;
+const CRUSHER_MOVE: i32 = 15;
+/// 激光和压碎机在龙按原速到达前后这么多逻辑帧内保持完全张开
+const ARRIVAL_MARGIN: i32 = 3;
+
+/// 龙在障碍物到达之前一定能到达的高度范围（含两端）
+///
+/// 只考虑正常的操作：扇动时每次重力更新最多上升一格，下冲时大约每个逻辑帧下降一格。
+/// 空隙中心落在这个范围内，龙就一定有办法在障碍物到达时待在空隙里。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reach {
+    pub top: i32,
+    pub bottom: i32,
+}
+
+impl Reach {
+    /// 不限制高度，空隙可以出现在任何位置
+    pub const ANYWHERE: Reach = Reach { top: 0, bottom: SCREEN_HEIGHT };
+
+    /// 从高度 `y` 出发，`ticks` 个逻辑帧后能到达的范围；`ticks_per_move` 是两次重力更新之间的逻辑帧数
+    pub fn from_player(y: i32, ticks: i32, ticks_per_move: i32) -> Self {
+        // 第一次重力更新可能在扇动之前，还会按原来的速度往下掉一格
+        let up = ((ticks - 1) / ticks_per_move - 1).max(0);
+        let down = (ticks - DIVE_WINDUP).max(0);
+        Reach {
+            top: (y - up).max(0),
+            bottom: (y + down).min(SCREEN_HEIGHT),
+        }
+    }
+
+    /// 两个范围的交集，没有重叠时为 `None`
+    pub fn intersect(self, other: Reach) -> Option<Reach> {
+        let reach = Reach {
+            top: self.top.max(other.top),
+            bottom: self.bottom.min(other.bottom),
+        };
+        (reach.top <= reach.bottom).then_some(reach)
+    }
+
+    pub fn contains(&self, y: i32) -> bool {
+        (self.top..=self.bottom).contains(&y)
+    }
+
+    /// 两端各收缩 `margin` 格
+    fn shrink(self, margin: i32) -> Option<Reach> {
+        let reach = Reach {
+            top: self.top + margin,
+            bottom: self.bottom - margin,
+        };
+        (reach.top <= reach.bottom).then_some(reach)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Obstacle {
     pub x: i32,
     pub gap_y: i32,
     pub size: i32,
     pub speed: f32,
     pub obstacle_type: ObstacleType,
+    move_timer: f32,
+    move_direction: i32,
+    /// 被火球烧毁后不再阻挡
+    #[serde(default)]
+    pub destroyed: bool,
+    /// 激光和压碎机周期内经过的逻辑帧数
+    #[serde(default)]
+    timer: i32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ObstacleType {
     Static,    // 静态障碍物
     Moving,    // 上下移动的障碍物
     Rotating,  // 旋转障碍物（视觉效果）
+    Breakable, // 可以被火球烧毁的障碍物
+    Laser,     // 空隙中周期性地射出激光
+    Crusher,   // 空隙周期性地合拢再张开
+    SpikeWall, // 只有一侧的半高尖刺墙
+}
+
+impl ObstacleType {
+    pub const ALL: [ObstacleType; 7] = [
+        ObstacleType::Static,
+        ObstacleType::Moving,
+        ObstacleType::Rotating,
+        ObstacleType::Breakable,
+        ObstacleType::Laser,
+        ObstacleType::Crusher,
+        ObstacleType::SpikeWall,
+    ];
 }
 
 impl Obstacle {
-    pub fn new(x: i32, score: i32) -> Self {
-        let mut random = RandomNumberGenerator::new();
-        
-        // 根据分数决定障碍物类型
-        let obstacle_type = if score > 20 && random.range(0, 100) < 30 {
-            if random.range(0, 2) == 0 {
-                ObstacleType::Moving
-            } else {
-                ObstacleType::Rotating
+    /// 空隙中心只会出现在 `reach` 内，保证龙能从当前位置飞进去；障碍物类型按生物群系的权重选择
+    ///
+    /// `arrivals` 是各条龙不左右移动时到达障碍物所需的逻辑帧数，激光和压碎机会在这些时刻保持张开。
+    pub fn new(
+        x: i32,
+        score: i32,
+        random: &mut RandomNumberGenerator,
+        reach: Reach,
+        biome: Biome,
+        arrivals: &[i32],
+    ) -> Self {
+        // 开局的 20 分只有静态障碍物
+        let mut obstacle_type = if score > 20 { biome.pick_obstacle_type(random) } else { ObstacleType::Static };
+
+        // 随机范围和可达范围没有交集时（龙贴着屏幕边缘），取可达范围里最接近的一端
+        let candidates = Reach { top: GAP_MIN, bottom: GAP_MAX - 1 };
+        let mut gaps = reach.intersect(candidates);
+        if obstacle_type == ObstacleType::Moving {
+            // 移动障碍物的空隙在途中还会偏离，放不下就换成静态的
+            match gaps.and_then(|gaps| gaps.shrink(MAX_DRIFT)) {
+                Some(narrowed) => gaps = Some(narrowed),
+                None => obstacle_type = ObstacleType::Static,
             }
-        } else {
-            ObstacleType::Static
+        }
+        let gap_y = match gaps {
+            Some(gaps) => random.range(gaps.top, gaps.bottom + 1),
+            None if reach.bottom < GAP_MIN => reach.bottom,
+            None => reach.top,
         };
 
-        Obstacle {
+        let mut obstacle = Obstacle {
             x,
-            gap_y: random.range(10, 40),
+            gap_y,
             size: i32::max(4, 20 - score), // 基础大小，随分数减小
             speed: 1.0,
             obstacle_type,
+            move_timer: 0.0,
+            move_direction: 1,
+            destroyed: false,
+            timer: 0,
+        };
+        obstacle.timer = obstacle.starting_timer(random, arrivals);
+        obstacle
+    }
+
+    /// 激光和压碎机从周期中随机的位置开始，但龙到达时空隙一定完全张开
+    ///
+    /// 找不到对所有龙都合适的位置时，只保证最先到达的龙。
+    fn starting_timer(&self, random: &mut RandomNumberGenerator, arrivals: &[i32]) -> i32 {
+        let cycle = match self.obstacle_type {
+            ObstacleType::Laser => LASER_CYCLE,
+            ObstacleType::Crusher => CRUSHER_CYCLE,
+            _ => return 0,
+        };
+        let open_on_arrival = |timer: i32, arrival: i32| {
+            (arrival - ARRIVAL_MARGIN..=arrival + ARRIVAL_MARGIN).all(|ticks| self.fully_open(timer + ticks.max(0)))
+        };
+        let first = arrivals.iter().copied().min().unwrap_or(0);
+        let mut timers: Vec<i32> =
+            (0..cycle).filter(|&timer| arrivals.iter().all(|&arrival| open_on_arrival(timer, arrival))).collect();
+        if timers.is_empty() {
+            timers = (0..cycle).filter(|&timer| open_on_arrival(timer, first)).collect();
         }
+        timers[random.range(0, timers.len() as i32) as usize]
     }
 
-    pub fn render(&mut self, ctx: &mut BTerm, player_x: i32, difficulty_settings: &DifficultySettings) {
-        // 应用难度设置
-        let dynamic_speed = difficulty_settings.get_dynamic_speed(0) as i32;
-        let actual_size = difficulty_settings.get_dynamic_gap_size(self.size, 0);
-        
-        // 根据难度调整移动速度
-        self.x -= dynamic_speed.max(1);
-        
+    /// 关卡文件中指定位置和空隙的障碍物
+    pub fn placed(x: i32, gap_y: i32, size: i32, obstacle_type: ObstacleType) -> Self {
+        Obstacle {
+            x,
+            gap_y,
+            size,
+            speed: 1.0,
+            obstacle_type,
+            move_timer: 0.0,
+            move_direction: 1,
+            destroyed: false,
+            timer: 0,
+        }
+    }
+
+    /// 每个逻辑帧向左移动的格数，由难度决定
+    pub fn step(difficulty_settings: &DifficultySettings) -> i32 {
+        (difficulty_settings.get_dynamic_speed(0) as i32).max(1)
+    }
+
+    /// 每个逻辑帧调用一次：向左移动 `step` 格并更新移动障碍物的位置
+    pub fn update(&mut self, step: i32) {
+        self.x -= step;
+
         // 处理移动障碍物
         self.update_position();
+    }
+
+    pub fn render(&self, ctx: &mut impl Canvas, _player_x: i32, difficulty_settings: &DifficultySettings) {
+        // 烧毁后只剩下一些碎石
+        if self.destroyed {
+            for y in (0..SCREEN_HEIGHT).step_by(4) {
+                ctx.set(self.x, y, GRAY, BLACK, to_cp437('.'));
+            }
+            return;
+        }
+
+        if matches!(self.obstacle_type, ObstacleType::Laser | ObstacleType::Crusher | ObstacleType::SpikeWall) {
+            self.render_timed(ctx);
+            return;
+        }
+
+        // 应用难度设置
+        let actual_size = difficulty_settings.get_dynamic_gap_size(self.size, 0);
         
         let half_size = actual_size / 2;
         
@@ -72,34 +243,83 @@ impl Obstacle {
         }
     }
 
+    /// 激光、压碎机和尖刺墙按实际碰撞的格子画出来
+    fn render_timed(&self, ctx: &mut impl Canvas) {
+        let half_size = self.size / 2;
+        let (top, bottom) = (self.gap_y - half_size, self.gap_y + half_size);
+        match self.obstacle_type {
+            ObstacleType::Laser => {
+                for y in (0..top).chain(bottom + 1..SCREEN_HEIGHT) {
+                    ctx.set(self.x, y, GRAY, BLACK, to_cp437('█'));
+                }
+                // 两端的发射器，快要射出时闪烁
+                let phase = self.timer % LASER_CYCLE;
+                let warning = phase >= LASER_CYCLE - LASER_ON - LASER_WARNING && phase % 4 < 2;
+                let emitter = if warning { YELLOW } else { RED };
+                ctx.set(self.x, top - 1, emitter, BLACK, to_cp437('▼'));
+                ctx.set(self.x, bottom + 1, emitter, BLACK, to_cp437('▲'));
+                if Self::laser_on(self.timer) {
+                    for y in top..=bottom {
+                        let color = if (y + self.timer) % 2 == 0 { RED } else { ORANGE_RED };
+                        ctx.set(self.x, y, color, BLACK, to_cp437('│'));
+                    }
+                }
+            }
+            ObstacleType::Crusher => {
+                let (open_top, open_bottom) = self.open_rows(self.timer).unwrap_or((self.gap_y, self.gap_y - 1));
+                for y in 0..open_top {
+                    let glyph = if y == open_top - 1 { '▀' } else { '▓' };
+                    ctx.set(self.x, y, STEEL_BLUE, BLACK, to_cp437(glyph));
+                }
+                for y in open_bottom + 1..SCREEN_HEIGHT {
+                    let glyph = if y == open_bottom + 1 { '▄' } else { '▓' };
+                    ctx.set(self.x, y, STEEL_BLUE, BLACK, to_cp437(glyph));
+                }
+            }
+            ObstacleType::SpikeWall => {
+                if self.spikes_from_bottom() {
+                    ctx.set(self.x, bottom + 1, LIGHT_GRAY, BLACK, to_cp437('^'));
+                    for y in bottom + 2..SCREEN_HEIGHT {
+                        ctx.set(self.x, y, DARK_GRAY, BLACK, to_cp437('▓'));
+                    }
+                } else {
+                    for y in 0..top - 1 {
+                        ctx.set(self.x, y, DARK_GRAY, BLACK, to_cp437('▓'));
+                    }
+                    ctx.set(self.x, top - 1, LIGHT_GRAY, BLACK, to_cp437('v'));
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn update_position(&mut self) {
+        self.timer += 1;
         match self.obstacle_type {
             ObstacleType::Moving => {
                 // 上下移动逻辑
-                static mut MOVE_TIMER: f32 = 0.0;
-                static mut MOVE_DIRECTION: i32 = 1;
-                
-                unsafe {
-                    MOVE_TIMER += 1.0;
-                    if MOVE_TIMER > 20.0 {
-                        self.gap_y += MOVE_DIRECTION;
-                        MOVE_TIMER = 0.0;
-                        
-                        // 边界检查
-                        if self.gap_y <= 15 {
-                            MOVE_DIRECTION = 1;
-                        } else if self.gap_y >= 35 {
-                            MOVE_DIRECTION = -1;
-                        }
+                self.move_timer += 1.0;
+                if self.move_timer > 20.0 {
+                    self.gap_y += self.move_direction;
+                    self.move_timer = 0.0;
+
+                    // 边界检查
+                    if self.gap_y <= 15 {
+                        self.move_direction = 1;
+                    } else if self.gap_y >= 35 {
+                        self.move_direction = -1;
                     }
                 }
             }
             ObstacleType::Rotating => {
                 // 旋转只是视觉效果，不改变实际碰撞
             }
-            ObstacleType::Static => {
+            ObstacleType::Static | ObstacleType::Breakable | ObstacleType::SpikeWall => {
                 // 静态障碍物不移动
             }
+            ObstacleType::Laser | ObstacleType::Crusher => {
+                // 按计时器开关，位置不变
+            }
         }
     }
 
@@ -113,15 +333,66 @@ impl Obstacle {
                 let char_index = ((self.x / 3) % 4) as usize;
                 (rotation_chars[char_index], MAGENTA)
             }
+            ObstacleType::Breakable => ('#', (190, 130, 70)),
+            ObstacleType::Laser => ('█', GRAY),
+            ObstacleType::Crusher => ('▓', STEEL_BLUE),
+            ObstacleType::SpikeWall => ('▓', DARK_GRAY),
         }
     }
 
     pub fn hit_obstacle(&self, player: &Player) -> bool {
+        player.x == self.x && self.blocks(player.y)
+    }
+
+    /// 第 `y` 行是否有障碍物（不在空隙里并且没有被烧毁）
+    pub fn blocks(&self, y: i32) -> bool {
+        let in_gap = self.open_rows(self.timer).is_some_and(|(top, bottom)| (top..=bottom).contains(&y));
+        !self.destroyed && !in_gap
+    }
+
+    /// `ticks` 个逻辑帧后可以通过的行（含两端），激光射出或者压碎机合拢时为 `None`
+    pub fn gap_after(&self, ticks: i32) -> Option<(i32, i32)> {
+        self.open_rows(self.timer + ticks)
+    }
+
+    /// 计时器为 `timer` 时空隙的范围
+    fn open_rows(&self, timer: i32) -> Option<(i32, i32)> {
+        let half_size = self.size / 2;
+        let (top, bottom) = (self.gap_y - half_size, self.gap_y + half_size);
+        match self.obstacle_type {
+            ObstacleType::Laser if Self::laser_on(timer) => None,
+            ObstacleType::Crusher => {
+                // 合拢和张开时每侧推进的格数，合拢到底时两侧相接
+                let phase = timer % CRUSHER_CYCLE - CRUSHER_OPEN;
+                let closing = phase.min(CRUSHER_CYCLE - CRUSHER_OPEN - phase).clamp(0, CRUSHER_MOVE);
+                let crush = closing * (half_size + 1) / CRUSHER_MOVE;
+                (top + crush <= bottom - crush).then_some((top + crush, bottom - crush))
+            }
+            // 尖刺墙只有离空隙较远的一侧，另一侧一直通到屏幕边缘
+            ObstacleType::SpikeWall if self.spikes_from_bottom() => Some((0, bottom)),
+            ObstacleType::SpikeWall => Some((top, SCREEN_HEIGHT)),
+            _ => Some((top, bottom)),
+        }
+    }
+
+    /// 计时器为 `timer` 时空隙是否和原本的大小一样
+    fn fully_open(&self, timer: i32) -> bool {
         let half_size = self.size / 2;
-        let does_x_match = player.x == self.x;
-        let player_above_gap = player.y < self.gap_y - half_size;
-        let player_below_gap = player.y > self.gap_y + half_size;
-        does_x_match && (player_above_gap || player_below_gap)
+        self.open_rows(timer) == Some((self.gap_y - half_size, self.gap_y + half_size))
+    }
+
+    fn laser_on(timer: i32) -> bool {
+        timer % LASER_CYCLE >= LASER_CYCLE - LASER_ON
+    }
+
+    /// 空隙偏上时尖刺墙从下面伸上来，否则从上面垂下来
+    fn spikes_from_bottom(&self) -> bool {
+        self.gap_y < SCREEN_HEIGHT / 2
+    }
+
+    /// 只有可以烧毁的障碍物会被火球烧毁
+    pub fn can_burn(&self) -> bool {
+        self.obstacle_type == ObstacleType::Breakable && !self.destroyed
     }
 
     /// 检查玩家是否成功通过障碍物
@@ -135,6 +406,98 @@ impl Obstacle {
             ObstacleType::Static => 1,
             ObstacleType::Moving => 2,
             ObstacleType::Rotating => 3,
+            ObstacleType::Breakable => 1,
+            ObstacleType::Laser => 3,
+            ObstacleType::Crusher => 3,
+            ObstacleType::SpikeWall => 2,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(obstacle_type: ObstacleType, gap_y: i32, timer: i32) -> Obstacle {
+        let mut obstacle = Obstacle::placed(30, gap_y, 10, obstacle_type);
+        obstacle.timer = timer;
+        obstacle
+    }
+
+    #[test]
+    fn laser_fires_at_the_end_of_each_cycle() {
+        for timer in 0..LASER_CYCLE * 2 {
+            let laser = at(ObstacleType::Laser, 25, timer);
+            let on = timer % LASER_CYCLE >= LASER_CYCLE - LASER_ON;
+            assert_eq!(laser.open_rows(timer), if on { None } else { Some((20, 30)) }, "timer {}", timer);
+            assert_eq!(laser.blocks(25), on, "timer {}", timer);
+            assert!(laser.blocks(19) && laser.blocks(31));
+        }
+        let laser = at(ObstacleType::Laser, 25, LASER_CYCLE - LASER_ON - 1);
+        assert_eq!(laser.gap_after(0), Some((20, 30)));
+        assert_eq!(laser.gap_after(1), None);
+        assert_eq!(laser.gap_after(LASER_ON + 1), Some((20, 30)));
+    }
+
+    #[test]
+    fn crusher_closes_and_reopens() {
+        let crusher = at(ObstacleType::Crusher, 25, 0);
+        let rows: Vec<_> = (0..=CRUSHER_CYCLE).map(|timer| crusher.open_rows(timer)).collect();
+        // 张开期间保持原来的空隙
+        assert!(rows[..=CRUSHER_OPEN as usize].iter().all(|&open| open == Some((20, 30))));
+        // 合拢时两侧同时推进，空隙只会变小
+        for timer in CRUSHER_OPEN..CRUSHER_OPEN + CRUSHER_MOVE {
+            let (now, next) = (rows[timer as usize].unwrap(), rows[timer as usize + 1]);
+            assert_eq!(now.0 - 20, 30 - now.1);
+            assert!(next.is_none_or(|next| next.0 >= now.0 && next.1 <= now.1), "timer {}", timer);
         }
+        assert_eq!(rows[(CRUSHER_OPEN + CRUSHER_MOVE) as usize], None);
+        assert!(at(ObstacleType::Crusher, 25, CRUSHER_OPEN + CRUSHER_MOVE).blocks(25));
+        // 停顿之后按相反的顺序张开，一个周期后回到原来的空隙
+        for timer in CRUSHER_OPEN..CRUSHER_CYCLE {
+            let mirrored = CRUSHER_CYCLE + CRUSHER_OPEN - timer;
+            assert_eq!(rows[timer as usize], rows[mirrored as usize], "timer {}", timer);
+        }
+        assert_eq!(rows[CRUSHER_CYCLE as usize], Some((20, 30)));
+        assert!(!at(ObstacleType::Crusher, 25, CRUSHER_CYCLE).blocks(25));
+    }
+
+    #[test]
+    fn spike_wall_covers_one_side() {
+        // 空隙偏上：尖刺从下面伸到空隙下沿，上面一直通到屏幕顶端
+        let low = at(ObstacleType::SpikeWall, 15, 0);
+        assert_eq!(low.open_rows(0), Some((0, 20)));
+        assert!(!low.blocks(0) && !low.blocks(20));
+        assert!(low.blocks(21) && low.blocks(SCREEN_HEIGHT - 1));
+
+        // 空隙偏下：尖刺从上面垂到空隙上沿
+        let high = at(ObstacleType::SpikeWall, 35, 0);
+        assert_eq!(high.open_rows(0), Some((30, SCREEN_HEIGHT)));
+        assert!(high.blocks(0) && high.blocks(29));
+        assert!(!high.blocks(30) && !high.blocks(SCREEN_HEIGHT - 1));
+
+        // 尖刺墙不随时间变化
+        assert!((0..200).all(|timer| low.open_rows(timer) == low.open_rows(0)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn timed_obstacles_are_open_on_arrival() {
+        let mut random = RandomNumberGenerator::seeded(5);
+        let reach = Reach::ANYWHERE;
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..2000 {
+            let arrival = random.range(0, 80);
+            let arrivals = [arrival, arrival + random.range(0, 10)];
+            let biome = Biome::ALL[i % Biome::ALL.len()];
+            let obstacle = Obstacle::new(SCREEN_WIDTH, 30, &mut random, reach, biome, &arrivals);
+            seen.insert(obstacle.obstacle_type.clone());
+            let half_size = obstacle.size / 2;
+            let gap = Some((obstacle.gap_y - half_size, obstacle.gap_y + half_size));
+            for ticks in arrivals.iter().flat_map(|&a| (a - ARRIVAL_MARGIN).max(0)..=a + ARRIVAL_MARGIN) {
+                if obstacle.obstacle_type != ObstacleType::SpikeWall {
+                    assert_eq!(obstacle.gap_after(ticks), gap, "{:?} after {}", obstacle.obstacle_type, ticks);
+                }
+            }
+        }
+        assert!(seen.contains(&ObstacleType::Laser) && seen.contains(&ObstacleType::Crusher));
+    }
+}
@@ -0,0 +1,179 @@
+use bracket_lib::prelude::*;
+use flappy::input::Action;
+use flappy::netplay::{state_hash, Lockstep, Message, RelayClient};
+use flappy::TICK_DURATION;
+use crate::menu::MenuItem;
+use crate::{GameMode, State};
+
+/// 联机对战的连接
+pub struct NetSession {
+    addr: String,
+    client: Option<RelayClient>,
+    /// 中继服务开始对局之前为 `None`
+    pub lockstep: Option<Lockstep>,
+    /// 连接失败或对手断开时显示的消息
+    message: Option<String>,
+}
+
+impl NetSession {
+    /// 本地玩家的编号，对局开始之前为 `None`
+    pub fn player(&self) -> Option<usize> {
+        self.lockstep.as_ref().map(Lockstep::player)
+    }
+
+    fn send(&mut self, message: &Message) {
+        if let Some(client) = &self.client {
+            if let Err(err) = client.send(message) {
+                self.message = Some(format!("Connection lost: {}", err));
+            }
+        }
+    }
+
+    /// 处理已经收到的消息，返回中继服务发来的开局消息
+    fn poll(&mut self) -> Option<Message> {
+        let mut start = None;
+        while let Some(received) = self.client.as_ref().and_then(RelayClient::try_recv) {
+            match received {
+                Ok(message @ Message::Start { .. }) => start = Some(message),
+                Ok(Message::Left) => {
+                    self.message = Some("Opponent disconnected".to_string());
+                    self.client = None;
+                }
+                Ok(message) => {
+                    if let Some(lockstep) = &mut self.lockstep {
+                        lockstep.receive(&message);
+                    }
+                }
+                Err(err) => {
+                    self.message = Some(format!("Connection lost: {}", err));
+                    self.client = None;
+                }
+            }
+        }
+        start
+    }
+}
+
+impl State {
+    /// 连接中继服务，等待另一名玩家加入
+    pub(crate) fn start_online(&mut self) {
+        let addr = RelayClient::addr_from_env();
+        let (client, message) = match RelayClient::connect(&addr, self.selected_difficulty.clone()) {
+            Ok(client) => (Some(client), None),
+            Err(err) => (None, Some(format!("Failed to connect: {}", err))),
+        };
+        self.net = Some(NetSession {
+            addr,
+            client,
+            lockstep: None,
+            message,
+        });
+        self.mode = GameMode::Lobby;
+    }
+
+    /// 回到主菜单，联机时同时断开连接，机器人操作的对局也随之结束
+    pub(crate) fn quit_to_menu(&mut self) {
+        self.net = None;
+        self.autopilot = None;
+        self.mode = GameMode::Menu;
+    }
+
+    pub(crate) fn lobby(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/menu_bg.png");
+        ctx.print_centered(5, "Online Race");
+
+        let Some(net) = self.net.as_mut() else {
+            self.mode = GameMode::Menu;
+            return;
+        };
+        if let Some(Message::Start { seed, difficulty, player }) = net.poll() {
+            net.lockstep = Some(Lockstep::new(player));
+            self.start_race_with(difficulty, seed);
+            return;
+        }
+        ctx.print_centered(8, format!("Relay: {}", net.addr));
+        match &net.message {
+            Some(message) => ctx.print_color_centered(10, RED, BLACK, message),
+            None => ctx.print_centered(10, "Waiting for an opponent..."),
+        }
+
+        let items = [MenuItem::centered(14, VirtualKeyCode::M, "(M) Main Menu")];
+        let back = ctx.key.is_some_and(|key| self.key_bindings.matches(Action::Back, key));
+        if back || self.menu.show(ctx, &items, &self.key_bindings).is_some() {
+            self.quit_to_menu();
+        }
+    }
+
+    /// 锁步推进两局游戏：对手的输入没有到达时等待
+    pub(crate) fn online_ticks(&mut self) {
+        let (Some(net), Some(rival)) = (self.net.as_mut(), self.race.as_mut()) else {
+            return;
+        };
+        net.poll();
+        while self.tick_accumulator >= TICK_DURATION && !self.game.is_over() && !rival.is_over() {
+            let Some(lockstep) = net.lockstep.as_mut().filter(|lockstep| lockstep.ready()) else {
+                break;
+            };
+            if net.message.is_some() {
+                break;
+            }
+            self.tick_accumulator -= TICK_DURATION;
+            // 本地玩家总是使用一号玩家的按键
+            let local = self.hold_repeat.tick(&self.actions, 0, std::mem::take(&mut self.pending_inputs[0]));
+            let Some((inputs, outgoing)) = lockstep.advance(local) else {
+                break;
+            };
+            for (game, input) in [&mut self.game, &mut *rival].into_iter().zip(inputs) {
+                game.tick(input);
+                game.drain_events();
+            }
+            let hash = lockstep.record_hash(|| state_hash(&[&self.game, &*rival]));
+            net.send(&outgoing);
+            if let Some(hash) = hash {
+                net.send(&hash);
+            }
+        }
+    }
+
+    /// 全屏显示本地玩家的那一局，对手显示为幽灵
+    pub(crate) fn render_online(&mut self, ctx: &mut BTerm) {
+        let (Some(net), Some(rival)) = (self.net.as_ref(), self.race.as_mut()) else {
+            return;
+        };
+        let player = net.player().unwrap_or(0);
+        let (local, opponent) = if player == 0 { (&mut self.game, rival) } else { (rival, &mut self.game) };
+
+        local.render_background(ctx);
+        let ghost = &opponent.dragons[0];
+        if !ghost.is_out() {
+            ghost.player.render_ghost(ctx);
+        }
+        local.render(ctx);
+
+        let status = if ghost.is_out() {
+            format!("Opponent OUT  |  Score: {}", ghost.score)
+        } else {
+            format!("Opponent Score: {}  |  Lives: {}", ghost.score, ghost.lives)
+        };
+        ctx.print_color(0, 1, (140, 140, 170), BLACK, status);
+        ctx.print(0, 0, format!(
+            "You are Player {} | Flap {} | Leave {}",
+            player + 1,
+            self.key_label(Action::Flap),
+            self.key_label(Action::Pause)
+        ));
+
+        if let Some(tick) = net.lockstep.as_ref().and_then(Lockstep::desync) {
+            ctx.print_color_centered(6, RED, BLACK, format!("Desync detected at tick {}", tick));
+        }
+        match &net.message {
+            Some(message) => ctx.print_color_centered(20, RED, BLACK, format!("{} - press {} to leave", message, self.key_label(Action::Pause))),
+            // 想推进却没能推进时说明在等对手的输入
+            None if self.tick_accumulator >= TICK_DURATION && !self.race_over() => {
+                ctx.print_centered(20, "Waiting for opponent...");
+            }
+            None => {}
+        }
+    }
+}
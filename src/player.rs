@@ -1,13 +1,21 @@
 use bracket_lib::prelude::*;
+use crate::render::Canvas;
 use crate::{SCREEN_WIDTH, difficulty::DifficultySettings};
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Player {
     pub x: i32,
     pub y: i32,
     velocity: f32,
     speed: i32,
+    // 动画和轨迹只在渲染时更新，不属于游戏状态，不参与存档和联机同步
+    #[serde(skip)]
     animation_frame: i32,
+    #[serde(skip)]
     trail_positions: Vec<(i32, i32)>, // 飞行轨迹
+    color: (u8, u8, u8),
+    fast_color: (u8, u8, u8),
 }
 
 impl Player {
@@ -19,10 +27,21 @@ impl Player {
             speed: 1,
             animation_frame: 0,
             trail_positions: Vec::new(),
+            color: YELLOW,
+            fast_color: ORANGE,
         }
     }
 
-    pub fn render(&mut self, ctx: &mut BTerm) {
+    /// 指定颜色的玩家，用于区分多名玩家
+    pub fn with_colors(x: i32, y: i32, color: (u8, u8, u8), fast_color: (u8, u8, u8)) -> Self {
+        Player {
+            color,
+            fast_color,
+            ..Player::new(x, y)
+        }
+    }
+
+    pub fn render(&mut self, ctx: &mut impl Canvas) {
         // 更新动画帧
         self.animation_frame = (self.animation_frame + 1) % 60;
         
@@ -40,9 +59,9 @@ impl Player {
         
         // 根据速度改变颜色
         let color = if self.velocity.abs() > 1.5 {
-            ORANGE // 快速移动时橙色
+            self.fast_color // 快速移动时橙色
         } else {
-            YELLOW // 正常时黄色
+            self.color // 正常时黄色
         };
         
         ctx.set(self.x, self.y, color, BLACK, to_cp437(player_char));
@@ -56,11 +75,16 @@ impl Player {
         self.update_trail();
     }
 
-    fn render_trail(&self, ctx: &mut BTerm) {
+    /// 联机对手的幽灵：只画一个暗淡的身影，不更新动画和轨迹
+    pub fn render_ghost(&self, ctx: &mut impl Canvas) {
+        ctx.set(self.x, self.y, (90, 90, 120), BLACK, to_cp437('>'));
+    }
+
+    fn render_trail(&self, ctx: &mut impl Canvas) {
         for (i, &(trail_x, trail_y)) in self.trail_positions.iter().enumerate() {
             let alpha = ((self.trail_positions.len() - i) as f32 / self.trail_positions.len() as f32 * 3.0) as u8;
             let trail_color = (alpha, alpha, 0); // 渐变的黄色轨迹
-            if trail_x >= 0 && trail_x < SCREEN_WIDTH && trail_y >= 0 && trail_y < 50 {
+            if (0..SCREEN_WIDTH).contains(&trail_x) && (0..50).contains(&trail_y) {
                 ctx.set(trail_x, trail_y, trail_color, BLACK, to_cp437('·'));
             }
         }
@@ -1,6 +1,8 @@
 use bracket_lib::prelude::*;
+use crate::render::Canvas;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PowerUpType {
     Shield,      // 护盾 - 免疫一次碰撞
     SlowMotion,  // 慢动作 - 减缓游戏速度
@@ -8,6 +10,16 @@ pub enum PowerUpType {
     ExtraLife,   // 额外生命 - 增加一条生命
 }
 
+impl PowerUpType {
+    pub const ALL: [PowerUpType; 4] = [
+        PowerUpType::Shield,
+        PowerUpType::SlowMotion,
+        PowerUpType::DoubleScore,
+        PowerUpType::ExtraLife,
+    ];
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct PowerUp {
     pub x: i32,
     pub y: i32,
@@ -25,12 +37,13 @@ impl PowerUp {
         }
     }
 
-    pub fn update(&mut self) {
-        self.x -= 1; // 向左移动
+    /// 和障碍物一起向左移动 `step` 格
+    pub fn update(&mut self, step: i32) {
+        self.x -= step;
         self.animation_timer += 16.67; // 假设60FPS，约16.67ms每帧
     }
 
-    pub fn render(&self, ctx: &mut BTerm) {
+    pub fn render(&self, ctx: &mut impl Canvas) {
         let (symbol, color) = self.get_visual_representation();
         
         // 添加闪烁效果
@@ -64,6 +77,7 @@ impl PowerUp {
 }
 
 /// 激活中的道具效果
+#[derive(Serialize, Deserialize)]
 pub struct ActivePowerUp {
     pub power_type: PowerUpType,
     pub timer: f32, // 剩余时间（毫秒）
@@ -0,0 +1,360 @@
+use crate::achievements::AchievementStore;
+use crate::difficulty::Difficulty;
+use crate::input::KeyBindings;
+use crate::level::CampaignStore;
+use crate::savegame::SaveStore;
+use crate::scores::ScoreStore;
+use crate::stats::StatsStore;
+use crate::storage::{data_dir, JsonStore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 玩家列表文件名
+const PROFILES_FILE: &str = "profiles.json";
+/// 各玩家数据所在的子目录
+const PROFILES_DIR: &str = "profiles";
+/// 每个玩家的设置文件名
+const SETTINGS_FILE: &str = "settings.json";
+/// 第一次运行时创建的默认玩家
+const DEFAULT_PROFILE_NAME: &str = "Player 1";
+/// 玩家名的最大长度
+pub const MAX_NAME_LEN: usize = 16;
+/// 改为按玩家保存之前直接放在数据目录下的文件
+const SHARED_FILES: [&str; 3] = ["scores.txt", "stats.json", "achievements.json"];
+/// 更早的版本写在当前工作目录下的分数文件
+const LEGACY_SCORES_FILE: &str = "scores.txt";
+
+/// 一个本地玩家
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    /// 所在的数据目录，不写入文件，由玩家列表在读取后设置
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+impl Profile {
+    /// 玩家的数据目录，保存分数、统计、成就、战役进度和设置
+    pub fn dir(&self) -> PathBuf {
+        self.root.join(PROFILES_DIR).join(&self.id)
+    }
+
+    pub fn scores(&self) -> ScoreStore {
+        ScoreStore::in_dir(&self.dir())
+    }
+
+    pub fn stats(&self) -> StatsStore {
+        StatsStore::in_dir(&self.dir())
+    }
+
+    pub fn achievements(&self) -> AchievementStore {
+        AchievementStore::in_dir(&self.dir())
+    }
+
+    pub fn saved_run(&self) -> SaveStore {
+        SaveStore::in_dir(&self.dir())
+    }
+
+    pub fn campaign(&self) -> CampaignStore {
+        CampaignStore::in_dir(&self.dir())
+    }
+
+    pub fn settings(&self) -> SettingsStore {
+        SettingsStore::new(self.dir().join(SETTINGS_FILE))
+    }
+}
+
+/// 每个玩家自己的设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    pub preferred_difficulty: Difficulty,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for ProfileSettings {
+    fn default() -> Self {
+        ProfileSettings {
+            preferred_difficulty: Difficulty::Normal,
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+pub type SettingsStore = JsonStore<ProfileSettings>;
+
+/// 所有玩家的列表和当前选中的玩家
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileList {
+    pub profiles: Vec<Profile>,
+    pub active: String,
+    next_id: u32,
+    /// 数据目录，各玩家的目录在它下面
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+impl ProfileList {
+    /// 数据目录 `root` 下只包含默认玩家的列表
+    pub fn with_default(root: &Path) -> Self {
+        let mut list = ProfileList::default();
+        list.set_root(root);
+        list.active = list.create(DEFAULT_PROFILE_NAME).expect("default profile name is valid");
+        list
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn set_root(&mut self, root: &Path) {
+        self.root = root.to_path_buf();
+        for profile in &mut self.profiles {
+            profile.root = root.to_path_buf();
+        }
+    }
+
+    /// 当前玩家；从 `ProfileStore::load_or_init` 读到的列表总是至少有一个玩家
+    pub fn active(&self) -> &Profile {
+        self.profiles
+            .iter()
+            .find(|p| p.id == self.active)
+            .or_else(|| self.profiles.first())
+            .expect("profile list is empty")
+    }
+
+    pub fn set_active(&mut self, id: &str) {
+        if self.profiles.iter().any(|p| p.id == id) {
+            self.active = id.to_string();
+        }
+    }
+
+    /// 新建玩家并返回其ID
+    ///
+    /// ID 只增不减，跳过仍在使用或者还留有数据目录的 ID，新玩家不会继承别人的数据。
+    pub fn create(&mut self, name: &str) -> Result<String, String> {
+        let name = self.validate_name(name, None)?;
+        // 旧版本的列表文件没有记录 next_id
+        let largest = self.profiles.iter().filter_map(|p| p.id.strip_prefix("profile-")?.parse().ok()).max();
+        self.next_id = self.next_id.max(largest.unwrap_or(0));
+        let id = loop {
+            self.next_id += 1;
+            let id = format!("profile-{}", self.next_id);
+            let profile = Profile {
+                id,
+                name: String::new(),
+                root: self.root.clone(),
+            };
+            if !self.profiles.iter().any(|p| p.id == profile.id) && !profile.dir().exists() {
+                break profile.id;
+            }
+        };
+        self.profiles.push(Profile {
+            id: id.clone(),
+            name,
+            root: self.root.clone(),
+        });
+        Ok(id)
+    }
+
+    pub fn rename(&mut self, id: &str, name: &str) -> Result<(), String> {
+        let name = self.validate_name(name, Some(id))?;
+        match self.profiles.iter_mut().find(|p| p.id == id) {
+            Some(profile) => {
+                profile.name = name;
+                Ok(())
+            }
+            None => Err("Profile not found".to_string()),
+        }
+    }
+
+    /// 删除玩家及其全部数据，至少保留一个玩家
+    pub fn delete(&mut self, id: &str) -> Result<(), String> {
+        if self.profiles.len() <= 1 {
+            return Err("Cannot delete the last profile".to_string());
+        }
+        let index = self
+            .profiles
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| "Profile not found".to_string())?;
+        let profile = self.profiles.remove(index);
+        if let Err(err) = fs::remove_dir_all(profile.dir()) {
+            if err.kind() != io::ErrorKind::NotFound {
+                eprintln!("Failed to remove {}: {}", profile.dir().display(), err);
+            }
+        }
+        if self.active == profile.id {
+            self.active = self.profiles[0].id.clone();
+        }
+        Ok(())
+    }
+
+    fn validate_name(&self, name: &str, exclude_id: Option<&str>) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Name cannot be empty".to_string());
+        }
+        if name.chars().count() > MAX_NAME_LEN {
+            return Err(format!("Name is longer than {} characters", MAX_NAME_LEN));
+        }
+        let taken = self
+            .profiles
+            .iter()
+            .any(|p| Some(p.id.as_str()) != exclude_id && p.name.eq_ignore_ascii_case(name));
+        if taken {
+            return Err("Name already in use".to_string());
+        }
+        Ok(name.to_string())
+    }
+}
+
+pub type ProfileStore = JsonStore<ProfileList>;
+
+impl ProfileStore {
+    pub fn open_default() -> Self {
+        Self::in_dir(&data_dir())
+    }
+
+    /// 数据目录 `root` 下的玩家列表，玩家的数据也都放在这个目录下
+    pub fn in_dir(root: &Path) -> Self {
+        Self::new(root.join(PROFILES_FILE))
+    }
+
+    /// 列表所在的数据目录
+    fn root(&self) -> &Path {
+        self.path().parent().unwrap_or(Path::new("."))
+    }
+
+    /// 在锁内读取、修改并写回玩家列表，修改时玩家的目录已经指向这个数据目录
+    pub fn edit<R>(&self, f: impl FnOnce(&mut ProfileList) -> R) -> io::Result<R> {
+        let root = self.root().to_path_buf();
+        self.update(|list| {
+            list.set_root(&root);
+            f(list)
+        })
+    }
+
+    /// 读取玩家列表；第一次运行时创建默认玩家，并把之前共用的数据迁移给它
+    ///
+    /// 列表为空（例如文件损坏）时同样重新创建默认玩家，保证总有一个当前玩家。
+    pub fn load_or_init(&self) -> io::Result<ProfileList> {
+        let (list, created) = self.edit(|list| {
+            let created = list.profiles.is_empty();
+            if created {
+                list.active = list.create(DEFAULT_PROFILE_NAME).expect("default profile name is valid");
+            }
+            if !list.profiles.iter().any(|p| p.id == list.active) {
+                list.active = list.profiles[0].id.clone();
+            }
+            (list.clone(), created)
+        })?;
+        if created {
+            migrate_shared_data(self.root(), list.active())?;
+        }
+        Ok(list)
+    }
+}
+
+/// 把旧版本所有人共用的数据移动到第一个玩家名下
+fn migrate_shared_data(root: &Path, profile: &Profile) -> io::Result<()> {
+    let dir = profile.dir();
+    fs::create_dir_all(&dir)?;
+    for file in SHARED_FILES {
+        let shared = root.join(file);
+        if shared.exists() {
+            fs::rename(&shared, dir.join(file))?;
+        }
+    }
+    let legacy = Path::new(LEGACY_SCORES_FILE);
+    let scores = dir.join(LEGACY_SCORES_FILE);
+    if !scores.exists() && legacy.exists() {
+        fs::copy(legacy, scores)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::temp_dir;
+
+    #[test]
+    fn empty_list_gets_a_default_profile() {
+        let root = temp_dir("profile-empty");
+        let store = ProfileStore::in_dir(&root);
+        fs::write(store.path(), r#"{"profiles": [], "active": "profile-7", "next_id": 7}"#).unwrap();
+
+        let list = store.load_or_init().unwrap();
+        assert_eq!(list.profiles.len(), 1);
+        assert_eq!(list.active().name, DEFAULT_PROFILE_NAME);
+        // 不会重新用回已经分配过的 ID
+        assert_eq!(list.active, "profile-8");
+        assert_eq!(list.active().dir(), root.join(PROFILES_DIR).join("profile-8"));
+        assert!(list.active().dir().exists());
+        assert_eq!(store.load_or_init().unwrap().active, "profile-8");
+    }
+
+    #[test]
+    fn unknown_active_profile_falls_back_to_first() {
+        let root = temp_dir("profile-stale");
+        let store = ProfileStore::in_dir(&root);
+        let mut list = ProfileList::with_default(&root);
+        list.create("Second").unwrap();
+        list.active = "missing".to_string();
+        store.update(|saved| *saved = list.clone()).unwrap();
+
+        assert_eq!(list.active().id, list.profiles[0].id);
+        let loaded = store.load_or_init().unwrap();
+        assert_eq!(loaded.active, list.profiles[0].id);
+        // 读回来的玩家指向同一个数据目录
+        assert_eq!(loaded.profiles, list.profiles);
+    }
+
+    #[test]
+    fn deleted_profile_ids_are_not_reused() {
+        let root = temp_dir("profile-delete");
+        let mut list = ProfileList::with_default(&root);
+        let second = list.create("Second").unwrap();
+        let third = list.create("Third").unwrap();
+        let dir = list.profiles[2].dir();
+        assert!(dir.starts_with(&root));
+        fs::create_dir_all(&dir).unwrap();
+
+        list.delete(&third).unwrap();
+        assert!(!dir.exists());
+        let fourth = list.create("Fourth").unwrap();
+        assert_ne!(fourth, third);
+        assert_ne!(fourth, second);
+        assert!(list.delete(&list.profiles[0].id.clone()).is_ok());
+        assert!(list.delete(&list.profiles[0].id.clone()).is_ok());
+        assert_eq!(list.delete(&fourth), Err("Cannot delete the last profile".to_string()));
+    }
+
+    #[test]
+    fn old_lists_without_next_id_skip_used_ids() {
+        let root = temp_dir("profile-old");
+        let store = ProfileStore::in_dir(&root);
+        fs::write(store.path(), r#"{"profiles": [{"id": "profile-2", "name": "Old"}], "active": "profile-2"}"#).unwrap();
+        // 留有数据目录的 ID 也跳过
+        fs::create_dir_all(root.join(PROFILES_DIR).join("profile-3")).unwrap();
+
+        let id = store.edit(|list| list.create("New")).unwrap().unwrap();
+        assert_eq!(id, "profile-4");
+        assert_eq!(store.load_or_init().unwrap().profiles.len(), 2);
+    }
+
+    #[test]
+    fn shared_data_moves_into_the_first_profile() {
+        let root = temp_dir("profile-migrate");
+        fs::write(root.join("stats.json"), "{}").unwrap();
+
+        let list = ProfileStore::in_dir(&root).load_or_init().unwrap();
+        assert!(!root.join("stats.json").exists());
+        assert_eq!(fs::read_to_string(list.active().dir().join("stats.json")).unwrap(), "{}");
+    }
+}
@@ -0,0 +1,171 @@
+use bracket_lib::prelude::*;
+use flappy::input::Action;
+use flappy::profile::{ProfileList, ProfileStore, MAX_NAME_LEN};
+use crate::{GameMode, State};
+
+/// 玩家管理界面的当前操作
+pub enum ProfileMenu {
+    Browse,
+    /// 输入名字；`rename` 为要改名的玩家ID，`None` 表示新建
+    Naming { rename: Option<String>, buffer: String },
+    ConfirmDelete(String),
+}
+
+impl State {
+    pub(crate) fn show_profiles(&mut self) {
+        let active = &self.profiles.active;
+        self.profile_cursor = self.profiles.profiles.iter().position(|p| &p.id == active).unwrap_or(0);
+        self.profile_menu = ProfileMenu::Browse;
+        self.profile_message = None;
+        self.mode = GameMode::Profiles;
+    }
+
+    pub(crate) fn display_profiles(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/menu_bg.png");
+        ctx.print_centered(5, "Player Profiles");
+
+        for (i, profile) in self.profiles.profiles.iter().enumerate() {
+            let y = 9 + i as i32 * 2;
+            let cursor = if i == self.profile_cursor { ">" } else { " " };
+            let active = if profile.id == self.profiles.active { "  (active)" } else { "" };
+            ctx.print(28, y, format!("{} {}{}", cursor, profile.name, active));
+        }
+
+        let bottom = 11 + self.profiles.profiles.len() as i32 * 2;
+        match &self.profile_menu {
+            ProfileMenu::Browse => {
+                ctx.print(20, bottom, format!("(Up/Down) Move  ({}) Select  (N) New", self.key_label(Action::Confirm)));
+                ctx.print(20, bottom + 2, "(R) Rename  (X) Delete  (M) Back to Menu");
+            }
+            ProfileMenu::Naming { buffer, .. } => {
+                ctx.print(20, bottom, format!("Name: {}_", buffer));
+                ctx.print(
+                    20,
+                    bottom + 2,
+                    format!("({}) Confirm  ({}) Cancel", self.key_label(Action::Confirm), self.key_label(Action::Back)),
+                );
+            }
+            ProfileMenu::ConfirmDelete(id) => {
+                let name = self.profiles.profiles.iter().find(|p| &p.id == id).map(|p| p.name.as_str());
+                ctx.print(20, bottom, format!("Delete {} and all of their data?", name.unwrap_or("?")));
+                ctx.print(20, bottom + 2, "(Y) Yes  (N) No");
+            }
+        }
+        if let Some(message) = &self.profile_message {
+            ctx.print_color(20, bottom + 4, RED, BLACK, message);
+        }
+
+        if let Some(key) = ctx.key {
+            self.profile_input(key, ctx.shift);
+        }
+    }
+
+    fn profile_input(&mut self, key: VirtualKeyCode, shift: bool) {
+        let selected_id = self.profiles.profiles.get(self.profile_cursor).map(|p| p.id.clone());
+        match &mut self.profile_menu {
+            ProfileMenu::Browse => match key {
+                key if self.key_bindings.matches(Action::Confirm, key) => {
+                    if let Some(id) = selected_id {
+                        self.edit_profiles(|list| {
+                            list.set_active(&id);
+                            Ok(())
+                        });
+                    }
+                }
+                key if self.key_bindings.matches(Action::Back, key) => self.mode = GameMode::Menu,
+                VirtualKeyCode::Up => self.profile_cursor = self.profile_cursor.saturating_sub(1),
+                VirtualKeyCode::Down => {
+                    self.profile_cursor = (self.profile_cursor + 1).min(self.profiles.profiles.len() - 1);
+                }
+                VirtualKeyCode::N => {
+                    self.profile_message = None;
+                    self.profile_menu = ProfileMenu::Naming { rename: None, buffer: String::new() };
+                }
+                VirtualKeyCode::R => {
+                    if let Some(id) = selected_id {
+                        self.profile_message = None;
+                        let buffer = self.profiles.profiles[self.profile_cursor].name.clone();
+                        self.profile_menu = ProfileMenu::Naming { rename: Some(id), buffer };
+                    }
+                }
+                VirtualKeyCode::X | VirtualKeyCode::Delete => {
+                    if let Some(id) = selected_id {
+                        self.profile_message = None;
+                        self.profile_menu = ProfileMenu::ConfirmDelete(id);
+                    }
+                }
+                VirtualKeyCode::M => self.mode = GameMode::Menu,
+                _ => {}
+            },
+            ProfileMenu::Naming { rename, buffer } => match key {
+                key if self.key_bindings.matches(Action::Confirm, key) => {
+                    let (rename, name) = (rename.clone(), buffer.clone());
+                    self.edit_profiles(|list| match &rename {
+                        Some(id) => list.rename(id, &name),
+                        None => list.create(&name).map(|_| ()),
+                    });
+                }
+                key if self.key_bindings.matches(Action::Back, key) => self.profile_menu = ProfileMenu::Browse,
+                VirtualKeyCode::Back => {
+                    buffer.pop();
+                }
+                _ => {
+                    if let Some(c) = key_to_char(key, shift) {
+                        if buffer.chars().count() < MAX_NAME_LEN {
+                            buffer.push(c);
+                        }
+                    }
+                }
+            },
+            ProfileMenu::ConfirmDelete(id) => match key {
+                VirtualKeyCode::Y => {
+                    let id = id.clone();
+                    self.edit_profiles(|list| list.delete(&id));
+                    self.profile_cursor = self.profile_cursor.min(self.profiles.profiles.len() - 1);
+                }
+                key if self.key_bindings.matches(Action::Back, key) => self.profile_menu = ProfileMenu::Browse,
+                VirtualKeyCode::N => self.profile_menu = ProfileMenu::Browse,
+                _ => {}
+            },
+        }
+    }
+
+    /// 在锁内修改玩家列表并保存；成功后切换到新的当前玩家
+    fn edit_profiles(&mut self, edit: impl FnOnce(&mut ProfileList) -> Result<(), String>) {
+        let result = ProfileStore::in_dir(self.profiles.root()).edit(|list| {
+            edit(list)?;
+            Ok(list.clone())
+        });
+        match result {
+            Ok(Ok(list)) => {
+                self.profiles = list;
+                self.profile_menu = ProfileMenu::Browse;
+                self.profile_message = None;
+                self.load_profile_data();
+            }
+            Ok(Err(message)) => self.profile_message = Some(message),
+            Err(err) => self.profile_message = Some(format!("Failed to save profiles: {}", err)),
+        }
+    }
+}
+
+/// 名字输入只接受字母、数字、空格和少量符号
+pub(crate) fn key_to_char(key: VirtualKeyCode, shift: bool) -> Option<char> {
+    use VirtualKeyCode::*;
+    let letters = [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z];
+    let digits = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    if let Some(i) = letters.iter().position(|&k| k == key) {
+        let c = (b'a' + i as u8) as char;
+        return Some(if shift { c.to_ascii_uppercase() } else { c });
+    }
+    if let Some(i) = digits.iter().position(|&k| k == key) {
+        return Some((b'0' + i as u8) as char);
+    }
+    match key {
+        Space => Some(' '),
+        Minus => Some(if shift { '_' } else { '-' }),
+        Period => Some('.'),
+        _ => None,
+    }
+}
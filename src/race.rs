@@ -0,0 +1,94 @@
+use bracket_lib::prelude::*;
+use flappy::difficulty::Difficulty;
+use flappy::game::{player_color, Game};
+use flappy::input::{Action, PLAYER_ACTIONS};
+use flappy::render::{blit_rows, viewport_first_row};
+use flappy::{SCREEN_HEIGHT, SCREEN_WIDTH, TICK_DURATION};
+use crate::{GameMode, State, MAX_TICKS_PER_FRAME};
+
+impl State {
+    /// 分屏竞速：两名玩家各玩一局种子相同的游戏，面对完全相同的障碍物和道具
+    pub(crate) fn start_race(&mut self) {
+        let seed = RandomNumberGenerator::new().next_u64();
+        self.start_race_with(self.selected_difficulty.clone(), seed);
+    }
+
+    /// 以指定的难度和种子开始竞速，联机对战时由中继服务决定
+    pub(crate) fn start_race_with(&mut self, difficulty: Difficulty, seed: u64) {
+        self.start_game(1);
+        self.game = Game::new(difficulty.clone(), seed);
+        self.race = Some(Game::new(difficulty, seed));
+        self.mode = GameMode::Race;
+    }
+
+    /// 任意一方失去所有生命时比赛结束
+    pub(crate) fn race_over(&self) -> bool {
+        self.game.is_over() || self.race.as_ref().is_some_and(Game::is_over)
+    }
+
+    pub(crate) fn race_play(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/game_bg.png");
+
+        self.handle_input();
+        if self.mode != GameMode::Race {
+            return;
+        }
+
+        self.tick_accumulator += ctx.frame_time_ms;
+        self.tick_accumulator = self.tick_accumulator.min(TICK_DURATION * MAX_TICKS_PER_FRAME as f32);
+        if self.net.is_some() {
+            self.online_ticks();
+            self.render_online(ctx);
+        } else {
+            self.local_race_ticks();
+            self.render_split_screen(ctx);
+        }
+
+        if self.race_over() {
+            self.mode = GameMode::Results;
+        }
+    }
+
+    fn local_race_ticks(&mut self) {
+        let Some(rival) = self.race.as_mut() else {
+            return;
+        };
+        while self.tick_accumulator >= TICK_DURATION && !self.game.is_over() && !rival.is_over() {
+            self.tick_accumulator -= TICK_DURATION;
+            // 竞速不计入录像、统计和成就
+            for (i, game) in [&mut self.game, &mut *rival].into_iter().enumerate() {
+                let input = self.hold_repeat.tick(&self.actions, i, std::mem::take(&mut self.pending_inputs[i]));
+                game.tick(input);
+                game.drain_events();
+            }
+        }
+    }
+
+    /// 每局先画到整屏大小的虚拟控制台，再按原尺寸把龙附近的几行画到上下两半，分界线下面是第二局
+    fn render_split_screen(&mut self, ctx: &mut BTerm) {
+        let Some(rival) = self.race.as_mut() else {
+            return;
+        };
+        let half = SCREEN_HEIGHT / 2;
+        for (i, game) in [&mut self.game, rival].into_iter().enumerate() {
+            let (top, rows) = if i == 0 { (0, half) } else { (half + 1, SCREEN_HEIGHT - half - 1) };
+            let mut view = VirtualConsole::new(Point::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+            game.render_world(&mut view);
+            let first_row = viewport_first_row(game.dragons[0].player.y, rows, SCREEN_HEIGHT);
+            blit_rows(&view, ctx, first_row, top, rows);
+            // 分数等信息固定在每一半的顶端
+            let mut hud = VirtualConsole::new(Point::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+            game.render_hud(&mut hud);
+            blit_rows(&hud, ctx, 0, top, rows);
+            let label = if game.is_over() { format!("P{} OUT", i + 1) } else { format!("P{}", i + 1) };
+            ctx.print_color(SCREEN_WIDTH - 10, top + 1, player_color(i), BLACK, label);
+        }
+        for x in 0..SCREEN_WIDTH {
+            ctx.set(x, half, GRAY, BLACK, to_cp437('─'));
+        }
+
+        let [p1, p2] = PLAYER_ACTIONS.map(|[flap, ..]| self.key_label(flap));
+        ctx.print(0, 0, format!("P1 Flap {} | P2 Flap {} | Pause {}", p1, p2, self.key_label(Action::Pause)));
+    }
+}
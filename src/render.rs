@@ -0,0 +1,113 @@
+use bracket_lib::prelude::*;
+
+/// 游戏画面的绘制目标：窗口本身，或者分屏时先画到内存里的虚拟控制台
+///
+/// 方法签名与 `BTerm` 的同名方法一致，渲染代码不需要关心画到哪里。
+pub trait Canvas {
+    fn set<F: Into<RGBA>, B: Into<RGBA>>(&mut self, x: i32, y: i32, fg: F, bg: B, glyph: FontCharType);
+    fn print<S: ToString>(&mut self, x: i32, y: i32, output: S);
+    fn print_color<S: ToString, F: Into<RGBA>, B: Into<RGBA>>(&mut self, x: i32, y: i32, fg: F, bg: B, output: S);
+    fn set_bg<B: Into<RGBA>>(&mut self, x: i32, y: i32, bg: B);
+}
+
+impl Canvas for BTerm {
+    fn set<F: Into<RGBA>, B: Into<RGBA>>(&mut self, x: i32, y: i32, fg: F, bg: B, glyph: FontCharType) {
+        BTerm::set(self, x, y, fg, bg, glyph);
+    }
+
+    fn print<S: ToString>(&mut self, x: i32, y: i32, output: S) {
+        BTerm::print(self, x, y, output);
+    }
+
+    fn print_color<S: ToString, F: Into<RGBA>, B: Into<RGBA>>(&mut self, x: i32, y: i32, fg: F, bg: B, output: S) {
+        BTerm::print_color(self, x, y, fg, bg, output);
+    }
+
+    fn set_bg<B: Into<RGBA>>(&mut self, x: i32, y: i32, bg: B) {
+        BTerm::set_bg(self, x, y, bg);
+    }
+}
+
+impl Canvas for VirtualConsole {
+    fn set<F: Into<RGBA>, B: Into<RGBA>>(&mut self, x: i32, y: i32, fg: F, bg: B, glyph: FontCharType) {
+        Console::set(self, x, y, fg.into(), bg.into(), glyph);
+    }
+
+    fn print<S: ToString>(&mut self, x: i32, y: i32, output: S) {
+        Console::print(self, x, y, &output.to_string());
+    }
+
+    fn print_color<S: ToString, F: Into<RGBA>, B: Into<RGBA>>(&mut self, x: i32, y: i32, fg: F, bg: B, output: S) {
+        Console::print_color(self, x, y, fg.into(), bg.into(), &output.to_string());
+    }
+
+    fn set_bg<B: Into<RGBA>>(&mut self, x: i32, y: i32, bg: B) {
+        Console::set_bg(self, x, y, bg.into());
+    }
+}
+
+/// 高 `rows` 行的视口跟随第 `focus_y` 行时，视口顶端在整屏中的行号，视口不会超出整屏
+pub fn viewport_first_row(focus_y: i32, rows: i32, height: i32) -> i32 {
+    (focus_y - rows / 2).clamp(0, (height - rows).max(0))
+}
+
+/// 把整屏大小的虚拟控制台从 `first_row` 开始的 `rows` 行原样画到窗口中从 `top` 开始的位置
+///
+/// 不缩放，窄的空隙和弹幕都和碰撞检测看到的一样；空白的格子保留窗口原来的背景。
+pub fn blit_rows(source: &VirtualConsole, ctx: &mut impl Canvas, first_row: i32, top: i32, rows: i32) {
+    let is_blank = |tile: &Tile| tile.glyph == 0 || tile.glyph == to_cp437(' ');
+    let last_row = (first_row + rows).min(source.height as i32);
+    for row in first_row.max(0)..last_row {
+        for x in 0..source.width as i32 {
+            let tile = &source.tiles[source.at(x, row)];
+            if !is_blank(tile) {
+                ctx.set(x, top + row - first_row, tile.fg, tile.bg, tile.glyph);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 空格和没画过的格子都算空白
+    fn glyph(console: &VirtualConsole, x: i32, y: i32) -> FontCharType {
+        match console.tiles[console.at(x, y)].glyph {
+            32 => 0,
+            glyph => glyph,
+        }
+    }
+
+    #[test]
+    fn viewport_follows_the_focus_inside_the_screen() {
+        assert_eq!(viewport_first_row(25, 24, 50), 13);
+        assert_eq!(viewport_first_row(3, 24, 50), 0);
+        assert_eq!(viewport_first_row(49, 24, 50), 26);
+        assert_eq!(viewport_first_row(10, 60, 50), 0);
+    }
+
+    #[test]
+    fn blitting_keeps_every_row() {
+        // 只有一行的空隙和紧挨着它的弹幕都要留在原来的行上
+        let mut source = VirtualConsole::new(Point::new(4, 50));
+        for y in 0..50 {
+            Canvas::set(&mut source, 1, y, WHITE, BLACK, to_cp437('#'));
+        }
+        Canvas::set(&mut source, 1, 31, WHITE, BLACK, to_cp437(' '));
+        Canvas::set(&mut source, 2, 32, RED, BLACK, to_cp437('*'));
+
+        let mut window = VirtualConsole::new(Point::new(4, 50));
+        let first_row = viewport_first_row(31, 20, 50);
+        blit_rows(&source, &mut window, first_row, 26, 20);
+        for row in 0..20 {
+            for x in 0..4 {
+                assert_eq!(glyph(&window, x, 26 + row), glyph(&source, x, first_row + row), "({}, {})", x, row);
+            }
+        }
+        assert_eq!(glyph(&window, 1, 26 + 31 - first_row), 0);
+        assert_eq!(glyph(&window, 2, 26 + 32 - first_row), to_cp437('*'));
+        // 视口以外的行不画
+        assert!((0..26).all(|y| glyph(&window, 1, y) != to_cp437('#')));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::difficulty::Difficulty;
+use crate::game::{Game, TickInput};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 可以验证的最长录像（一小时），防止伪造的超长录像拖垮服务端
+pub const MAX_REPLAY_TICKS: usize = 60 * 60 * 60;
+
+/// 一局游戏的输入录像：种子 + 难度 + 每个逻辑帧的输入
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "EncodedReplay", from = "EncodedReplay")]
+pub struct Replay {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub inputs: Vec<u8>,
+}
+
+/// 录像验证失败的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayError {
+    TooLong(usize),
+    /// 在录像结束前游戏就已经结束
+    EndedEarly { tick: usize },
+    /// 录像播放完游戏仍未结束
+    NotFinished,
+    ScoreMismatch { claimed: i32, actual: i32 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::TooLong(ticks) => write!(f, "replay too long ({} ticks)", ticks),
+            ReplayError::EndedEarly { tick } => write!(f, "game ended at tick {} before the replay did", tick),
+            ReplayError::NotFinished => write!(f, "game is still running at the end of the replay"),
+            ReplayError::ScoreMismatch { claimed, actual } => {
+                write!(f, "claimed score {} but replay scores {}", claimed, actual)
+            }
+        }
+    }
+}
+
+impl Replay {
+    pub fn new(seed: u64, difficulty: Difficulty) -> Self {
+        Replay {
+            seed,
+            difficulty,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// 记录一个逻辑帧的输入
+    pub fn record(&mut self, input: TickInput) {
+        self.inputs.push(input.to_bits());
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// 录像的指纹（FNV-1a 64位），用于在排行榜上标识一局游戏
+    pub fn hash(&self) -> String {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let difficulty = format!("{:?}", self.difficulty);
+        let bytes = self
+            .seed
+            .to_le_bytes()
+            .into_iter()
+            .chain(difficulty.bytes())
+            .chain(self.inputs.iter().copied());
+        let hash = bytes.fold(OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME));
+        format!("{:016x}", hash)
+    }
+
+    /// 用无界面的游戏核心重新播放录像
+    pub fn simulate(&self) -> Game {
+        let mut game = Game::new(self.difficulty.clone(), self.seed);
+        for &bits in &self.inputs {
+            game.tick(TickInput::from_bits(bits));
+        }
+        game
+    }
+
+    /// 重新模拟整局游戏，确认录像恰好在最后一帧结束且得分与声明一致
+    pub fn verify(&self, claimed_score: i32) -> Result<(), ReplayError> {
+        if self.inputs.len() > MAX_REPLAY_TICKS {
+            return Err(ReplayError::TooLong(self.inputs.len()));
+        }
+        let mut game = Game::new(self.difficulty.clone(), self.seed);
+        for (tick, &bits) in self.inputs.iter().enumerate() {
+            if game.is_over() {
+                return Err(ReplayError::EndedEarly { tick });
+            }
+            game.tick(TickInput::from_bits(bits));
+        }
+        if !game.is_over() {
+            return Err(ReplayError::NotFinished);
+        }
+        let score = game.dragons[0].score;
+        if score != claimed_score {
+            return Err(ReplayError::ScoreMismatch {
+                claimed: claimed_score,
+                actual: score,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// 录像的序列化格式：输入按 `[输入, 连续帧数]` 做游程编码
+#[derive(Serialize, Deserialize)]
+struct EncodedReplay {
+    seed: u64,
+    difficulty: Difficulty,
+    inputs: Vec<(u8, u32)>,
+}
+
+impl From<Replay> for EncodedReplay {
+    fn from(replay: Replay) -> Self {
+        let mut runs: Vec<(u8, u32)> = Vec::new();
+        for bits in replay.inputs {
+            match runs.last_mut() {
+                Some((last, count)) if *last == bits => *count += 1,
+                _ => runs.push((bits, 1)),
+            }
+        }
+        EncodedReplay {
+            seed: replay.seed,
+            difficulty: replay.difficulty,
+            inputs: runs,
+        }
+    }
+}
+
+impl From<EncodedReplay> for Replay {
+    fn from(encoded: EncodedReplay) -> Self {
+        // 展开时限制总长度，超长的录像在验证时会被拒绝
+        let mut inputs = Vec::new();
+        for (bits, count) in encoded.inputs {
+            let remaining = (MAX_REPLAY_TICKS + 1).saturating_sub(inputs.len());
+            let count = (count as usize).min(remaining);
+            inputs.extend(std::iter::repeat_n(bits, count));
+        }
+        Replay {
+            seed: encoded.seed,
+            difficulty: encoded.difficulty,
+            inputs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+
+    /// 机器人玩一段时间后松手，录下直到游戏结束的整局输入
+    fn recorded_run(seed: u64) -> (Replay, i32) {
+        let mut replay = Replay::new(seed, Difficulty::Normal);
+        let mut game = Game::new(Difficulty::Normal, seed);
+        let bot = Bot::new(0);
+        while !game.is_over() {
+            let input = if replay.ticks() < 1500 { bot.input(&game) } else { TickInput::default() };
+            replay.record(input);
+            game.tick(input);
+        }
+        (replay, game.dragons[0].score)
+    }
+
+    #[test]
+    fn recorded_run_verifies_with_its_score() {
+        let (replay, score) = recorded_run(7);
+        assert!(score > 0);
+        assert_eq!(replay.verify(score), Ok(()));
+        assert_eq!(replay.simulate().dragons[0].score, score);
+    }
+
+    #[test]
+    fn tampered_runs_are_rejected() {
+        let (replay, score) = recorded_run(7);
+        assert_eq!(
+            replay.verify(score + 1),
+            Err(ReplayError::ScoreMismatch {
+                claimed: score + 1,
+                actual: score
+            })
+        );
+
+        // 游戏结束后还有多余的输入
+        let mut padded = replay.clone();
+        padded.inputs.push(0);
+        assert_eq!(padded.verify(score), Err(ReplayError::EndedEarly { tick: replay.ticks() }));
+
+        // 截掉最后几帧，游戏还没结束
+        let mut truncated = replay.clone();
+        truncated.inputs.truncate(replay.ticks() - 5);
+        assert_eq!(truncated.verify(score), Err(ReplayError::NotFinished));
+
+        let mut too_long = replay.clone();
+        too_long.inputs.resize(MAX_REPLAY_TICKS + 1, 0);
+        assert_eq!(too_long.verify(score), Err(ReplayError::TooLong(MAX_REPLAY_TICKS + 1)));
+    }
+
+    #[test]
+    fn serialization_keeps_inputs_and_caps_length() {
+        let (replay, score) = recorded_run(11);
+        let json = serde_json::to_string(&replay).unwrap();
+        let decoded: Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, replay);
+        assert_eq!(decoded.hash(), replay.hash());
+        assert_eq!(decoded.verify(score), Ok(()));
+
+        // 声称极长的游程在展开时就被截断，验证时报告超长
+        let json = format!(r#"{{"seed":1,"difficulty":"Normal","inputs":[[0,{}]]}}"#, u32::MAX);
+        let decoded: Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.verify(0), Err(ReplayError::TooLong(MAX_REPLAY_TICKS + 1)));
+    }
+}
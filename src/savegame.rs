@@ -0,0 +1,116 @@
+use crate::achievements::AchievementTracker;
+use crate::game::Game;
+use crate::replay::Replay;
+use crate::stats::RunStats;
+use crate::storage::JsonStore;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+/// 保存的对局文件名，和玩家的其他数据放在同一目录
+const SAVE_FILE: &str = "saved_run.json";
+
+/// 暂停时保存的完整对局，继续时原样恢复
+#[derive(Serialize, Deserialize)]
+pub struct SavedRun {
+    pub game: Game,
+    /// 到目前为止的录像，继续后接着录制，提交排行榜时仍能完整验证
+    pub replay: Replay,
+    pub run_stats: RunStats,
+    pub achievements: AchievementTracker,
+}
+
+/// 每个玩家最多保存一局
+pub type SaveStore = JsonStore<Option<SavedRun>>;
+
+impl SaveStore {
+    /// 打开 `dir` 目录下的文件
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(SAVE_FILE))
+    }
+
+    /// 保存对局，覆盖之前的存档
+    pub fn save(&self, run: SavedRun) -> io::Result<()> {
+        self.update(|saved| *saved = Some(run))
+    }
+
+    /// 取出存档并立即删除，同一份存档只能继续一次
+    pub fn take(&self) -> io::Result<Option<SavedRun>> {
+        self.update(Option::take)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::achievements::AchievementProgress;
+    use crate::bot::Bot;
+    use crate::difficulty::Difficulty;
+    use crate::storage::temp_dir;
+    use std::fs;
+
+    /// 机器人一直玩到首领战中途，首领、弹幕和喷火的能量都在存档里
+    fn mid_run() -> SavedRun {
+        let mut game = Game::new(Difficulty::Normal, 21);
+        let mut replay = Replay::new(21, Difficulty::Normal);
+        let bot = Bot::new(0);
+        let mut boss_ticks = 0;
+        while boss_ticks < 100 {
+            assert!(!game.is_over(), "bot never reached the boss");
+            let input = bot.input(&game);
+            replay.record(input);
+            game.tick(input);
+            if game.boss.is_some() {
+                boss_ticks += 1;
+            }
+        }
+        SavedRun {
+            game,
+            replay,
+            run_stats: RunStats::default(),
+            achievements: AchievementTracker::new(&AchievementProgress::default()),
+        }
+    }
+
+    fn snapshot(game: &Game) -> String {
+        serde_json::to_string(game).unwrap()
+    }
+
+    #[test]
+    fn resumed_run_plays_out_identically() {
+        let store = SaveStore::in_dir(&temp_dir("savegame-resume"));
+        let mut original = mid_run().game;
+        store.save(mid_run()).unwrap();
+
+        let saved = fs::read_to_string(store.path()).unwrap();
+        for field in ["random", "boss", "fireballs", "hazards", "biome", "energy", "next_boss_score"] {
+            assert!(saved.contains(&format!("\"{}\"", field)), "missing {}", field);
+        }
+
+        let resumed = store.take().unwrap().expect("saved run");
+        let mut game = resumed.game;
+        assert_eq!(snapshot(&game), snapshot(&original));
+        assert_eq!(resumed.replay.ticks() as u64, game.ticks);
+
+        // 之后的每个逻辑帧都完全一致，包括随机数生成器的状态
+        let bot = Bot::new(0);
+        for _ in 0..600 {
+            let input = bot.input(&original);
+            assert_eq!(bot.input(&game), input);
+            original.tick(input);
+            game.tick(input);
+            assert_eq!(snapshot(&game), snapshot(&original), "diverged at tick {}", game.ticks);
+        }
+    }
+
+    #[test]
+    fn take_consumes_the_save() {
+        let store = SaveStore::in_dir(&temp_dir("savegame-take"));
+        assert!(store.take().unwrap().is_none());
+
+        store.save(mid_run()).unwrap();
+        assert!(store.take().unwrap().is_some());
+        assert!(store.take().unwrap().is_none());
+        assert!(store.load().unwrap().is_none());
+    }
+}
@@ -0,0 +1,393 @@
+use crate::difficulty::Difficulty;
+use crate::storage::{lock_file, unix_now, write_atomic};
+use bracket_lib::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 分数文件名
+const SCORES_FILE: &str = "scores.txt";
+/// CSV 导出的表头
+const CSV_HEADER: &str = "run_id,score,difficulty,timestamp";
+/// 对局ID的最大长度
+const MAX_RUN_ID_LEN: usize = 64;
+
+/// 一局游戏的分数记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    /// 唯一的对局ID，合并不同机器的记录时用于去重
+    pub run_id: String,
+    pub score: i32,
+    pub difficulty: Difficulty,
+    /// 结束时间（Unix 秒）
+    pub timestamp: u64,
+}
+
+impl ScoreEntry {
+    pub fn new(score: i32, difficulty: Difficulty) -> Self {
+        ScoreEntry {
+            run_id: new_run_id(),
+            score,
+            difficulty,
+            timestamp: unix_now(),
+        }
+    }
+}
+
+/// 导出/导入的文件格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// 根据文件扩展名判断格式，默认 JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+}
+
+/// 高分记录的持久化存储
+///
+/// 每次写入都会先写临时文件再重命名，并用锁文件防止两个游戏实例同时写入。
+pub struct ScoreStore {
+    path: PathBuf,
+}
+
+impl ScoreStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ScoreStore { path: path.into() }
+    }
+
+    /// 打开 `dir` 目录下的分数文件
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(SCORES_FILE))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 追加一条分数记录
+    pub fn append(&self, entry: ScoreEntry) -> io::Result<()> {
+        let _lock = self.lock()?;
+        let mut entries = self.read_entries()?;
+        entries.push(entry);
+        self.write_entries(&entries)
+    }
+
+    /// 读取所有分数记录（未排序）
+    pub fn load(&self) -> io::Result<Vec<ScoreEntry>> {
+        let _lock = self.lock()?;
+        self.read_entries()
+    }
+
+    /// 合并外部记录，按对局ID去重，返回新增的条数；有对局ID不合法时整批拒绝
+    pub fn merge(&self, incoming: Vec<ScoreEntry>) -> io::Result<usize> {
+        if let Some(entry) = incoming.iter().find(|entry| !is_valid_run_id(&entry.run_id)) {
+            return Err(invalid_run_id(&entry.run_id));
+        }
+        let _lock = self.lock()?;
+        let mut entries = self.read_entries()?;
+        let mut known: HashSet<String> = entries.iter().map(|e| e.run_id.clone()).collect();
+        let before = entries.len();
+        for entry in incoming {
+            if known.insert(entry.run_id.clone()) {
+                entries.push(entry);
+            }
+        }
+        let added = entries.len() - before;
+        if added > 0 {
+            self.write_entries(&entries)?;
+        }
+        Ok(added)
+    }
+
+    /// 导出全部记录到文件
+    pub fn export(&self, path: &Path, format: ExportFormat) -> io::Result<usize> {
+        let entries = self.load()?;
+        let content = match format {
+            ExportFormat::Json => to_json(&entries)?,
+            ExportFormat::Csv => to_csv(&entries),
+        };
+        fs::write(path, content)?;
+        Ok(entries.len())
+    }
+
+    /// 从导出文件中导入并合并，返回新增的条数
+    pub fn import(&self, path: &Path, format: ExportFormat) -> io::Result<usize> {
+        let content = fs::read_to_string(path)?;
+        let incoming = match format {
+            ExportFormat::Json => from_json(&content)?,
+            ExportFormat::Csv => from_csv(&content)?,
+        };
+        self.merge(incoming)
+    }
+
+    /// 读取文件；发现损坏的行时先备份原文件，再写回能解析的部分
+    fn read_entries(&self) -> io::Result<Vec<ScoreEntry>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            // 非 UTF-8 内容也视为损坏
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                self.backup()?;
+                self.write_entries(&[])?;
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut entries = Vec::new();
+        let mut corrupted = false;
+        let mut missing_ids = false;
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            match parse_line(line) {
+                Some(entry) => {
+                    missing_ids |= line.split(':').count() < 4;
+                    entries.push(entry);
+                }
+                None => corrupted = true,
+            }
+        }
+
+        if corrupted {
+            eprintln!(
+                "Corrupted entries found in {}, backup saved to {}",
+                self.path.display(),
+                self.backup_path().display()
+            );
+            self.backup()?;
+        }
+        // 旧格式的记录在这里分配好对局ID并写回，保证ID稳定
+        if corrupted || missing_ids {
+            self.write_entries(&entries)?;
+        }
+        Ok(entries)
+    }
+
+    fn write_entries(&self, entries: &[ScoreEntry]) -> io::Result<()> {
+        let mut content = String::new();
+        for entry in entries {
+            content.push_str(&format!(
+                "{}:{:?}:{}:{}\n",
+                entry.score, entry.difficulty, entry.run_id, entry.timestamp
+            ));
+        }
+        write_atomic(&self.path, content.as_bytes())
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        self.path.with_extension("txt.bak")
+    }
+
+    fn backup(&self) -> io::Result<()> {
+        fs::copy(&self.path, self.backup_path()).map(|_| ())
+    }
+
+    fn lock(&self) -> io::Result<File> {
+        lock_file(&self.path)
+    }
+}
+
+/// 解析一行 `分数:难度:对局ID:时间`，兼容 `分数:难度` 和只有分数的旧格式
+fn parse_line(line: &str) -> Option<ScoreEntry> {
+    let parts: Vec<&str> = line.trim().split(':').collect();
+    match parts.as_slice() {
+        [score, difficulty, run_id, timestamp] if !run_id.is_empty() => Some(ScoreEntry {
+            run_id: run_id.to_string(),
+            score: score.parse().ok()?,
+            difficulty: difficulty.parse().ok()?,
+            timestamp: timestamp.parse().ok()?,
+        }),
+        [score, difficulty] => Some(ScoreEntry::new(score.parse().ok()?, difficulty.parse().ok()?)),
+        [score] => Some(ScoreEntry::new(score.parse().ok()?, Difficulty::Normal)),
+        _ => None,
+    }
+}
+
+fn to_json(entries: &[ScoreEntry]) -> io::Result<String> {
+    serde_json::to_string_pretty(entries).map_err(io::Error::other)
+}
+
+fn from_json(content: &str) -> io::Result<Vec<ScoreEntry>> {
+    let entries: Vec<ScoreEntry> =
+        serde_json::from_str(content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    match entries.iter().find(|entry| !is_valid_run_id(&entry.run_id)) {
+        Some(entry) => Err(invalid_run_id(&entry.run_id)),
+        None => Ok(entries),
+    }
+}
+
+fn to_csv(entries: &[ScoreEntry]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for entry in entries {
+        out.push_str(&format!(
+            "{},{},{:?},{}\n",
+            entry.run_id, entry.score, entry.difficulty, entry.timestamp
+        ));
+    }
+    out
+}
+
+fn from_csv(content: &str) -> io::Result<Vec<ScoreEntry>> {
+    let invalid = |line_no: usize| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("invalid CSV record on line {}", line_no))
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && line.trim() != CSV_HEADER)
+        .map(|(i, line)| {
+            let fields: Vec<&str> = line.trim().split(',').map(str::trim).collect();
+            match fields.as_slice() {
+                [run_id, score, difficulty, timestamp] if is_valid_run_id(run_id) => Ok(ScoreEntry {
+                    run_id: run_id.to_string(),
+                    score: score.parse().map_err(|_| invalid(i + 1))?,
+                    difficulty: difficulty.parse().map_err(|_| invalid(i + 1))?,
+                    timestamp: timestamp.parse().map_err(|_| invalid(i + 1))?,
+                }),
+                _ => Err(invalid(i + 1)),
+            }
+        })
+        .collect()
+}
+
+/// 对局ID只能由字母、数字和 `-` 组成，否则写进以 `:` 分隔的分数文件后无法再读出来；排行榜服务用同样的规则
+pub fn is_valid_run_id(run_id: &str) -> bool {
+    !run_id.is_empty()
+        && run_id.len() <= MAX_RUN_ID_LEN
+        && run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn invalid_run_id(run_id: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid run id {:?}", run_id))
+}
+
+/// 生成对局ID：时间戳 + 随机数，足以区分不同机器上的对局
+fn new_run_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    let mut random = RandomNumberGenerator::new();
+    format!("{:x}-{:08x}", nanos, random.next_u64() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::temp_dir;
+
+    fn entry(run_id: &str, score: i32) -> ScoreEntry {
+        ScoreEntry {
+            run_id: run_id.to_string(),
+            score,
+            difficulty: Difficulty::Hard,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn merge_skips_known_run_ids() {
+        let store = ScoreStore::in_dir(&temp_dir("scores-merge"));
+        store.append(entry("a-1", 10)).unwrap();
+
+        let added = store.merge(vec![entry("a-1", 99), entry("b-2", 20), entry("b-2", 30)]).unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(store.load().unwrap(), vec![entry("a-1", 10), entry("b-2", 20)]);
+        assert_eq!(store.merge(vec![entry("b-2", 20)]).unwrap(), 0);
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let dir = temp_dir("scores-export");
+        let source = ScoreStore::new(dir.join("source.txt"));
+        let entries = vec![entry("a-1", 10), entry("b-2", 20), ScoreEntry::new(5, Difficulty::Easy)];
+        source.merge(entries.clone()).unwrap();
+
+        for (format, file) in [(ExportFormat::Json, "scores.json"), (ExportFormat::Csv, "scores.csv")] {
+            let path = dir.join(file);
+            assert_eq!(ExportFormat::from_path(&path), format);
+            assert_eq!(source.export(&path, format).unwrap(), 3);
+
+            let target = ScoreStore::new(dir.join(format!("{}.txt", file)));
+            assert_eq!(target.import(&path, format).unwrap(), 3);
+            assert_eq!(target.load().unwrap(), entries);
+            // 再导入一次不会重复
+            assert_eq!(target.import(&path, format).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_run_ids() {
+        let dir = temp_dir("scores-invalid");
+        let store = ScoreStore::in_dir(&dir);
+        store.append(entry("a-1", 10)).unwrap();
+
+        let json = dir.join("bad.json");
+        for run_id in ["", "a:b", "a\nb", "a,b", "ünï"] {
+            fs::write(&json, to_json(&[entry(run_id, 1)]).unwrap()).unwrap();
+            let err = store.import(&json, ExportFormat::Json).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", run_id);
+        }
+        let csv = dir.join("bad.csv");
+        fs::write(&csv, format!("{}\nx:y,1,Hard,0\n", CSV_HEADER)).unwrap();
+        assert_eq!(store.import(&csv, ExportFormat::Csv).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(store.merge(vec![entry("ok", 1), entry("bad:id", 2)]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // 存储本身没有被改动，也没有产生备份
+        assert_eq!(store.load().unwrap(), vec![entry("a-1", 10)]);
+        assert!(!store.backup_path().exists());
+    }
+
+    #[test]
+    fn corrupted_file_is_backed_up_and_repaired() {
+        let store = ScoreStore::in_dir(&temp_dir("scores-corrupt"));
+        let original = "10:Hard:a-1:1700000000\nnot a score\n20:Hard:b-2:1700000000\n";
+        fs::write(store.path(), original).unwrap();
+
+        assert_eq!(store.load().unwrap(), vec![entry("a-1", 10), entry("b-2", 20)]);
+        // 原文件完整保存在 .bak 里，主文件只保留能解析的行
+        assert_eq!(fs::read_to_string(store.backup_path()).unwrap(), original);
+        assert_eq!(
+            fs::read_to_string(store.path()).unwrap(),
+            "10:Hard:a-1:1700000000\n20:Hard:b-2:1700000000\n"
+        );
+
+        // 非 UTF-8 的文件同样备份，然后从空记录开始
+        fs::write(store.path(), [0xff, 0xfe, 0x00]).unwrap();
+        assert!(store.load().unwrap().is_empty());
+        assert_eq!(fs::read(store.backup_path()).unwrap(), vec![0xff, 0xfe, 0x00]);
+        store.append(entry("c-3", 30)).unwrap();
+        assert_eq!(store.load().unwrap(), vec![entry("c-3", 30)]);
+    }
+
+    #[test]
+    fn legacy_lines_get_stable_run_ids() {
+        let store = ScoreStore::in_dir(&temp_dir("scores-legacy"));
+        fs::write(store.path(), "12:Easy\n7\n").unwrap();
+
+        let first = store.load().unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!((first[0].score, &first[0].difficulty), (12, &Difficulty::Easy));
+        assert_eq!((first[1].score, &first[1].difficulty), (7, &Difficulty::Normal));
+        assert!(first.iter().all(|entry| is_valid_run_id(&entry.run_id)));
+        assert_ne!(first[0].run_id, first[1].run_id);
+
+        // 升级后写回四段格式，再次读取得到同样的ID
+        let content = fs::read_to_string(store.path()).unwrap();
+        assert!(content.lines().all(|line| line.split(':').count() == 4), "{}", content);
+        assert_eq!(store.load().unwrap(), first);
+        // 旧格式不算损坏，不产生备份
+        assert!(!store.backup_path().exists());
+    }
+}
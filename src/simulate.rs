@@ -0,0 +1,344 @@
+use crate::bot::Bot;
+use crate::difficulty::Difficulty;
+use crate::game::{DeathCause, Game, GameEvent};
+use crate::powerup::PowerUpType;
+use crate::scores::ExportFormat;
+use crate::TICK_DURATION;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// 批量模拟的参数
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    pub difficulties: Vec<Difficulty>,
+    /// 每个难度模拟的局数
+    pub games: usize,
+    pub threads: usize,
+    /// 每局最多推进的逻辑帧数，机器人一直不死时强制结束
+    pub max_ticks: u64,
+    /// 第 i 局使用种子 `seed + i`，相同的参数总会得到相同的报告
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            difficulties: Difficulty::ALL.to_vec(),
+            games: 1000,
+            threads: thread::available_parallelism().map(usize::from).unwrap_or(1),
+            max_ticks: 60 * 60 * 10,
+            seed: 0,
+        }
+    }
+}
+
+/// 机器人玩一局的结果
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub score: i32,
+    pub ticks: u64,
+    /// 是否在 `max_ticks` 之前结束
+    pub finished: bool,
+    pub deaths: Vec<DeathCause>,
+    pub powerups: Vec<PowerUpType>,
+    pub shields_consumed: usize,
+}
+
+/// 让机器人不开窗口地玩一局
+pub fn run_bot_game(difficulty: Difficulty, seed: u64, max_ticks: u64) -> RunSummary {
+    let mut game = Game::new(difficulty, seed);
+    let bot = Bot::new(0);
+    let mut summary = RunSummary {
+        score: 0,
+        ticks: 0,
+        finished: false,
+        deaths: Vec::new(),
+        powerups: Vec::new(),
+        shields_consumed: 0,
+    };
+    while !game.is_over() && game.ticks < max_ticks {
+        game.tick(bot.input(&game));
+        for (_, event) in game.drain_events() {
+            match event {
+                GameEvent::LifeLost(cause) => summary.deaths.push(cause),
+                GameEvent::PowerUpCollected(power_type) => summary.powerups.push(power_type),
+                GameEvent::ShieldConsumed => summary.shields_consumed += 1,
+                _ => {}
+            }
+        }
+    }
+    summary.score = game.dragons[0].score;
+    summary.ticks = game.ticks;
+    summary.finished = game.is_over();
+    summary
+}
+
+/// 分数分布
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScoreDistribution {
+    pub mean: f32,
+    pub min: i32,
+    pub p25: i32,
+    pub median: i32,
+    pub p75: i32,
+    pub p90: i32,
+    pub max: i32,
+}
+
+impl ScoreDistribution {
+    fn from_scores(mut scores: Vec<i32>) -> Self {
+        if scores.is_empty() {
+            return ScoreDistribution::default();
+        }
+        scores.sort_unstable();
+        let percentile = |p: f32| scores[((scores.len() - 1) as f32 * p).round() as usize];
+        ScoreDistribution {
+            mean: scores.iter().sum::<i32>() as f32 / scores.len() as f32,
+            min: scores[0],
+            p25: percentile(0.25),
+            median: percentile(0.5),
+            p75: percentile(0.75),
+            p90: percentile(0.9),
+            max: scores[scores.len() - 1],
+        }
+    }
+}
+
+/// 某种道具对分数的影响：拾取过和没拾取过这种道具的对局的平均分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PowerUpImpact {
+    pub power_type: PowerUpType,
+    /// 所有对局中一共拾取的次数
+    pub collected: usize,
+    /// 至少拾取过一次的局数
+    pub games_with: usize,
+    pub avg_score_with: f32,
+    pub avg_score_without: f32,
+}
+
+/// 一个难度的模拟报告
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DifficultyReport {
+    pub difficulty: Difficulty,
+    pub games: usize,
+    /// 达到 `max_ticks` 仍未结束的局数
+    pub unfinished: usize,
+    pub score: ScoreDistribution,
+    pub avg_survival_secs: f32,
+    /// 按原因统计的失去生命次数
+    pub deaths: BTreeMap<String, usize>,
+    pub shields_consumed: usize,
+    pub powerups: Vec<PowerUpImpact>,
+}
+
+impl DifficultyReport {
+    pub fn from_runs(difficulty: Difficulty, runs: &[RunSummary]) -> Self {
+        let average = |scores: Vec<i32>| {
+            if scores.is_empty() {
+                0.0
+            } else {
+                scores.iter().sum::<i32>() as f32 / scores.len() as f32
+            }
+        };
+        let deaths = DeathCause::all()
+            .into_iter()
+            .map(|cause| {
+                let count = runs.iter().flat_map(|run| &run.deaths).filter(|&death| *death == cause).count();
+                (cause.name(), count)
+            })
+            .collect();
+        let powerups = PowerUpType::ALL
+            .iter()
+            .map(|power_type| {
+                let (with, without): (Vec<&RunSummary>, Vec<&RunSummary>) =
+                    runs.iter().partition(|run| run.powerups.contains(power_type));
+                PowerUpImpact {
+                    power_type: power_type.clone(),
+                    collected: runs.iter().flat_map(|run| &run.powerups).filter(|&p| p == power_type).count(),
+                    games_with: with.len(),
+                    avg_score_with: average(with.iter().map(|run| run.score).collect()),
+                    avg_score_without: average(without.iter().map(|run| run.score).collect()),
+                }
+            })
+            .collect();
+        let total_ticks: u64 = runs.iter().map(|run| run.ticks).sum();
+
+        DifficultyReport {
+            difficulty,
+            games: runs.len(),
+            unfinished: runs.iter().filter(|run| !run.finished).count(),
+            score: ScoreDistribution::from_scores(runs.iter().map(|run| run.score).collect()),
+            avg_survival_secs: total_ticks as f32 * TICK_DURATION / 1000.0 / runs.len().max(1) as f32,
+            deaths,
+            shields_consumed: runs.iter().map(|run| run.shields_consumed).sum(),
+            powerups,
+        }
+    }
+}
+
+/// 在多个线程上模拟所有难度，按 `config.difficulties` 的顺序返回报告
+pub fn simulate(config: &SimulationConfig) -> Vec<DifficultyReport> {
+    let jobs: Vec<(usize, u64)> = (0..config.difficulties.len())
+        .flat_map(|difficulty| (0..config.games as u64).map(move |i| (difficulty, i)))
+        .collect();
+    let next_job = AtomicUsize::new(0);
+    let runs: Mutex<Vec<Vec<RunSummary>>> = Mutex::new(vec![Vec::new(); config.difficulties.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..config.threads.max(1) {
+            scope.spawn(|| {
+                while let Some(&(difficulty, i)) = jobs.get(next_job.fetch_add(1, Ordering::Relaxed)) {
+                    let seed = config.seed.wrapping_add(i);
+                    let run = run_bot_game(config.difficulties[difficulty].clone(), seed, config.max_ticks);
+                    runs.lock().unwrap()[difficulty].push(run);
+                }
+            });
+        }
+    });
+
+    let runs = runs.into_inner().unwrap();
+    config
+        .difficulties
+        .iter()
+        .zip(&runs)
+        .map(|(difficulty, runs)| DifficultyReport::from_runs(difficulty.clone(), runs))
+        .collect()
+}
+
+/// 把报告写成 JSON 或每个难度一行的 CSV
+pub fn format_reports(reports: &[DifficultyReport], format: ExportFormat) -> io::Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(reports).map_err(io::Error::other),
+        ExportFormat::Csv => Ok(to_csv(reports)),
+    }
+}
+
+fn to_csv(reports: &[DifficultyReport]) -> String {
+    let mut header = vec![
+        "difficulty", "games", "unfinished", "score_mean", "score_min", "score_p25", "score_median",
+        "score_p75", "score_p90", "score_max", "avg_survival_secs", "shields_consumed",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect::<Vec<_>>();
+    header.extend(DeathCause::all().iter().map(|cause| format!("deaths_{}", cause.name())));
+    for power_type in &PowerUpType::ALL {
+        let name = format!("{:?}", power_type).to_lowercase();
+        header.extend(["collected", "games_with", "avg_score_with", "avg_score_without"].map(|column| format!("{}_{}", name, column)));
+    }
+
+    let mut lines = vec![header.join(",")];
+    for report in reports {
+        let score = &report.score;
+        let mut row = vec![
+            format!("{:?}", report.difficulty),
+            report.games.to_string(),
+            report.unfinished.to_string(),
+            format!("{:.2}", score.mean),
+            score.min.to_string(),
+            score.p25.to_string(),
+            score.median.to_string(),
+            score.p75.to_string(),
+            score.p90.to_string(),
+            score.max.to_string(),
+            format!("{:.2}", report.avg_survival_secs),
+            report.shields_consumed.to_string(),
+        ];
+        row.extend(DeathCause::all().iter().map(|cause| report.deaths.get(&cause.name()).copied().unwrap_or(0).to_string()));
+        for impact in &report.powerups {
+            row.extend([
+                impact.collected.to_string(),
+                impact.games_with.to_string(),
+                format!("{:.2}", impact.avg_score_with),
+                format!("{:.2}", impact.avg_score_without),
+            ]);
+        }
+        lines.push(row.join(","));
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obstacle::ObstacleType;
+
+    fn run(score: i32, deaths: Vec<DeathCause>, powerups: Vec<PowerUpType>) -> RunSummary {
+        RunSummary { score, ticks: 600, finished: true, deaths, powerups, shields_consumed: 0 }
+    }
+
+    #[test]
+    fn score_distribution_uses_nearest_rank_percentiles() {
+        let distribution = ScoreDistribution::from_scores(vec![9, 0, 3, 1, 7, 5, 2, 8, 4, 6, 10]);
+        assert_eq!(
+            distribution,
+            ScoreDistribution { mean: 5.0, min: 0, p25: 3, median: 5, p75: 8, p90: 9, max: 10 }
+        );
+        assert_eq!(ScoreDistribution::from_scores(vec![4]).p90, 4);
+        assert_eq!(ScoreDistribution::from_scores(Vec::new()), ScoreDistribution::default());
+    }
+
+    #[test]
+    fn report_groups_deaths_and_power_up_impact() {
+        let mut unfinished = run(2, Vec::new(), Vec::new());
+        unfinished.finished = false;
+        let runs = [
+            run(10, vec![DeathCause::Floor, DeathCause::Floor], vec![PowerUpType::Shield, PowerUpType::Shield]),
+            run(4, vec![DeathCause::Obstacle(ObstacleType::Static)], Vec::new()),
+            unfinished,
+        ];
+        let report = DifficultyReport::from_runs(Difficulty::Normal, &runs);
+        assert_eq!((report.games, report.unfinished), (3, 1));
+        assert!((report.avg_survival_secs - 10.0).abs() < 1e-3);
+        assert_eq!(report.deaths[&DeathCause::Floor.name()], 2);
+        assert_eq!(report.deaths[&DeathCause::Obstacle(ObstacleType::Static).name()], 1);
+        assert_eq!(report.deaths.len(), DeathCause::all().len());
+
+        let shield = report.powerups.iter().find(|impact| impact.power_type == PowerUpType::Shield).unwrap();
+        assert_eq!((shield.collected, shield.games_with), (2, 1));
+        assert_eq!((shield.avg_score_with, shield.avg_score_without), (10.0, 3.0));
+        let extra_life = report.powerups.iter().find(|impact| impact.power_type == PowerUpType::ExtraLife).unwrap();
+        assert_eq!((extra_life.games_with, extra_life.avg_score_with), (0, 0.0));
+
+        let empty = DifficultyReport::from_runs(Difficulty::Easy, &[]);
+        assert_eq!((empty.games, empty.avg_survival_secs), (0, 0.0));
+    }
+
+    #[test]
+    fn csv_rows_match_the_header() {
+        let runs = [run(3, vec![DeathCause::Ceiling], vec![PowerUpType::DoubleScore])];
+        let reports = [
+            DifficultyReport::from_runs(Difficulty::Easy, &runs),
+            DifficultyReport::from_runs(Difficulty::Hard, &[]),
+        ];
+        let csv = to_csv(&reports);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let columns = lines[0].split(',').count();
+        for line in &lines[1..] {
+            assert_eq!(line.split(',').count(), columns, "{}", line);
+        }
+        assert!(lines[1].starts_with("Easy,1,0,3.00,3,"));
+    }
+
+    #[test]
+    fn fixed_seed_simulation_is_reproducible() {
+        let config = SimulationConfig {
+            difficulties: vec![Difficulty::Easy, Difficulty::Insane],
+            games: 4,
+            threads: 3,
+            max_ticks: 60 * 20,
+            seed: 7,
+        };
+        let reports = simulate(&config);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1].difficulty, Difficulty::Insane);
+        assert_eq!(simulate(&config), reports);
+        // 线程数只影响速度，不影响结果
+        assert_eq!(simulate(&SimulationConfig { threads: 1, ..config }), reports);
+    }
+}
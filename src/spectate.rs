@@ -0,0 +1,204 @@
+use crate::game::Game;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// 设置后游戏会在这个地址广播对局，例如 `127.0.0.1:7880`
+pub const SPECTATE_ENV: &str = "FLAPPY_SPECTATE_ADDR";
+/// 观战的默认地址
+pub const DEFAULT_SPECTATE_ADDR: &str = "127.0.0.1:7880";
+/// 观众超过这个时间收不下数据就断开，避免拖慢广播
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+/// 连接广播的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 把进行中的对局逐帧广播给观众，每帧是一行序列化的 `Game`
+///
+/// 观众只读，不能影响对局。发送在后台线程进行，观众卡顿不会拖慢游戏。
+pub struct Broadcaster {
+    addr: String,
+    viewers: Arc<Mutex<Vec<TcpStream>>>,
+    frames: Sender<String>,
+}
+
+impl Broadcaster {
+    pub fn bind(addr: &str) -> io::Result<Broadcaster> {
+        let listener = TcpListener::bind(addr)?;
+        // 端口为 0 时记录系统实际分配的地址
+        let addr = listener.local_addr()?.to_string();
+        let viewers: Arc<Mutex<Vec<TcpStream>>> = Arc::default();
+
+        let accepted = viewers.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if stream.set_nodelay(true).is_ok() && stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok() {
+                    accepted.lock().unwrap().push(stream);
+                }
+            }
+        });
+
+        let (frames, pending) = mpsc::channel::<String>();
+        let receivers = viewers.clone();
+        thread::spawn(move || {
+            for frame in pending {
+                receivers.lock().unwrap().retain_mut(|viewer| viewer.write_all(frame.as_bytes()).is_ok());
+            }
+        });
+
+        Ok(Broadcaster {
+            addr,
+            viewers,
+            frames,
+        })
+    }
+
+    /// 设置了环境变量时开始广播
+    pub fn from_env() -> Option<Self> {
+        let addr = std::env::var(SPECTATE_ENV).ok()?;
+        match Self::bind(&addr) {
+            Ok(broadcaster) => Some(broadcaster),
+            Err(err) => {
+                eprintln!("Failed to broadcast on {}={}: {}", SPECTATE_ENV, addr, err);
+                None
+            }
+        }
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// 正在观战的人数
+    pub fn viewers(&self) -> usize {
+        self.viewers.lock().unwrap().len()
+    }
+
+    /// 广播一个逻辑帧之后的对局状态，没有观众时什么也不做
+    pub fn publish(&self, game: &Game) {
+        if self.viewers() == 0 {
+            return;
+        }
+        if let Ok(mut frame) = serde_json::to_string(game) {
+            frame.push('\n');
+            let _ = self.frames.send(frame);
+        }
+    }
+}
+
+/// 连接到正在广播的游戏
+pub struct Spectator {
+    frames: Receiver<io::Result<Game>>,
+}
+
+impl Spectator {
+    /// 在后台线程连接并接收，不会阻塞调用方；连接失败时 `latest` 返回错误
+    pub fn connect(addr: &str) -> Spectator {
+        let addr = addr.to_string();
+        let (sender, frames) = mpsc::channel();
+        thread::spawn(move || {
+            let stream = addr.to_socket_addrs().and_then(|mut addrs| {
+                let addr = addrs
+                    .next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "could not resolve address"))?;
+                TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+            });
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    let _ = sender.send(Err(err));
+                    return;
+                }
+            };
+            let reader = BufReader::new(stream);
+            for line in reader.lines() {
+                let frame = line.and_then(|line| {
+                    serde_json::from_str(&line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                });
+                let failed = frame.is_err();
+                if sender.send(frame).is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Spectator { frames }
+    }
+
+    /// 取出收到的最新一帧，中间的帧直接丢弃；连接断开时返回错误
+    pub fn latest(&self) -> io::Result<Option<Game>> {
+        let mut latest = None;
+        loop {
+            match self.frames.try_recv() {
+                Ok(frame) => latest = Some(frame?),
+                Err(TryRecvError::Empty) => return Ok(latest),
+                Err(TryRecvError::Disconnected) => {
+                    return match latest {
+                        Some(game) => Ok(Some(game)),
+                        None => Err(io::Error::new(io::ErrorKind::ConnectionAborted, "broadcast ended")),
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+    use crate::difficulty::Difficulty;
+    use std::time::Instant;
+
+    /// 等到 `f` 返回 `Some`，最多五秒
+    fn wait_for<T>(mut f: impl FnMut() -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(value) = f() {
+                return value;
+            }
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn broadcast_frames_reach_the_spectator() {
+        let broadcaster = Broadcaster::bind("127.0.0.1:0").unwrap();
+        let spectator = Spectator::connect(broadcaster.addr());
+        wait_for(|| (broadcaster.viewers() == 1).then_some(()));
+
+        let mut game = Game::new(Difficulty::Hard, 4);
+        let bot = Bot::new(0);
+        for _ in 0..300 {
+            game.tick(bot.input(&game));
+        }
+        broadcaster.publish(&game);
+
+        let received = wait_for(|| spectator.latest().unwrap());
+        assert_eq!(serde_json::to_string(&received).unwrap(), serde_json::to_string(&game).unwrap());
+
+        // 观众只保留最新的一帧
+        game.tick(bot.input(&game));
+        broadcaster.publish(&game);
+        game.tick(bot.input(&game));
+        broadcaster.publish(&game);
+        let latest = wait_for(|| spectator.latest().unwrap().filter(|frame| frame.ticks == game.ticks));
+        assert_eq!(serde_json::to_string(&latest).unwrap(), serde_json::to_string(&game).unwrap());
+    }
+
+    #[test]
+    fn failed_connect_is_reported_without_blocking() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let started = Instant::now();
+        let spectator = Spectator::connect(&addr);
+        assert!(started.elapsed() < Duration::from_millis(100));
+        wait_for(|| spectator.latest().err());
+        let spectator = Spectator::connect("not an address");
+        wait_for(|| spectator.latest().err());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::difficulty::Difficulty;
+use crate::game::GameEvent;
+use crate::obstacle::ObstacleType;
+use crate::powerup::PowerUpType;
+use crate::storage::JsonStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 统计文件名，和分数文件放在同一个玩家目录
+const STATS_FILE: &str = "stats.json";
+
+/// 单局游戏中累计的数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    pub flaps: u64,
+    pub obstacles_passed: HashMap<ObstacleType, u64>,
+    pub powerups_collected: HashMap<PowerUpType, u64>,
+    pub shields_consumed: u64,
+    pub lives_lost: u64,
+    #[serde(default)]
+    pub fireballs: u64,
+    #[serde(default)]
+    pub targets_destroyed: u64,
+}
+
+impl RunStats {
+    pub fn record(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Flapped => self.flaps += 1,
+            GameEvent::ObstaclePassed(obstacle_type) => {
+                *self.obstacles_passed.entry(obstacle_type.clone()).or_default() += 1;
+            }
+            GameEvent::PowerUpCollected(power_type) => {
+                *self.powerups_collected.entry(power_type.clone()).or_default() += 1;
+            }
+            GameEvent::ShieldConsumed => self.shields_consumed += 1,
+            GameEvent::LifeLost(_) => self.lives_lost += 1,
+            GameEvent::Fired => self.fireballs += 1,
+            GameEvent::TargetDestroyed(_) => self.targets_destroyed += 1,
+        }
+    }
+}
+
+/// 某个难度下的成绩汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DifficultyStats {
+    pub runs: u64,
+    pub total_score: i64,
+    pub best_score: i32,
+}
+
+impl DifficultyStats {
+    pub fn average_score(&self) -> f32 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.total_score as f32 / self.runs as f32
+        }
+    }
+}
+
+/// 所有已完成对局的累计统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub runs: u64,
+    pub flaps: u64,
+    pub obstacles_passed: HashMap<ObstacleType, u64>,
+    pub powerups_collected: HashMap<PowerUpType, u64>,
+    pub shields_consumed: u64,
+    pub lives_lost: u64,
+    pub fireballs: u64,
+    pub targets_destroyed: u64,
+    pub longest_combo: i32,
+    /// 总游戏时间（毫秒）
+    pub play_time_ms: u64,
+    pub per_difficulty: HashMap<Difficulty, DifficultyStats>,
+}
+
+impl LifetimeStats {
+    /// 把一局结束的游戏计入统计
+    pub fn record_run(&mut self, run: &RunStats, difficulty: &Difficulty, score: i32, best_combo: i32, play_time_ms: u64) {
+        self.runs += 1;
+        self.flaps += run.flaps;
+        for (obstacle_type, count) in &run.obstacles_passed {
+            *self.obstacles_passed.entry(obstacle_type.clone()).or_default() += count;
+        }
+        for (power_type, count) in &run.powerups_collected {
+            *self.powerups_collected.entry(power_type.clone()).or_default() += count;
+        }
+        self.shields_consumed += run.shields_consumed;
+        self.lives_lost += run.lives_lost;
+        self.fireballs += run.fireballs;
+        self.targets_destroyed += run.targets_destroyed;
+        self.longest_combo = self.longest_combo.max(best_combo);
+        self.play_time_ms += play_time_ms;
+
+        let stats = self.per_difficulty.entry(difficulty.clone()).or_default();
+        stats.runs += 1;
+        stats.total_score += score as i64;
+        stats.best_score = stats.best_score.max(score);
+    }
+}
+
+/// 统计数据的持久化存储
+pub type StatsStore = JsonStore<LifetimeStats>;
+
+impl StatsStore {
+    /// 打开 `dir` 目录下的文件
+    pub fn in_dir(dir: &Path) -> Self {
+        Self::new(dir.join(STATS_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fireball::Target;
+    use crate::game::DeathCause;
+    use crate::storage::temp_dir;
+
+    fn run_of(events: &[GameEvent]) -> RunStats {
+        let mut run = RunStats::default();
+        for event in events {
+            run.record(event);
+        }
+        run
+    }
+
+    #[test]
+    fn run_counts_every_event() {
+        let run = run_of(&[
+            GameEvent::Flapped,
+            GameEvent::Flapped,
+            GameEvent::ObstaclePassed(ObstacleType::Static),
+            GameEvent::ObstaclePassed(ObstacleType::Laser),
+            GameEvent::ObstaclePassed(ObstacleType::Static),
+            GameEvent::PowerUpCollected(PowerUpType::Shield),
+            GameEvent::ShieldConsumed,
+            GameEvent::Fired,
+            GameEvent::TargetDestroyed(Target::Obstacle),
+            GameEvent::LifeLost(DeathCause::Floor),
+        ]);
+        assert_eq!(run.flaps, 2);
+        assert_eq!(run.obstacles_passed[&ObstacleType::Static], 2);
+        assert_eq!(run.obstacles_passed[&ObstacleType::Laser], 1);
+        assert_eq!(run.powerups_collected[&PowerUpType::Shield], 1);
+        assert!(!run.powerups_collected.contains_key(&PowerUpType::ExtraLife));
+        assert_eq!((run.shields_consumed, run.fireballs, run.targets_destroyed, run.lives_lost), (1, 1, 1, 1));
+    }
+
+    #[test]
+    fn lifetime_adds_up_runs_per_difficulty() {
+        let first = run_of(&[GameEvent::Flapped, GameEvent::ObstaclePassed(ObstacleType::Static)]);
+        let second = run_of(&[GameEvent::Flapped, GameEvent::ObstaclePassed(ObstacleType::Static), GameEvent::Fired]);
+        let mut stats = LifetimeStats::default();
+        stats.record_run(&first, &Difficulty::Normal, 10, 4, 1000);
+        stats.record_run(&second, &Difficulty::Normal, 20, 2, 500);
+        stats.record_run(&RunStats::default(), &Difficulty::Hard, 3, 0, 250);
+
+        assert_eq!((stats.runs, stats.flaps, stats.fireballs), (3, 2, 1));
+        assert_eq!(stats.obstacles_passed[&ObstacleType::Static], 2);
+        assert_eq!(stats.longest_combo, 4);
+        assert_eq!(stats.play_time_ms, 1750);
+        let normal = &stats.per_difficulty[&Difficulty::Normal];
+        assert_eq!((normal.runs, normal.total_score, normal.best_score), (2, 30, 20));
+        assert_eq!(normal.average_score(), 15.0);
+        assert_eq!(stats.per_difficulty[&Difficulty::Hard].best_score, 3);
+        assert_eq!(DifficultyStats::default().average_score(), 0.0);
+    }
+
+    #[test]
+    fn store_keeps_stats_between_sessions() {
+        let dir = temp_dir("stats-store");
+        let run = run_of(&[GameEvent::Flapped]);
+        StatsStore::in_dir(&dir)
+            .update(|stats| stats.record_run(&run, &Difficulty::Easy, 5, 1, 100))
+            .unwrap();
+        let stats = StatsStore::in_dir(&dir).load().unwrap();
+        assert_eq!((stats.runs, stats.flaps), (1, 1));
+        assert_eq!(stats.per_difficulty[&Difficulty::Easy].best_score, 5);
+    }
+}
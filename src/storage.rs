@@ -0,0 +1,165 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 数据目录名
+const APP_DIR: &str = "flappy_dragon";
+
+/// 每个用户的数据目录（Linux 下遵循 XDG 规范）
+pub fn data_dir() -> PathBuf {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    // 找不到用户目录时退回到当前目录
+    base.unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR)
+}
+
+/// 当前时间（Unix 秒）
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 原子写入：写临时文件并同步到磁盘后再重命名覆盖
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+/// 获取 `path` 对应的独占锁（同目录下的 `.lock` 文件），锁在返回的文件句柄被释放时解除
+pub fn lock_file(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))?;
+    lock_file.lock()?;
+    Ok(lock_file)
+}
+
+/// 以 JSON 文件保存的数据，写入方式与分数文件相同
+pub struct JsonStore<T> {
+    path: PathBuf,
+    _data: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonStore<T> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonStore {
+            path: path.into(),
+            _data: PhantomData,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> io::Result<T> {
+        let _lock = lock_file(&self.path)?;
+        self.read()
+    }
+
+    /// 在锁内读取、修改并写回
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> io::Result<R> {
+        let _lock = lock_file(&self.path)?;
+        let mut data = self.read()?;
+        let result = f(&mut data);
+        let content = serde_json::to_string_pretty(&data).map_err(io::Error::other)?;
+        write_atomic(&self.path, content.as_bytes())?;
+        Ok(result)
+    }
+
+    fn read(&self) -> io::Result<T> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(data) => Ok(data),
+                Err(err) => {
+                    // 损坏的文件备份后重新开始
+                    eprintln!("Corrupted data in {}: {}", self.path.display(), err);
+                    fs::copy(&self.path, self.path.with_extension("json.bak"))?;
+                    Ok(T::default())
+                }
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// 测试用的空临时目录，每次调用都不同
+pub fn temp_dir(name: &str) -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = env::temp_dir().join(format!(
+        "flappy-{}-{}-{}",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create temp dir");
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_atomic_leaves_no_temp_file() {
+        let dir = temp_dir("storage-atomic");
+        let path = dir.join("nested/data.txt");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec!["data.txt"]);
+    }
+
+    #[test]
+    fn corrupted_json_is_backed_up() {
+        let dir = temp_dir("storage-json");
+        let store: JsonStore<Vec<u32>> = JsonStore::new(dir.join("data.json"));
+        fs::write(store.path(), "[1, 2,").unwrap();
+
+        assert!(store.load().unwrap().is_empty());
+        assert_eq!(fs::read_to_string(dir.join("data.json.bak")).unwrap(), "[1, 2,");
+
+        // 之后的写入从默认值重新开始
+        store.update(|data| data.push(3)).unwrap();
+        assert_eq!(store.load().unwrap(), vec![3]);
+        assert!(!dir.join("data.json.tmp").exists());
+    }
+}
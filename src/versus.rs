@@ -0,0 +1,68 @@
+use bracket_lib::prelude::*;
+use flappy::game::{player_color, Dragon};
+use flappy::input::Action;
+use crate::menu::MenuItem;
+use crate::online::NetSession;
+use crate::State;
+
+impl State {
+    pub(crate) fn is_two_player(&self) -> bool {
+        self.game.dragons.len() > 1
+    }
+
+    /// 双人对局或分屏竞速结束后比较两名玩家的成绩
+    pub(crate) fn results(&mut self, ctx: &mut BTerm) {
+        self.audio.stop_bgm();
+        ctx.cls();
+        self.set_background(ctx, "assets/end_bg.png");
+        ctx.print_centered(6, "Results");
+        ctx.print_centered(7, format!("Difficulty: {:?}", self.game.difficulty));
+
+        // 分屏竞速时两名玩家各有一局，先失去所有生命的一方输；同屏对战比较分数
+        let (dragons, winners): (Vec<&Dragon>, Vec<usize>) = match &self.race {
+            Some(rival) => {
+                let games = [&self.game, rival];
+                let dragons = games.iter().map(|game| &game.dragons[0]).collect();
+                let survivors = (0..games.len()).filter(|&i| !games[i].is_over()).collect();
+                (dragons, survivors)
+            }
+            None => {
+                let dragons: Vec<&Dragon> = self.game.dragons.iter().collect();
+                let best = dragons.iter().map(|d| d.score).max().unwrap_or(0);
+                let winners = (0..dragons.len()).filter(|&i| dragons[i].score == best).collect();
+                (dragons, winners)
+            }
+        };
+        for (i, dragon) in dragons.iter().enumerate() {
+            let x = 20 + i as i32 * 30;
+            let you = if self.net.as_ref().and_then(NetSession::player) == Some(i) { " (You)" } else { "" };
+            ctx.print_color(x, 10, player_color(i), BLACK, format!("Player {}{}", i + 1, you));
+            ctx.print(x, 12, format!("Score: {}", dragon.score));
+            ctx.print(x, 13, format!("Best combo: {}", dragon.best_combo));
+        }
+
+        let verdict = match winners.as_slice() {
+            [winner] => format!("Player {} wins!", winner + 1),
+            _ => "It's a draw!".to_string(),
+        };
+        ctx.print_centered(16, verdict);
+
+        let items = [
+            MenuItem::new(35, 19, VirtualKeyCode::P, "(P) Play Again"),
+            MenuItem::new(35, 21, VirtualKeyCode::M, "(M) Main Menu"),
+            MenuItem::new(35, 23, VirtualKeyCode::Q, "(Q) Quit Game"),
+        ];
+        if ctx.key.is_some_and(|key| self.key_bindings.matches(Action::Back, key)) {
+            self.quit_to_menu();
+        } else if let Some(key) = self.menu.show(ctx, &items, &self.key_bindings) {
+            match key {
+                VirtualKeyCode::P if self.net.is_some() => self.start_online(),
+                VirtualKeyCode::P if self.race.is_some() => self.start_race(),
+                VirtualKeyCode::P => self.start_game(2),
+                VirtualKeyCode::M => self.quit_to_menu(),
+                VirtualKeyCode::Q => ctx.quitting = true,
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use bracket_lib::prelude::*;
+use flappy::game::Game;
+use flappy::input::Action;
+use flappy::spectate::Spectator;
+use crate::{GameMode, State};
+
+/// 连接断开后隔多久重连（毫秒）
+const RETRY_MS: f32 = 2000.0;
+
+/// 观战界面：只读地显示另一个游戏实例广播的对局，断开后自动重连
+pub struct Watch {
+    addr: String,
+    spectator: Option<Spectator>,
+    /// 当前连接是否已经收到过画面，用来区分连不上和连接断开
+    connected: bool,
+    /// 最近收到的一帧
+    game: Option<Game>,
+    retry_ms: f32,
+    message: Option<String>,
+}
+
+impl State {
+    pub(crate) fn start_watching(&mut self, addr: String) {
+        self.watch = Some(Watch {
+            addr,
+            spectator: None,
+            connected: false,
+            game: None,
+            retry_ms: 0.0,
+            message: None,
+        });
+        self.mode = GameMode::Spectate;
+    }
+
+    pub(crate) fn spectate(&mut self, ctx: &mut BTerm) {
+        ctx.cls();
+        self.set_background(ctx, "assets/game_bg.png");
+
+        let Some(watch) = self.watch.as_mut() else {
+            self.mode = GameMode::Menu;
+            return;
+        };
+        if watch.spectator.is_none() {
+            watch.retry_ms -= ctx.frame_time_ms;
+            if watch.retry_ms <= 0.0 {
+                // 在后台连接，连接失败会在下面作为错误收到
+                watch.spectator = Some(Spectator::connect(&watch.addr));
+                watch.connected = false;
+            }
+        }
+        if let Some(spectator) = &watch.spectator {
+            match spectator.latest() {
+                Ok(Some(game)) => {
+                    watch.game = Some(game);
+                    watch.connected = true;
+                    watch.message = None;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    watch.spectator = None;
+                    let prefix = if watch.connected { "Disconnected" } else { "Can't connect" };
+                    watch.message = Some(format!("{}: {}", prefix, err));
+                    watch.retry_ms = RETRY_MS;
+                }
+            }
+        }
+
+        match &mut watch.game {
+            Some(game) => {
+                game.render_background(ctx);
+                game.render(ctx);
+                if game.is_over() {
+                    ctx.print_centered(20, "GAME OVER");
+                }
+            }
+            None => ctx.print_centered(20, "Waiting for a game to start..."),
+        }
+        if let Some(message) = &watch.message {
+            ctx.print_color_centered(22, RED, BLACK, message);
+        }
+        let addr = watch.addr.clone();
+        ctx.print(0, 0, format!("SPECTATING {} | Back {}", addr, self.key_label(Action::Back)));
+
+        if self.back_pressed(ctx) {
+            self.watch = None;
+            self.mode = GameMode::Menu;
+        }
+    }
+}
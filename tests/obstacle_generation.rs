@@ -0,0 +1,141 @@
+use bracket_lib::prelude::RandomNumberGenerator;
+use flappy::biome::Biome;
+use flappy::bot::Bot;
+use flappy::difficulty::Difficulty;
+use flappy::game::Game;
+use flappy::obstacle::{Obstacle, ObstacleType, Reach};
+use flappy::player::Player;
+use flappy::{FRAME_DURATION, SCREEN_HEIGHT, SCREEN_WIDTH, TICK_DURATION};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 空隙最小为 4，中心上下各两格都是安全的
+const GAP_HALF: i32 = 2;
+const START_Y: i32 = SCREEN_HEIGHT / 2;
+
+/// 按 `Game::tick_players` 的顺序穷举扇动、下冲和不动，返回每个逻辑帧后能到达的所有高度
+fn reachable_heights(velocity: f32, frame_time: f32, effective_frame_time: f32) -> Vec<BTreeSet<i32>> {
+    let mut player = Player::new(5, START_Y);
+    player.flap();
+    while player.get_status().velocity < velocity {
+        player.move_down();
+    }
+    player.y = START_Y;
+
+    let mut states = BTreeMap::new();
+    states.insert((player.y, 0), (player, frame_time));
+    let mut heights = vec![BTreeSet::from([START_Y])];
+    for _ in 0..SCREEN_WIDTH {
+        let mut next = BTreeMap::new();
+        for (player, frame_time) in states.values() {
+            for input in 0..3 {
+                let (mut player, mut frame_time) = (player.clone(), frame_time + effective_frame_time);
+                if frame_time > FRAME_DURATION {
+                    frame_time = 0.0;
+                    player.gravity_and_move();
+                }
+                match input {
+                    1 => player.flap(),
+                    2 => player.move_down(),
+                    _ => {}
+                }
+                if player.y <= SCREEN_HEIGHT {
+                    let velocity = (player.get_status().velocity * 1000.0).round() as i32;
+                    next.insert((player.y, velocity), (player, frame_time));
+                }
+            }
+        }
+        states = next;
+        heights.push(states.keys().map(|&(y, _)| y).collect());
+    }
+    heights
+}
+
+#[test]
+fn reach_is_within_physics() {
+    for effective_frame_time in [TICK_DURATION, TICK_DURATION * 0.5] {
+        let ticks_per_move = (FRAME_DURATION / effective_frame_time) as i32 + 1;
+        for phase in 0..ticks_per_move {
+            for velocity in [-2.0, 0.0, 1.5] {
+                let heights = reachable_heights(velocity, phase as f32 * effective_frame_time, effective_frame_time);
+                for (ticks, reachable) in heights.iter().enumerate() {
+                    let reach = Reach::from_player(START_Y, ticks as i32, ticks_per_move);
+                    for y in reach.top..=reach.bottom {
+                        assert!(
+                            reachable.range(y - GAP_HALF..=y + GAP_HALF).next().is_some(),
+                            "y {} not reachable after {} ticks (velocity {}, phase {}, {} ticks per move)",
+                            y, ticks, velocity, phase, ticks_per_move
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn generated_obstacles_are_reachable() {
+    for seed in 0..3000 {
+        let difficulty = Difficulty::ALL[seed as usize % Difficulty::ALL.len()].clone();
+        let mut game = Game::new(difficulty, seed);
+        let bot = Bot::new(0);
+        let mut spawned = true;
+        while !game.is_over() && game.ticks < 1500 {
+            if spawned {
+                let dragon = &game.dragons[0];
+                let obstacle = &game.obstacle;
+                let ticks = (obstacle.x - dragon.player.x) / Obstacle::step(&game.settings);
+                let reach = Reach::from_player(dragon.player.y, ticks, dragon.ticks_per_move());
+                // 移动障碍物的空隙途中最多偏离 5 格
+                let drift = if obstacle.obstacle_type == ObstacleType::Moving { SCREEN_WIDTH / 20 + 1 } else { 0 };
+                assert!(
+                    reach.contains(obstacle.gap_y - drift) && reach.contains(obstacle.gap_y + drift),
+                    "seed {} tick {}: gap {} ({:?}) outside {:?}",
+                    seed, game.ticks, obstacle.gap_y, obstacle.obstacle_type, reach
+                );
+                assert_open_on_arrival(obstacle, ticks);
+            }
+            game.tick(bot.input(&game));
+            spawned = game.obstacle.x == SCREEN_WIDTH;
+        }
+    }
+}
+
+/// 激光和压碎机在龙到达时空隙完全张开，尖刺墙至少让出整个空隙
+fn assert_open_on_arrival(obstacle: &Obstacle, ticks: i32) {
+    let half_size = obstacle.size / 2;
+    let (top, bottom) = (obstacle.gap_y - half_size, obstacle.gap_y + half_size);
+    let open = obstacle.gap_after(ticks);
+    assert!(
+        open.is_some_and(|(open_top, open_bottom)| open_top <= top && open_bottom >= bottom),
+        "{:?} gap {}..={} is {:?} after {} ticks",
+        obstacle.obstacle_type, top, bottom, open, ticks
+    );
+}
+
+#[test]
+fn generated_obstacle_types_are_passable() {
+    let mut random = RandomNumberGenerator::seeded(17);
+    let mut seen = BTreeSet::new();
+    for i in 0..20000 {
+        let biome = Biome::ALL[i % Biome::ALL.len()];
+        let (y, x) = (random.range(0, SCREEN_HEIGHT), random.range(2, 30));
+        let ticks = SCREEN_WIDTH - x;
+        let ticks_per_move = random.range(2, 5);
+        let reach = Reach::from_player(y, ticks, ticks_per_move);
+        let obstacle = Obstacle::new(SCREEN_WIDTH, 30, &mut random, reach, biome, &[ticks]);
+        seen.insert(format!("{:?}", obstacle.obstacle_type));
+
+        let drift = if obstacle.obstacle_type == ObstacleType::Moving { SCREEN_WIDTH / 20 + 1 } else { 0 };
+        let clamped = Reach { top: reach.top.max(10), bottom: reach.bottom.min(39) };
+        // 可达范围和随机范围有交集时空隙一定在可达范围内
+        if clamped.top + drift <= clamped.bottom - drift {
+            assert!(
+                reach.contains(obstacle.gap_y - drift) && reach.contains(obstacle.gap_y + drift),
+                "{:?} gap {} outside {:?}",
+                obstacle.obstacle_type, obstacle.gap_y, reach
+            );
+        }
+        assert_open_on_arrival(&obstacle, ticks);
+    }
+    assert_eq!(seen.len(), ObstacleType::ALL.len(), "{:?}", seen);
+}